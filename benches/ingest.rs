@@ -0,0 +1,86 @@
+//! Ingest throughput against the number of signature verification workers.
+//!
+//! `cargo bench --bench ingest` reports records/s per worker count; with verification
+//! off the aggregator lock it should grow with the workers up to the number of cores.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use rofl_enclave::aggregator::DataAggregator;
+use rofl_enclave::config::Config;
+use rofl_enclave::crypto::CryptoService;
+use rofl_enclave::ingest::VerifierPool;
+use rofl_enclave::models::{MeterRecord, RecordEncoding, SignatureScheme};
+use rofl_enclave::registry::MeterRegistry;
+use rofl_enclave::signer::EnclaveSigner;
+
+const RECORDS: usize = 1024;
+const METERS: usize = 64;
+
+fn signed_records() -> Vec<(MeterRecord, String)> {
+    let crypto = CryptoService::new();
+    let meters: Vec<EnclaveSigner> = (1..=METERS)
+        .map(|i| EnclaveSigner::from_hex(&format!("{:064x}", i)).unwrap())
+        .collect();
+
+    (0..RECORDS).map(|i| {
+        let record = MeterRecord {
+            meter_id: format!("meter_{:03}", i % METERS),
+            timestamp: 1640995200000 + i as i64,
+            kwh_delta: 0.5,
+            nonce: format!("{:032x}", i),
+            encoding: RecordEncoding::V1,
+        };
+        let digest = crypto.meter_signing_digest(&record, SignatureScheme::Eip712).unwrap();
+        let signature = hex::encode(meters[i % METERS].sign_digest(&digest));
+        (record, signature)
+    }).collect()
+}
+
+fn bench_ingest(c: &mut Criterion) {
+    std::env::set_var("OUTPUT_DIR", std::env::temp_dir().join("rofl-bench"));
+    std::env::set_var("MAX_RECORDS_PER_WINDOW", (RECORDS * 2).to_string());
+    let config = Config::from_env().unwrap();
+    let records = signed_records();
+
+    let cores = std::thread::available_parallelism().map(|cores| cores.get()).unwrap_or(1);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(cores)
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("ingest");
+    group.throughput(Throughput::Elements(RECORDS as u64));
+    group.sample_size(10);
+
+    let mut workers = 1;
+    while workers <= cores {
+        let verifier = VerifierPool::new(MeterRegistry::default(), workers, true);
+        group.bench_with_input(BenchmarkId::from_parameter(workers), &verifier, |b, verifier| {
+            b.to_async(&runtime).iter_batched(
+                || (Arc::new(Mutex::new(DataAggregator::new(config.clone()))), records.clone()),
+                |(aggregator, records)| async move {
+                    let tasks: Vec<_> = records.into_iter().map(|(record, signature)| {
+                        let (aggregator, verifier) = (aggregator.clone(), verifier.clone());
+                        tokio::spawn(async move {
+                            let verified = verifier.verify(record, signature, SignatureScheme::Eip712).await?;
+                            aggregator.lock().await.insert_record(verified).await
+                        })
+                    }).collect();
+
+                    for task in tasks {
+                        task.await.unwrap().unwrap();
+                    }
+                },
+                BatchSize::PerIteration,
+            );
+        });
+        workers *= 2;
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_ingest);
+criterion_main!(benches);
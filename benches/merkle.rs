@@ -0,0 +1,53 @@
+//! Merkle tree construction and proofs for large aggregation windows.
+//!
+//! `cargo bench --bench merkle` reports leaves/s for building a v2 tree from the hex record
+//! hashes a window stores and from raw nodes, plus one proof round trip per tree size.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sha3::{Digest, Keccak256};
+
+use rofl_enclave::merkle::{MerkleBuilder, MerkleTree, Node};
+use rofl_enclave::models::HashScheme;
+
+const SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+
+fn leaves(count: usize) -> Vec<Node> {
+    (0..count as u64).map(|i| Keccak256::digest(i.to_be_bytes()).into()).collect()
+}
+
+fn bench_merkle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merkle");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let nodes = leaves(size);
+        let hashes: Vec<String> = nodes.iter().map(hex::encode).collect();
+        group.throughput(Throughput::Elements(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("build_hex", size), &hashes, |b, hashes| {
+            b.iter(|| MerkleTree::with_scheme(hashes, HashScheme::V2).unwrap());
+        });
+
+        group.bench_with_input(BenchmarkId::new("build_nodes", size), &nodes, |b, nodes| {
+            b.iter(|| {
+                let mut builder = MerkleBuilder::with_capacity(HashScheme::V2, nodes.len());
+                nodes.iter().for_each(|leaf| builder.push(*leaf));
+                builder.finish().unwrap()
+            });
+        });
+
+        let tree = MerkleTree::with_scheme(&hashes, HashScheme::V2).unwrap();
+        let index = size / 3;
+        group.throughput(Throughput::Elements(1));
+        group.bench_with_input(BenchmarkId::new("proof", size), &tree, |b, tree| {
+            b.iter(|| {
+                let proof = tree.generate_proof(index).unwrap();
+                assert!(MerkleTree::verify_scheme_proof(HashScheme::V2, &hashes[index], &proof, &tree.root, index, hashes.len()).unwrap());
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_merkle);
+criterion_main!(benches);
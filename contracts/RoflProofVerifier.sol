@@ -0,0 +1,92 @@
+// FILE: contracts/RoflProofVerifier.sol
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/access/AccessControl.sol";
+import "@openzeppelin/contracts/utils/cryptography/ECDSA.sol";
+import "@openzeppelin/contracts/utils/cryptography/EIP712.sol";
+
+/**
+ * @title RoflProofVerifier
+ * @dev Verifies aggregation proofs signed by the ROFL enclave (EIP-712)
+ * @notice Matches `GET /api/v1/proofs/{id}/evm`; the enclave signs for this contract's address and chain ID
+ */
+contract RoflProofVerifier is EIP712, AccessControl {
+    // ==================== Constants ====================
+
+    bytes32 public constant ENCLAVE_MANAGER_ROLE = keccak256("ENCLAVE_MANAGER_ROLE");
+
+    bytes32 public constant ROFL_PROOF_TYPEHASH = keccak256(
+        "RoflProof(bytes16 proofId,bytes32 merkleRoot,uint256 aggregateMicroKwh,uint64 windowStart,uint64 windowEnd,uint32 recordCount,bytes32 sumRoot,uint8 hashScheme)"
+    );
+
+    // ==================== Types ====================
+
+    struct RoflProof {
+        bytes16 proofId;
+        bytes32 merkleRoot;
+        uint256 aggregateMicroKwh;
+        uint64 windowStart;
+        uint64 windowEnd;
+        uint32 recordCount;
+        // Merkle sum tree root over the records and their micro-kWh total (zero for legacy proofs)
+        bytes32 sumRoot;
+        // N of the `greenshare.merkle.vN` scheme the roots were computed with
+        uint8 hashScheme;
+    }
+
+    // ==================== State Variables ====================
+
+    /// @notice Enclave signing addresses whose proofs are accepted
+    mapping(address => bool) public trustedEnclaves;
+
+    // ==================== Events ====================
+
+    event EnclaveTrustUpdated(address indexed enclave, bool trusted);
+
+    // ==================== Constructor ====================
+
+    constructor(address admin, address enclave) EIP712("GreenShare ROFL", "2") {
+        _grantRole(DEFAULT_ADMIN_ROLE, admin);
+        _grantRole(ENCLAVE_MANAGER_ROLE, admin);
+
+        trustedEnclaves[enclave] = true;
+        emit EnclaveTrustUpdated(enclave, true);
+    }
+
+    // ==================== Verification ====================
+
+    /// @notice EIP-712 digest of a proof under this contract's domain
+    function hashProof(RoflProof memory proof) public view returns (bytes32) {
+        return _hashTypedDataV4(keccak256(abi.encode(ROFL_PROOF_TYPEHASH, proof)));
+    }
+
+    /// @notice Signer of a proof (reverts on malformed signatures)
+    function recoverSigner(RoflProof memory proof, bytes calldata signature) public view returns (address) {
+        return ECDSA.recover(hashProof(proof), signature);
+    }
+
+    /// @notice Whether a proof was signed by a trusted enclave
+    function verifyProof(RoflProof calldata proof, bytes calldata signature) external view returns (bool) {
+        return trustedEnclaves[recoverSigner(proof, signature)];
+    }
+
+    /// @notice Same as `verifyProof` for the `encoded` field of the enclave API
+    function verifyEncodedProof(bytes calldata encoded, bytes calldata signature) external view returns (bool) {
+        RoflProof memory proof = abi.decode(encoded, (RoflProof));
+        return trustedEnclaves[recoverSigner(proof, signature)];
+    }
+
+    /// @notice EIP-712 domain separator for this chain
+    function domainSeparator() external view returns (bytes32) {
+        return _domainSeparatorV4();
+    }
+
+    // ==================== Admin ====================
+
+    function setEnclaveTrust(address enclave, bool trusted) external onlyRole(ENCLAVE_MANAGER_ROLE) {
+        require(enclave != address(0), "Invalid enclave");
+        trustedEnclaves[enclave] = trusted;
+        emit EnclaveTrustUpdated(enclave, trusted);
+    }
+}
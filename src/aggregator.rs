@@ -1,19 +1,31 @@
 // FILE: src/aggregator.rs
 use chrono::{DateTime, Utc, Duration};
-use log::{info, warn, error, debug};
-use std::collections::HashMap;
+use log::{info, warn, debug};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use uuid::Uuid;
 use tokio::fs;
 
 use crate::config::Config;
-use crate::models::{MeterRecord, VerifiedRecord, AggregationWindow, ProofData, WindowStatus};
-use crate::crypto::CryptoService;
+use crate::models::{HashScheme, VerifiedRecord, AggregationWindow, ProofData, WindowStatus};
+use crate::crypto::{self, CryptoService};
 use crate::merkle::MerkleTree;
+use crate::sum_tree::MerkleSumTree;
+use crate::mmr::Mmr;
+use crate::state_tree::{MeterState, StateTree};
+use crate::seal::SealResponse;
+use crate::reconcile::ReconciliationReport;
 
 pub struct DataAggregator {
     config: Config,
     crypto: CryptoService,
     current_window: Option<AggregationWindow>,
+    /// Accumulator of finalized proof leaf digests, loaded on first use
+    mmr: Option<Mmr>,
+    /// Cumulative state of every meter, loaded on first use
+    meter_states: Option<StateTree>,
+    /// Every stored proof ordered by window start, loaded on first use
+    proofs: Option<Arc<Vec<ProofData>>>,
     stats: AggregatorStats,
 }
 
@@ -33,41 +45,30 @@ impl DataAggregator {
             config,
             crypto: CryptoService::new(),
             current_window: None,
+            mmr: None,
+            meter_states: None,
+            proofs: None,
             stats: AggregatorStats::default(),
         }
     }
 
-    /// Process incoming meter data
-    pub async fn process_record(&mut self, record: MeterRecord, signature: String) 
+    /// Add a record verified by the [`VerifierPool`](crate::ingest::VerifierPool) to the current window
+    pub async fn insert_record(&mut self, verified_record: VerifiedRecord)
         -> Result<Uuid, Box<dyn std::error::Error + Send + Sync>> {
-        
+
         let receipt_id = Uuid::new_v4();
+        let record = verified_record.record.clone();
+        let signer = verified_record.signer.clone();
         debug!("Processing record for meter {} with receipt {}", record.meter_id, receipt_id);
 
-        // Verify signature if enabled
-        if self.config.enable_signature_verification {
-            if !self.crypto.verify_signature(&record, &signature)? {
-                self.stats.records_rejected_signature += 1;
-                return Err("Invalid signature".into());
-            }
-        }
-
-        // Create verified record
-        let record_hash = self.crypto.create_record_hash(&record)?;
-        let verified_record = VerifiedRecord {
-            record: record.clone(),
-            signature,
-            verification_timestamp: Utc::now(),
-            record_hash,
-        };
-
         // Ensure we have a current window
         self.ensure_current_window()?;
 
-        // Check for duplicate records (same meter_id and nonce)
+        // Check for duplicate records (same nonce from the same meter_id or signer)
         if let Some(ref window) = self.current_window {
             let is_duplicate = window.records.iter().any(|r| 
-                r.record.meter_id == record.meter_id && r.record.nonce == record.nonce
+                r.record.nonce == record.nonce
+                    && (r.record.meter_id == record.meter_id || (signer.is_some() && r.signer == signer))
             );
             
             if is_duplicate {
@@ -76,38 +77,41 @@ impl DataAggregator {
             }
         }
 
-        // Add to current window
-        if let Some(ref mut window) = self.current_window {
-            // Check window capacity
-            if window.records.len() >= self.config.max_records_per_window {
+        // Finalize the current window first when it is full or has expired
+        let (full, expired) = match self.current_window {
+            Some(ref window) => (window.records.len() >= self.config.max_records_per_window, Utc::now() >= window.window_end),
+            None => (false, false),
+        };
+        if full || expired {
+            if full {
                 warn!("Window capacity exceeded, forcing aggregation");
-                self.finalize_current_window().await?;
-                self.ensure_current_window()?;
-            }
-
-            // Check if window has expired
-            if Utc::now() >= window.window_end {
+            } else {
                 info!("Window expired, finalizing aggregation");
-                self.finalize_current_window().await?;
-                self.ensure_current_window()?;
             }
+            self.finalize_current_window().await?;
+            self.ensure_current_window()?;
+        }
 
-            // Add record to current window
-            if let Some(ref mut window) = self.current_window {
-                window.records.push(verified_record);
-                self.stats.total_records_processed += 1;
-                
-                debug!("Added record to window. Total records in window: {}", window.records.len());
-            }
+        // Add record to current window
+        if let Some(ref mut window) = self.current_window {
+            window.records.push(verified_record);
+            self.stats.total_records_processed += 1;
+            
+            debug!("Added record to window. Total records in window: {}", window.records.len());
         }
 
         Ok(receipt_id)
     }
 
-    /// Ensure we have a current aggregation window
+    /// Count a record the verifier pool rejected
+    pub fn record_signature_rejection(&mut self) {
+        self.stats.records_rejected_signature += 1;
+    }
+
+    /// Ensure we have a current aggregation window; an expired one still holding readings is
+    /// kept until it is finalized
     fn ensure_current_window(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if self.current_window.is_none() || 
-           (self.current_window.as_ref().unwrap().window_end <= Utc::now()) {
+        if self.current_window.as_ref().is_none_or(|window| window.window_end <= Utc::now() && window.records.is_empty()) {
             self.start_new_window()?;
         }
         Ok(())
@@ -116,13 +120,13 @@ impl DataAggregator {
     /// Start a new aggregation window
     fn start_new_window(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let now = Utc::now();
-        let window_duration = Duration::seconds(self.config.agg_window_sec as i64);
+        let window_duration = Duration::seconds(self.config.agg_window_sec.max(1) as i64);
         
-        // Align window to hour boundaries for consistency
-        let window_start = now
-            .with_minute(0).unwrap()
-            .with_second(0).unwrap()
-            .with_nanosecond(0).unwrap();
+        // Align window to multiples of its own length (hour boundaries by default) for consistency;
+        // aligning a shorter window to the hour would start it already expired
+        let window_ms = window_duration.num_milliseconds();
+        let window_start = DateTime::from_timestamp_millis(now.timestamp_millis().div_euclid(window_ms) * window_ms)
+            .ok_or("Window start out of range")?;
         
         let window_end = window_start + window_duration;
 
@@ -138,7 +142,7 @@ impl DataAggregator {
 
     /// Finalize current window and generate proof
     pub async fn finalize_current_window(&mut self) -> Result<Option<ProofData>, Box<dyn std::error::Error + Send + Sync>> {
-        let window = match self.current_window.take() {
+        let mut window = match self.current_window.take() {
             Some(window) => window,
             None => return Ok(None),
         };
@@ -151,7 +155,7 @@ impl DataAggregator {
         info!("Finalizing window with {} records", window.records.len());
 
         // Filter outliers
-        let filtered_records = self.filter_outliers(window.records)?;
+        let filtered_records = self.filter_outliers(std::mem::take(&mut window.records))?;
         info!("After outlier filtering: {} records", filtered_records.len());
 
         if filtered_records.is_empty() {
@@ -159,11 +163,26 @@ impl DataAggregator {
             return Ok(None);
         }
 
-        // Generate proof
-        let proof = self.generate_proof(&window, filtered_records).await?;
-        
-        // Save proof to file
-        self.save_proof(&proof).await?;
+        // Generate proof, count the window into a copy of the meter state and append the proof's
+        // leaf digest to a copy of the accumulator. The copies replace the loaded ones only once
+        // everything is saved, so a failed save cannot leave memory ahead of disk.
+        let saved = self.build_and_save(&window, &filtered_records).await;
+        let (proof, mmr, meter_states) = match saved {
+            Ok(saved) => saved,
+            Err(e) => {
+                // Keep the readings for the next attempt
+                window.records = filtered_records;
+                self.current_window = Some(window);
+                return Err(e);
+            }
+        };
+        self.mmr = Some(mmr);
+        self.meter_states = Some(meter_states);
+        if let Some(proofs) = &mut self.proofs {
+            let proofs = Arc::make_mut(proofs);
+            let index = proofs.partition_point(|stored| proof_order(stored) <= proof_order(&proof));
+            proofs.insert(index, proof.clone());
+        }
         
         self.stats.total_proofs_generated += 1;
         self.stats.last_proof_generated = Some(Utc::now());
@@ -174,6 +193,20 @@ impl DataAggregator {
         Ok(Some(proof))
     }
 
+    /// Proof of a window with the accumulator and meter state that include it, all saved
+    async fn build_and_save(&mut self, window: &AggregationWindow, records: &[VerifiedRecord])
+        -> Result<(ProofData, Mmr, StateTree), Box<dyn std::error::Error + Send + Sync>> {
+        let mut proof = self.generate_proof(window, records).await?;
+        let mut meter_states = self.meter_states().await?.clone();
+        meter_states.apply(records);
+        proof.state_root = Some(meter_states.root());
+        let mut mmr = self.accumulator().await?.clone();
+        proof.mmr = Some(mmr.append(&crypto::proof_leaf_hash(&proof))?);
+
+        self.save_window(&proof, records, &mmr, &meter_states).await?;
+        Ok((proof, mmr, meter_states))
+    }
+
     /// Filter outliers from records
     fn filter_outliers(&mut self, records: Vec<VerifiedRecord>) 
         -> Result<Vec<VerifiedRecord>, Box<dyn std::error::Error + Send + Sync>> {
@@ -203,7 +236,7 @@ impl DataAggregator {
     }
 
     /// Generate cryptographic proof from aggregated data
-    async fn generate_proof(&self, window: &AggregationWindow, records: Vec<VerifiedRecord>) 
+    async fn generate_proof(&self, window: &AggregationWindow, records: &[VerifiedRecord]) 
         -> Result<ProofData, Box<dyn std::error::Error + Send + Sync>> {
         
         // Calculate aggregate kWh
@@ -217,11 +250,9 @@ impl DataAggregator {
         meter_ids.dedup();
 
         // Generate Merkle tree from record hashes
-        let record_hashes: Vec<String> = records.iter()
-            .map(|r| r.record_hash.clone())
-            .collect();
-
-        let merkle_tree = MerkleTree::new(record_hashes)?;
+        let record_hashes = records.iter().map(|r| &r.record_hash);
+        let merkle_tree = MerkleTree::with_scheme(record_hashes, HashScheme::CURRENT).map_err(|e| e.to_string())?;
+        let sum_tree = MerkleSumTree::from_records(records)?;
         
         Ok(ProofData {
             proof_id: Uuid::new_v4(),
@@ -233,23 +264,67 @@ impl DataAggregator {
             meter_ids,
             generated_at: Utc::now(),
             version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::CURRENT,
+            sum_root: Some(sum_tree.root()),
+            state_root: None,
+            mmr: None,
         })
     }
 
-    /// Save proof to JSON file
-    async fn save_proof(&self, proof: &ProofData) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let filename = format!("proof_{}.json", proof.proof_id);
-        let filepath = format!("{}/{}", self.config.output_dir, filename);
-        
-        let json_content = serde_json::to_string_pretty(proof)?;
-        fs::write(&filepath, json_content).await?;
-        
-        // Also save as latest.json for easy access
-        let latest_path = format!("{}/latest.json", self.config.output_dir);
-        let json_content = serde_json::to_string_pretty(proof)?;
-        fs::write(latest_path, json_content).await?;
-        
-        info!("Saved proof to {}", filepath);
+    /// Save a finalized window: its records and proof, the accumulator and meter state that
+    /// include it, and `latest.json`. Every file is written aside first and then renamed into
+    /// place, the proof ahead of the accumulator leaf and meter totals that refer to it, so an
+    /// accumulator leaf is never stored without its proof.
+    async fn save_window(&self, proof: &ProofData, records: &[VerifiedRecord], mmr: &Mmr, meter_states: &StateTree)
+        -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let output_dir = &self.config.output_dir;
+        let files = [
+            (format!("{}/records_{}.json", output_dir, proof.proof_id), serde_json::to_string_pretty(records)?),
+            (format!("{}/proof_{}.json", output_dir, proof.proof_id), serde_json::to_string_pretty(proof)?),
+            (format!("{}/mmr.json", output_dir), serde_json::to_string_pretty(mmr.digests())?),
+            (format!("{}/meter_states.json", output_dir), serde_json::to_string_pretty(meter_states.states())?),
+            (format!("{}/latest.json", output_dir), serde_json::to_string_pretty(proof)?),
+        ];
+        // The records and proof are new files; the rest replace earlier versions
+        const NEW_FILES: usize = 2;
+
+        let mut staged = Vec::with_capacity(files.len());
+        for (path, content) in &files {
+            let temp = format!("{}.tmp", path);
+            if let Err(e) = fs::write(&temp, content).await {
+                remove_files(&staged).await;
+                return Err(e.into());
+            }
+            staged.push(temp);
+        }
+
+        // Earlier versions of the replaced files, put back if the window cannot be saved whole
+        let mut previous = Vec::with_capacity(files.len() - NEW_FILES);
+        for (path, _) in &files[NEW_FILES..] {
+            match fs::read(path).await {
+                Ok(content) => previous.push(Some(content)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => previous.push(None),
+                Err(e) => {
+                    remove_files(&staged).await;
+                    return Err(e.into());
+                }
+            }
+        }
+
+        for (moved, ((path, _), temp)) in files.iter().zip(&staged).enumerate() {
+            if let Err(e) = fs::rename(temp, path).await {
+                // Take the window's files back out and leave the accumulator as it was
+                let new_files: Vec<String> = files[..moved.min(NEW_FILES)].iter().map(|(path, _)| path.clone()).collect();
+                remove_files(&new_files).await;
+                for ((path, _), content) in files[NEW_FILES..moved.max(NEW_FILES)].iter().zip(&previous) {
+                    restore_file(path, content.as_deref()).await;
+                }
+                remove_files(&staged[moved..]).await;
+                return Err(e.into());
+            }
+        }
+
+        info!("Saved proof {} with {} records to {}", proof.proof_id, records.len(), output_dir);
         Ok(())
     }
 
@@ -266,6 +341,140 @@ impl DataAggregator {
         }
     }
 
+    /// Get a previously generated proof by ID
+    pub async fn get_proof_by_id(&self, proof_id: &Uuid) -> Result<Option<ProofData>, Box<dyn std::error::Error + Send + Sync>> {
+        let proof_path = format!("{}/proof_{}.json", self.config.output_dir, proof_id);
+
+        match fs::read_to_string(proof_path).await {
+            Ok(content) => {
+                let proof: ProofData = serde_json::from_str(&content)?;
+                Ok(Some(proof))
+            }
+            Err(_) => Ok(None), // File doesn't exist
+        }
+    }
+
+    /// All stored proofs, ordered by window start. Read from disk on first use, then kept up to
+    /// date by finalization, so holding the aggregator lock for this is cheap.
+    pub async fn list_proofs(&mut self) -> Result<Arc<Vec<ProofData>>, Box<dyn std::error::Error + Send + Sync>> {
+        if self.proofs.is_none() {
+            self.proofs = Some(Arc::new(self.load_proofs().await?));
+        }
+        Ok(self.proofs.clone().unwrap())
+    }
+
+    /// Read every stored proof from the output directory
+    async fn load_proofs(&self) -> Result<Vec<ProofData>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut proofs = Vec::new();
+        let mut entries = match fs::read_dir(&self.config.output_dir).await {
+            Ok(entries) => entries,
+            Err(_) => return Ok(proofs), // Nothing generated yet
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if !(name.starts_with("proof_") && name.ends_with(".json")) {
+                continue;
+            }
+
+            let content = fs::read_to_string(entry.path()).await?;
+            proofs.push(serde_json::from_str::<ProofData>(&content)?);
+        }
+
+        proofs.sort_by_key(proof_order);
+        Ok(proofs)
+    }
+
+    /// Get the verified records behind a proof, if they were kept
+    pub async fn get_records_by_id(&self, proof_id: &Uuid) -> Result<Option<Vec<VerifiedRecord>>, Box<dyn std::error::Error + Send + Sync>> {
+        let records_path = format!("{}/records_{}.json", self.config.output_dir, proof_id);
+
+        match fs::read_to_string(records_path).await {
+            Ok(content) => {
+                let records: Vec<VerifiedRecord> = serde_json::from_str(&content)?;
+                Ok(Some(records))
+            }
+            Err(_) => Ok(None), // File doesn't exist
+        }
+    }
+
+    /// Accumulator of every finalized proof, loaded from disk on first use
+    pub async fn accumulator(&mut self) -> Result<&mut Mmr, Box<dyn std::error::Error + Send + Sync>> {
+        if self.mmr.is_none() {
+            let mmr_path = format!("{}/mmr.json", self.config.output_dir);
+            let digests: Vec<String> = match fs::read_to_string(&mmr_path).await {
+                Ok(content) => serde_json::from_str(&content)?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(e.into()),
+            };
+            self.mmr = Some(Mmr::from_digests(digests)?);
+        }
+        Ok(self.mmr.as_mut().unwrap())
+    }
+
+    /// Cumulative state of every meter, loaded from disk on first use
+    pub async fn meter_states(&mut self) -> Result<&mut StateTree, Box<dyn std::error::Error + Send + Sync>> {
+        if self.meter_states.is_none() {
+            let states_path = format!("{}/meter_states.json", self.config.output_dir);
+            let states = match fs::read_to_string(&states_path).await {
+                Ok(content) => serde_json::from_str::<BTreeMap<String, MeterState>>(&content)?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+                Err(e) => return Err(e.into()),
+            };
+            self.meter_states = Some(StateTree::from_states(states));
+        }
+        Ok(self.meter_states.as_mut().unwrap())
+    }
+
+    /// Keep the Walrus seal receipt of a proof
+    pub async fn save_seal_receipt(&self, proof_id: &Uuid, receipt: &SealResponse) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let filepath = format!("{}/seal_{}.json", self.config.output_dir, proof_id);
+
+        let json_content = serde_json::to_string_pretty(receipt)?;
+        fs::write(&filepath, json_content).await?;
+
+        info!("Saved seal receipt to {}", filepath);
+        Ok(())
+    }
+
+    /// Get the seal receipt of a proof, if it has been sealed
+    pub async fn get_seal_receipt(&self, proof_id: &Uuid) -> Result<Option<SealResponse>, Box<dyn std::error::Error + Send + Sync>> {
+        let receipt_path = format!("{}/seal_{}.json", self.config.output_dir, proof_id);
+
+        match fs::read_to_string(receipt_path).await {
+            Ok(content) => {
+                let receipt: SealResponse = serde_json::from_str(&content)?;
+                Ok(Some(receipt))
+            }
+            Err(_) => Ok(None), // File doesn't exist
+        }
+    }
+
+    /// Keep the latest chain reconciliation report
+    pub async fn save_reconciliation_report(&self, report: &ReconciliationReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let filepath = format!("{}/reconciliation.json", self.config.output_dir);
+
+        let json_content = serde_json::to_string_pretty(report)?;
+        fs::write(&filepath, json_content).await?;
+
+        debug!("Saved reconciliation report to {}", filepath);
+        Ok(())
+    }
+
+    /// Get the latest chain reconciliation report, if one has run
+    pub async fn get_reconciliation_report(&self) -> Result<Option<ReconciliationReport>, Box<dyn std::error::Error + Send + Sync>> {
+        let report_path = format!("{}/reconciliation.json", self.config.output_dir);
+
+        match fs::read_to_string(report_path).await {
+            Ok(content) => {
+                let report: ReconciliationReport = serde_json::from_str(&content)?;
+                Ok(Some(report))
+            }
+            Err(_) => Ok(None), // File doesn't exist
+        }
+    }
+
     /// Get current window status
     pub fn get_window_status(&self) -> Option<WindowStatus> {
         self.current_window.as_ref().map(|window| {
@@ -294,4 +503,194 @@ impl DataAggregator {
             Ok(None)
         }
     }
-}
\ No newline at end of file
+}
+
+/// Remove files, ignoring the ones that are already gone
+async fn remove_files(paths: &[String]) {
+    for path in paths {
+        if let Err(e) = fs::remove_file(path).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Cannot remove {}: {}", path, e);
+            }
+        }
+    }
+}
+
+/// Put back the earlier contents of a file, or remove it if it did not exist
+async fn restore_file(path: &str, content: Option<&[u8]>) {
+    let Some(content) = content else {
+        return remove_files(&[path.to_string()]).await;
+    };
+    if let Err(e) = fs::write(path, content).await {
+        warn!("Cannot restore {}: {}", path, e);
+    }
+}
+
+/// Order of [`DataAggregator::list_proofs`]
+fn proof_order(proof: &ProofData) -> (DateTime<Utc>, DateTime<Utc>) {
+    (proof.window_start, proof.generated_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MeterRecord, RecordEncoding, SignatureScheme};
+
+    fn reading(meter_id: &str, nonce: u32) -> VerifiedRecord {
+        VerifiedRecord {
+            record: MeterRecord {
+                meter_id: meter_id.to_string(),
+                timestamp: Utc::now().timestamp_millis(),
+                kwh_delta: 1.0,
+                nonce: format!("{:032x}", nonce),
+                encoding: RecordEncoding::V1,
+            },
+            signature: "0x00".to_string(),
+            scheme: SignatureScheme::Legacy,
+            signer: None,
+            verification_timestamp: Utc::now(),
+            record_hash: hex::encode([nonce as u8; 32]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_keeps_state_in_memory_unchanged() {
+        let output_dir = std::env::temp_dir().join(format!("rofl_aggregator_{}", Uuid::new_v4()));
+        let mut aggregator = DataAggregator::new(Config {
+            output_dir: output_dir.to_string_lossy().into_owned(),
+            ..Config::default()
+        });
+
+        // The output directory does not exist yet, so nothing can be saved
+        aggregator.insert_record(reading("meter_001", 1)).await.unwrap();
+        assert!(aggregator.force_finalize().await.is_err());
+        assert!(aggregator.accumulator().await.unwrap().is_empty());
+        assert!(aggregator.meter_states().await.unwrap().states().is_empty());
+        assert_eq!(aggregator.get_window_status().unwrap().records_collected, 1);
+
+        // The kept reading goes into the next proof
+        fs::create_dir_all(&output_dir).await.unwrap();
+        aggregator.insert_record(reading("meter_001", 2)).await.unwrap();
+        let proof = aggregator.force_finalize().await.unwrap().unwrap();
+        assert_eq!(proof.record_count, 2);
+        assert_eq!(proof.mmr.unwrap().leaf_index, 0);
+        assert_eq!(aggregator.meter_states().await.unwrap().get("meter_001").unwrap().readings, 2);
+        assert_eq!(proof.state_root.as_deref(), Some(aggregator.meter_states().await.unwrap().root().as_str()));
+
+        fs::remove_dir_all(&output_dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_leaves_no_orphans_on_disk() {
+        let output_dir = std::env::temp_dir().join(format!("rofl_aggregator_{}", Uuid::new_v4()));
+        fs::create_dir_all(&output_dir).await.unwrap();
+        let config = Config {
+            output_dir: output_dir.to_string_lossy().into_owned(),
+            ..Config::default()
+        };
+        let dir = &output_dir;
+        let stored = || async move {
+            let mut names = Vec::new();
+            let mut entries = fs::read_dir(dir).await.unwrap();
+            while let Some(entry) = entries.next_entry().await.unwrap() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+            names.sort();
+            names
+        };
+
+        // Every file is written, but the accumulator cannot be moved into place
+        let mut aggregator = DataAggregator::new(config.clone());
+        aggregator.accumulator().await.unwrap();
+        fs::create_dir(output_dir.join("mmr.json")).await.unwrap();
+        aggregator.insert_record(reading("meter_001", 1)).await.unwrap();
+        assert!(aggregator.force_finalize().await.is_err());
+
+        // Neither the proof nor its records outlive the failure, and the readings are kept
+        assert_eq!(stored().await, vec!["mmr.json".to_string()]);
+        assert_eq!(aggregator.get_window_status().unwrap().records_collected, 1);
+        assert!(aggregator.accumulator().await.unwrap().is_empty());
+
+        fs::remove_dir(output_dir.join("mmr.json")).await.unwrap();
+        let proof = aggregator.force_finalize().await.unwrap().unwrap();
+        assert_eq!(proof.record_count, 1);
+        assert!(stored().await.iter().all(|name| !name.ends_with(".tmp")));
+
+        // After a restart the accumulator holds exactly the stored proof
+        let mut restarted = DataAggregator::new(config);
+        assert_eq!(restarted.accumulator().await.unwrap().len(), 1);
+        assert_eq!(restarted.list_proofs().await.unwrap().len(), 1);
+        assert_eq!(restarted.meter_states().await.unwrap().get("meter_001").unwrap().readings, 1);
+
+        fs::remove_dir_all(&output_dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_late_save_failure_restores_replaced_files() {
+        let output_dir = std::env::temp_dir().join(format!("rofl_aggregator_{}", Uuid::new_v4()));
+        fs::create_dir_all(&output_dir).await.unwrap();
+        let config = Config {
+            output_dir: output_dir.to_string_lossy().into_owned(),
+            ..Config::default()
+        };
+        let read = |name: &str| std::fs::read_to_string(output_dir.join(name)).unwrap();
+
+        let mut aggregator = DataAggregator::new(config.clone());
+        aggregator.insert_record(reading("meter_001", 1)).await.unwrap();
+        let first = aggregator.force_finalize().await.unwrap().unwrap();
+        let (mmr, meter_states) = (read("mmr.json"), read("meter_states.json"));
+
+        // The accumulator and meter states are moved into place, then latest.json fails
+        fs::remove_file(output_dir.join("latest.json")).await.unwrap();
+        fs::create_dir(output_dir.join("latest.json")).await.unwrap();
+        aggregator.insert_record(reading("meter_001", 2)).await.unwrap();
+        assert!(aggregator.force_finalize().await.is_err());
+
+        assert_eq!(read("mmr.json"), mmr);
+        assert_eq!(read("meter_states.json"), meter_states);
+        let mut stored = Vec::new();
+        let mut entries = fs::read_dir(&output_dir).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            stored.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        stored.sort();
+        let expected = ["latest.json".to_string(), "meter_states.json".to_string(), "mmr.json".to_string(),
+            format!("proof_{}.json", first.proof_id), format!("records_{}.json", first.proof_id)];
+        assert_eq!(stored, expected);
+
+        // The retry takes the next leaf, on disk as in memory
+        fs::remove_dir(output_dir.join("latest.json")).await.unwrap();
+        aggregator.force_finalize().await.unwrap().unwrap();
+        assert_eq!(aggregator.accumulator().await.unwrap().len(), 2);
+        let mut restarted = DataAggregator::new(config);
+        assert_eq!(restarted.accumulator().await.unwrap().len(), 2);
+        assert_eq!(restarted.meter_states().await.unwrap().get("meter_001").unwrap().readings, 2);
+
+        fs::remove_dir_all(&output_dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_proof_index() {
+        let output_dir = std::env::temp_dir().join(format!("rofl_aggregator_{}", Uuid::new_v4()));
+        fs::create_dir_all(&output_dir).await.unwrap();
+        let config = Config {
+            output_dir: output_dir.to_string_lossy().into_owned(),
+            ..Config::default()
+        };
+
+        let mut aggregator = DataAggregator::new(config.clone());
+        aggregator.insert_record(reading("meter_001", 1)).await.unwrap();
+        let first = aggregator.force_finalize().await.unwrap().unwrap();
+
+        // Loaded from disk once, then kept up to date without reading it again
+        let mut restarted = DataAggregator::new(config);
+        let ids = |proofs: &[ProofData]| proofs.iter().map(|proof| proof.proof_id).collect::<Vec<_>>();
+        assert_eq!(ids(&restarted.list_proofs().await.unwrap()), vec![first.proof_id]);
+        restarted.insert_record(reading("meter_001", 2)).await.unwrap();
+        let second = restarted.force_finalize().await.unwrap().unwrap();
+        fs::remove_file(output_dir.join(format!("proof_{}.json", first.proof_id))).await.unwrap();
+        assert_eq!(ids(&restarted.list_proofs().await.unwrap()), vec![first.proof_id, second.proof_id]);
+
+        fs::remove_dir_all(&output_dir).await.unwrap();
+    }
+}
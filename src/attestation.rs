@@ -0,0 +1,414 @@
+//! Manufacturer and installer trust anchors vouching for meter keys through device certificates
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::{HashMap, HashSet};
+
+use crate::canonical;
+use crate::schemes::{self, KeyScheme, SignatureVerifier};
+
+type AttestationResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Version tag embedded in every canonical device certificate
+pub const DEVICE_CERTIFICATE_ENCODING: &str = "greenshare.device-cert.v1";
+
+/// Root key of a manufacturer or installer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustAnchor {
+    /// Name certificates refer to in `issuer`
+    pub name: String,
+    #[serde(default)]
+    pub scheme: KeyScheme,
+    /// Hex public key, or the Ethereum address for recoverable keys
+    pub public_key: String,
+}
+
+/// `TRUST_ANCHORS_FILE` contents
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustAnchorsFile {
+    pub anchors: Vec<TrustAnchor>,
+    /// Serials of revoked device certificates
+    #[serde(default)]
+    pub revoked: Vec<String>,
+}
+
+/// Binding of a meter ID to its public key, signed by a trust anchor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceCertificate {
+    pub serial: String,
+    /// Name of the signing [`TrustAnchor`]
+    pub issuer: String,
+    pub meter_id: String,
+    #[serde(default)]
+    pub scheme: KeyScheme,
+    /// Meter key, in the `public_key` format of a registered meter
+    pub public_key: String,
+    /// Installed generation capacity
+    pub capacity_kw: f64,
+    /// Free-form installation details (site, installer, commissioning date)
+    #[serde(default)]
+    pub installation: serde_json::Map<String, serde_json::Value>,
+    /// Validity (ms), checked against the timestamp of each reading and the time it is ingested
+    pub not_before: i64,
+    pub not_after: i64,
+    /// Issuer's signature over [`DeviceCertificate::message`], made like a key rotation
+    pub signature: String,
+}
+
+impl DeviceCertificate {
+    /// RFC 8785 (JCS) encoding of every field but the signature
+    pub fn message(&self) -> String {
+        canonical::jcs(&serde_json::json!({
+            "encoding": DEVICE_CERTIFICATE_ENCODING,
+            "serial": self.serial,
+            "issuer": self.issuer,
+            "meter_id": self.meter_id,
+            "scheme": self.scheme,
+            "public_key": self.public_key,
+            "capacity_kw": self.capacity_kw,
+            "installation": self.installation,
+            "not_before": self.not_before,
+            "not_after": self.not_after,
+        }))
+    }
+
+    /// Whether the certificate vouches for the given meter key
+    pub fn certifies(&self, scheme: KeyScheme, public_key: &str) -> bool {
+        self.scheme == scheme && self.public_key.eq_ignore_ascii_case(public_key)
+    }
+}
+
+/// Why a device certificate was refused
+#[derive(Debug, thiserror::Error)]
+pub enum CertificateError {
+    #[error("unknown certificate issuer {0}")]
+    UnknownIssuer(String),
+    #[error("certificate is not signed by {issuer}: {reason}")]
+    BadSignature { issuer: String, reason: String },
+    #[error("certificate {0} is revoked")]
+    Revoked(String),
+    #[error("certificate {serial} is not valid at {timestamp}")]
+    Expired { serial: String, timestamp: i64 },
+    #[error("certificate is for meter {0}")]
+    WrongMeter(String),
+    #[error("certificate does not match the key of meter {0}")]
+    KeyMismatch(String),
+    #[error("invalid certificate: {0}")]
+    Invalid(String),
+    #[error("cannot store enrollment: {0}")]
+    Storage(String),
+}
+
+/// Configured trust anchors and the certificates they revoked
+#[derive(Default)]
+pub struct TrustStore {
+    anchors: HashMap<String, (TrustAnchor, Box<dyn SignatureVerifier>)>,
+    revoked: HashSet<String>,
+}
+
+impl TrustStore {
+    /// Load a [`TrustAnchorsFile`]
+    pub fn from_file(path: &str) -> AttestationResult<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read trust anchors {}: {}", path, e))?;
+        Self::new(serde_json::from_str(&content)?)
+    }
+
+    pub fn new(file: TrustAnchorsFile) -> AttestationResult<Self> {
+        let mut anchors = HashMap::new();
+        for anchor in file.anchors {
+            let verifier = schemes::verifier_for(anchor.scheme, Some(&anchor.public_key))
+                .map_err(|e| format!("Trust anchor {}: {}", anchor.name, e))?;
+            anchors.insert(anchor.name.clone(), (anchor, verifier));
+        }
+        Ok(Self { anchors, revoked: file.revoked.into_iter().collect() })
+    }
+
+    pub fn len(&self) -> usize {
+        self.anchors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchors.is_empty()
+    }
+
+    /// Check the issuer's signature and revocation; validity is checked per reading
+    pub fn verify(&self, certificate: &DeviceCertificate) -> Result<(), CertificateError> {
+        self.verify_signature(certificate)?;
+        self.check_revoked(certificate)
+    }
+
+    /// Check only the issuer's signature, as when replaying certificates accepted earlier
+    pub fn verify_signature(&self, certificate: &DeviceCertificate) -> Result<(), CertificateError> {
+        let (anchor, issuer) = self.anchors.get(&certificate.issuer)
+            .ok_or_else(|| CertificateError::UnknownIssuer(certificate.issuer.clone()))?;
+        let signature = hex::decode(certificate.signature.trim_start_matches("0x"))
+            .map_err(|_| CertificateError::Invalid("signature is not valid hex".to_string()))?;
+
+        let identity = issuer.verify_message(certificate.message().as_bytes(), &signature)
+            .map_err(|e| CertificateError::BadSignature { issuer: certificate.issuer.clone(), reason: e.to_string() })?;
+        // Recoverable anchors verify anything; the recovered address must be the configured one
+        if anchor.scheme == KeyScheme::Secp256k1Recoverable && !anchor.public_key.eq_ignore_ascii_case(&identity) {
+            return Err(CertificateError::BadSignature {
+                issuer: certificate.issuer.clone(),
+                reason: format!("signed by {}", identity),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check a verified certificate still vouches for a reading taken at `timestamp`
+    pub fn check_valid_at(&self, certificate: &DeviceCertificate, timestamp: i64) -> Result<(), CertificateError> {
+        self.check_revoked(certificate)?;
+        if timestamp < certificate.not_before || timestamp > certificate.not_after {
+            return Err(CertificateError::Expired { serial: certificate.serial.clone(), timestamp });
+        }
+        Ok(())
+    }
+
+    fn check_revoked(&self, certificate: &DeviceCertificate) -> Result<(), CertificateError> {
+        if self.revoked.contains(&certificate.serial) {
+            return Err(CertificateError::Revoked(certificate.serial.clone()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoService;
+    use crate::models::{MeterRecord, RecordEncoding, SignatureScheme};
+    use crate::registry::{KeyRotation, MeterRegistry, RegisteredMeter};
+    use crate::signer::EnclaveSigner;
+    use sha2::{Digest, Sha256};
+
+    fn signed(anchor: &EnclaveSigner, certificate: DeviceCertificate) -> DeviceCertificate {
+        let digest: [u8; 32] = Sha256::digest(certificate.message().as_bytes()).into();
+        DeviceCertificate { signature: hex::encode(anchor.sign_digest(&digest)), ..certificate }
+    }
+
+    #[test]
+    fn test_device_certificates() {
+        let crypto = CryptoService::new();
+        let anchor = EnclaveSigner::from_hex(&"51".repeat(32)).unwrap();
+        let meter_key = ed25519_dalek::SigningKey::from_bytes(&[8u8; 32]);
+        let trust = TrustStore::new(TrustAnchorsFile {
+            anchors: vec![TrustAnchor {
+                name: "acme".to_string(),
+                scheme: KeyScheme::Secp256k1Recoverable,
+                public_key: anchor.address(),
+            }],
+            revoked: vec!["acme-2".to_string()],
+        }).unwrap();
+        let mut registry = MeterRegistry::default();
+        registry.register(RegisteredMeter {
+            meter_id: "meter_registered".to_string(),
+            scheme: KeyScheme::Ed25519,
+            public_key: Some(hex::encode([1u8; 32])),
+        }).unwrap();
+        let registry = registry.with_trust_store(trust);
+
+        let certificate = signed(&anchor, DeviceCertificate {
+            serial: "acme-1".to_string(),
+            issuer: "acme".to_string(),
+            meter_id: "meter_100".to_string(),
+            scheme: KeyScheme::Ed25519,
+            public_key: hex::encode(meter_key.verifying_key().as_bytes()),
+            capacity_kw: 6.5,
+            installation: serde_json::json!({ "installer": "Sunny Roofs" }).as_object().unwrap().clone(),
+            not_before: 1_000,
+            not_after: 5_000,
+            signature: String::new(),
+        });
+
+        // Refused: another meter, tampered, unknown or impostor issuer, revoked, key conflict
+        assert!(matches!(registry.enroll_at("meter_101", certificate.clone(), 2_000), Err(CertificateError::WrongMeter(_))));
+        let tampered = DeviceCertificate { capacity_kw: 60.0, ..certificate.clone() };
+        assert!(matches!(registry.enroll_at("meter_100", tampered, 2_000), Err(CertificateError::BadSignature { .. })));
+        let unknown = signed(&anchor, DeviceCertificate { issuer: "other".to_string(), ..certificate.clone() });
+        assert!(matches!(registry.enroll_at("meter_100", unknown, 2_000), Err(CertificateError::UnknownIssuer(_))));
+        let impostor = EnclaveSigner::from_hex(&"52".repeat(32)).unwrap();
+        assert!(matches!(registry.enroll_at("meter_100", signed(&impostor, certificate.clone()), 2_000), Err(CertificateError::BadSignature { .. })));
+        let revoked = signed(&anchor, DeviceCertificate { serial: "acme-2".to_string(), ..certificate.clone() });
+        assert!(matches!(registry.enroll_at("meter_100", revoked, 2_000), Err(CertificateError::Revoked(_))));
+        let registered = signed(&anchor, DeviceCertificate { meter_id: "meter_registered".to_string(), ..certificate.clone() });
+        assert!(matches!(registry.enroll_at("meter_registered", registered, 2_000), Err(CertificateError::KeyMismatch(_))));
+        // Nor is a certificate outside its validity when it is enrolled
+        for now in [999, 5_001] {
+            let refused = registry.enroll_at("meter_100", certificate.clone(), now);
+            assert!(matches!(refused, Err(CertificateError::Expired { timestamp, .. }) if timestamp == now));
+        }
+        assert!(registry.history("meter_100").is_none());
+
+        let history = registry.enroll_at("meter_100", certificate.clone(), 2_000).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].certificate.as_ref(), Some(&certificate));
+        assert!(registry.enroll_at("meter_100", certificate, 2_000).is_ok());
+
+        // Readings are accepted only while the certificate is valid
+        for (timestamp, accepted) in [(999, false), (1_000, true), (5_000, true), (5_001, false)] {
+            let record = MeterRecord {
+                meter_id: "meter_100".to_string(),
+                timestamp,
+                kwh_delta: 0.5,
+                nonce: "nonce".to_string(),
+                encoding: RecordEncoding::V1,
+            };
+            let signature = ed25519_dalek::Signer::sign(&meter_key, crypto.create_message(&record).as_bytes());
+            let verified = registry.verify(&crypto, &record, &hex::encode(signature.to_bytes()), SignatureScheme::Legacy);
+            assert_eq!(verified.is_ok(), accepted, "reading at {}", timestamp);
+        }
+
+        // An expired certificate cannot vouch for a reading backdated into its validity
+        let record = MeterRecord {
+            meter_id: "meter_100".to_string(),
+            timestamp: 4_000,
+            kwh_delta: 0.5,
+            nonce: "backdated".to_string(),
+            encoding: RecordEncoding::V1,
+        };
+        let signature = hex::encode(ed25519_dalek::Signer::sign(&meter_key, crypto.create_message(&record).as_bytes()).to_bytes());
+        assert!(registry.verify_and_bind_at(&crypto, &record, &signature, SignatureScheme::Legacy, 4_500).is_ok());
+        let expired = registry.verify_and_bind_at(&crypto, &record, &signature, SignatureScheme::Legacy, 5_001).unwrap_err();
+        assert!(expired.downcast_ref::<CertificateError>().is_some_and(|e| matches!(e, CertificateError::Expired { timestamp: 5_001, .. })));
+        assert!(registry.verify_and_bind(&crypto, &record, &signature, SignatureScheme::Legacy).is_err());
+    }
+
+    #[test]
+    fn test_certificate_replaces_first_use_binding() {
+        let crypto = CryptoService::new();
+        let anchor = EnclaveSigner::from_hex(&"51".repeat(32)).unwrap();
+        let squatter = EnclaveSigner::from_hex(&"53".repeat(32)).unwrap();
+        let meter_key = ed25519_dalek::SigningKey::from_bytes(&[8u8; 32]);
+        let trust = || TrustStore::new(TrustAnchorsFile {
+            anchors: vec![TrustAnchor {
+                name: "acme".to_string(),
+                scheme: KeyScheme::Secp256k1Recoverable,
+                public_key: anchor.address(),
+            }],
+            revoked: Vec::new(),
+        }).unwrap();
+        let log = std::env::temp_dir().join(format!("bindings_{}.json", uuid::Uuid::new_v4()));
+        let registry = MeterRegistry::default().with_binding_log(&log).unwrap().with_trust_store(trust());
+
+        // Someone else's key got to the meter first
+        let record = MeterRecord {
+            meter_id: "meter_200".to_string(),
+            timestamp: 2_000,
+            kwh_delta: 0.5,
+            nonce: "nonce".to_string(),
+            encoding: RecordEncoding::V1,
+        };
+        let digest = crypto.meter_signing_digest(&record, SignatureScheme::Eip712).unwrap();
+        let squatted = hex::encode(squatter.sign_digest(&digest));
+        registry.verify_and_bind(&crypto, &record, &squatted, SignatureScheme::Eip712).unwrap();
+
+        let certificate = signed(&anchor, DeviceCertificate {
+            serial: "acme-3".to_string(),
+            issuer: "acme".to_string(),
+            meter_id: "meter_200".to_string(),
+            scheme: KeyScheme::Ed25519,
+            public_key: hex::encode(meter_key.verifying_key().as_bytes()),
+            capacity_kw: 6.5,
+            installation: serde_json::Map::new(),
+            not_before: 1_000,
+            not_after: 5_000,
+            signature: String::new(),
+        });
+        let history = registry.enroll_at("meter_200", certificate.clone(), 2_000).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].certificate.as_ref(), Some(&certificate));
+
+        let signature = hex::encode(ed25519_dalek::Signer::sign(&meter_key, crypto.create_message(&record).as_bytes()).to_bytes());
+        assert!(registry.verify(&crypto, &record, &signature, SignatureScheme::Legacy).is_ok());
+        assert!(registry.verify(&crypto, &record, &squatted, SignatureScheme::Eip712).is_err());
+
+        // The squatter's binding is not replayed on restart
+        let restored = MeterRegistry::default().with_binding_log(&log).unwrap();
+        assert!(restored.history("meter_200").is_none());
+
+        std::fs::remove_file(&log).unwrap();
+    }
+
+    #[test]
+    fn test_enrollment_survives_restart() {
+        let crypto = CryptoService::new();
+        let anchor = EnclaveSigner::from_hex(&"51".repeat(32)).unwrap();
+        let squatter = EnclaveSigner::from_hex(&"53".repeat(32)).unwrap();
+        let meter_key = EnclaveSigner::from_hex(&"54".repeat(32)).unwrap();
+        let next_key = EnclaveSigner::from_hex(&"55".repeat(32)).unwrap();
+        let trust = || TrustStore::new(TrustAnchorsFile {
+            anchors: vec![TrustAnchor {
+                name: "acme".to_string(),
+                scheme: KeyScheme::Secp256k1Recoverable,
+                public_key: anchor.address(),
+            }],
+            revoked: Vec::new(),
+        }).unwrap();
+        let dir = std::env::temp_dir();
+        let logs = ["bindings", "certificates", "rotations"]
+            .map(|log| dir.join(format!("{}_{}.json", log, uuid::Uuid::new_v4())));
+        let load = || MeterRegistry::default()
+            .with_trust_store(trust())
+            .with_binding_log(&logs[0]).unwrap()
+            .with_certificate_log(&logs[1]).unwrap()
+            .with_rotation_log(&logs[2]).unwrap();
+        let registry = load();
+
+        let record = |timestamp| MeterRecord {
+            meter_id: "meter_300".to_string(),
+            timestamp,
+            kwh_delta: 0.5,
+            nonce: "nonce".to_string(),
+            encoding: RecordEncoding::V1,
+        };
+        let sign = |key: &EnclaveSigner, record: &MeterRecord| {
+            hex::encode(key.sign_digest(&crypto.meter_signing_digest(record, SignatureScheme::Eip712).unwrap()))
+        };
+        let certificate = |serial: &str, key: &EnclaveSigner| signed(&anchor, DeviceCertificate {
+            serial: serial.to_string(),
+            issuer: "acme".to_string(),
+            meter_id: "meter_300".to_string(),
+            scheme: KeyScheme::Secp256k1Recoverable,
+            public_key: key.address(),
+            capacity_kw: 6.5,
+            installation: serde_json::Map::new(),
+            not_before: 1_000,
+            not_after: 10_000,
+            signature: String::new(),
+        });
+
+        // Enroll over a squatter's binding, rotate, then certify the new key too
+        registry.verify_and_bind_at(&crypto, &record(2_000), &sign(&squatter, &record(2_000)), SignatureScheme::Eip712, 2_000).unwrap();
+        registry.enroll_at("meter_300", certificate("acme-4", &meter_key), 2_000).unwrap();
+        let rotation = KeyRotation {
+            meter_id: "meter_300".to_string(),
+            new_scheme: KeyScheme::Secp256k1Recoverable,
+            new_public_key: next_key.address(),
+            effective_at: 3_000,
+            nonce: "rotation-1".to_string(),
+            signature: String::new(),
+        };
+        let digest: [u8; 32] = Sha256::digest(rotation.message().as_bytes()).into();
+        let rotation = KeyRotation { signature: hex::encode(meter_key.sign_digest(&digest)), ..rotation };
+        registry.rotate(rotation).unwrap();
+        registry.enroll_at("meter_300", certificate("acme-5", &next_key), 3_000).unwrap();
+        let history = registry.history("meter_300").unwrap();
+
+        let restored = load();
+        let replayed = restored.history("meter_300").unwrap();
+        assert_eq!(replayed.len(), 2);
+        for (epoch, expected) in replayed.iter().zip(&history) {
+            assert_eq!(epoch.public_key, expected.public_key);
+            assert_eq!(epoch.certificate, expected.certificate);
+        }
+        assert!(restored.verify(&crypto, &record(2_000), &sign(&meter_key, &record(2_000)), SignatureScheme::Eip712).is_ok());
+        assert!(restored.verify(&crypto, &record(2_000), &sign(&squatter, &record(2_000)), SignatureScheme::Eip712).is_err());
+        assert!(restored.verify(&crypto, &record(4_000), &sign(&next_key, &record(4_000)), SignatureScheme::Eip712).is_ok());
+
+        for log in logs {
+            std::fs::remove_file(log).unwrap();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! JSON renderings that must match the TypeScript side byte for byte
+
+use serde_json::Value;
+
+use crate::models::{MeterRecord, RecordEncoding};
+
+/// Version tag embedded in every canonical meter record message
+pub const METER_RECORD_ENCODING: &str = "greenshare.meter-record.v1";
+
+/// Format a number exactly like JavaScript's `Number.prototype.toString`
+/// (which is also what `JSON.stringify` emits). Non-finite values become
+/// `null`, as they do in `JSON.stringify`.
+pub fn format_js_number(value: f64) -> String {
+    if !value.is_finite() {
+        return "null".to_string();
+    }
+    if value == 0.0 {
+        return "0".to_string(); // also covers -0
+    }
+    if value < 0.0 {
+        return format!("-{}", format_js_number(-value));
+    }
+
+    // Rust's `{:e}` yields the shortest round-trip digits, e.g. "1.2345e2"
+    let formatted = format!("{:e}", value);
+    let (mantissa, exponent) = formatted.split_once('e').expect("exponent formatting");
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().expect("exponent is an integer");
+
+    // value = 0.digits * 10^n, using the ECMAScript naming (k digits, exponent n)
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        if k == 1 {
+            format!("{}e{}{}", digits, sign, (n - 1).abs())
+        } else {
+            format!("{}.{}e{}{}", &digits[..1], &digits[1..], sign, (n - 1).abs())
+        }
+    }
+}
+
+/// Quote a string the way `JSON.stringify` does
+pub fn json_string(value: &str) -> String {
+    serde_json::to_string(value).expect("strings always serialize")
+}
+
+/// RFC 8785 (JCS) serialization: object keys sorted by UTF-16 code units, numbers as in
+/// JavaScript, no whitespace. Matches `canonicalize` in `src/crypto.ts`.
+pub fn jcs(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => format_js_number(n.as_f64().unwrap_or(f64::NAN)),
+        Value::String(s) => json_string(s),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(jcs).collect();
+            format!("[{}]", items.join(","))
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by_cached_key(|(key, _)| key.encode_utf16().collect::<Vec<u16>>());
+            let entries: Vec<String> = entries.into_iter()
+                .map(|(key, value)| format!("{}:{}", json_string(key), jcs(value)))
+                .collect();
+            format!("{{{}}}", entries.join(","))
+        }
+    }
+}
+
+/// The bytes a meter signs under `record.encoding`
+pub fn meter_record_message(record: &MeterRecord) -> String {
+    match record.encoding {
+        RecordEncoding::Legacy => legacy_meter_record(record),
+        RecordEncoding::V1 => canonical_meter_record(record),
+    }
+}
+
+/// Pre-v1 record JSON: `serde_json` with keys sorted and Rust float formatting. Only kept so
+/// existing meters still verify; it can disagree with `JSON.stringify` (e.g. `1e-7`).
+pub fn legacy_meter_record(record: &MeterRecord) -> String {
+    serde_json::json!({
+        "meter_id": record.meter_id,
+        "timestamp": record.timestamp,
+        "kwh_delta": record.kwh_delta,
+        "nonce": record.nonce,
+    }).to_string()
+}
+
+/// JCS of the record plus the `encoding` version tag
+pub fn canonical_meter_record(record: &MeterRecord) -> String {
+    jcs(&serde_json::json!({
+        "encoding": METER_RECORD_ENCODING,
+        "meter_id": record.meter_id,
+        "timestamp": record.timestamp,
+        "kwh_delta": record.kwh_delta,
+        "nonce": record.nonce,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_js_number_formatting() {
+        // Expected values taken from `String(x)` in Node.js
+        let cases: &[(f64, &str)] = &[
+            (0.0, "0"),
+            (-0.0, "0"),
+            (123.0, "123"),
+            (123.45, "123.45"),
+            (-1.5, "-1.5"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (1.23e-18, "1.23e-18"),
+            (1e21, "1e+21"),
+            (1.5e22, "1.5e+22"),
+            (123456789012345680000.0, "123456789012345680000"),
+            (f64::NAN, "null"),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(format_js_number(*value), *expected, "formatting {:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_jcs_sorts_keys_by_utf16() {
+        // U+1F600 (surrogate pair 0xD83D...) sorts before U+FB01 in UTF-16, after it in UTF-8
+        let value = serde_json::json!({ "b": [1.0, "x", null], "\u{fb01}": true, "\u{1f600}": false, "a": { "d": 2, "c": 1e-7 } });
+        assert_eq!(jcs(&value), "{\"a\":{\"c\":1e-7,\"d\":2},\"b\":[1,\"x\",null],\"\u{1f600}\":false,\"\u{fb01}\":true}");
+    }
+
+    #[test]
+    fn test_meter_record_vectors() {
+        use sha2::{Digest, Sha256};
+
+        // Shared with tests/crypto.test.ts
+        let vectors: Vec<Value> = serde_json::from_str(include_str!("../tests/vectors/meter-record-v1.json")).unwrap();
+        assert!(!vectors.is_empty());
+
+        for vector in vectors {
+            let record: MeterRecord = serde_json::from_value(vector["record"].clone()).unwrap();
+            let canonical = canonical_meter_record(&record);
+
+            assert_eq!(canonical, vector["canonical"].as_str().unwrap(), "{}", vector["name"]);
+            assert_eq!(hex::encode(Sha256::digest(canonical.as_bytes())), vector["sha256"].as_str().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_record_encodings() {
+        let record = MeterRecord {
+            meter_id: "meter_001".to_string(),
+            timestamp: 1640995200000,
+            kwh_delta: 1e-7,
+            nonce: "nonce".to_string(),
+            encoding: RecordEncoding::Legacy,
+        };
+
+        // Untagged records keep the bytes meters signed before v1
+        let legacy = r#"{"kwh_delta":1e-7,"meter_id":"meter_001","nonce":"nonce","timestamp":1640995200000}"#;
+        assert_eq!(meter_record_message(&record), legacy);
+
+        let record = MeterRecord { encoding: RecordEncoding::V1, ..record };
+        assert_eq!(meter_record_message(&record), canonical_meter_record(&record));
+        assert_ne!(meter_record_message(&record), legacy);
+
+        // The tag travels with the record
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["encoding"], METER_RECORD_ENCODING);
+        let untagged: MeterRecord = serde_json::from_value(serde_json::json!({
+            "meter_id": "meter_001", "timestamp": 1640995200000i64, "kwh_delta": 0.5, "nonce": "nonce",
+        })).unwrap();
+        assert_eq!(untagged.encoding, RecordEncoding::Legacy);
+    }
+
+    #[test]
+    fn test_json_string_escaping() {
+        assert_eq!(json_string("meter_001"), "\"meter_001\"");
+        assert_eq!(json_string("a\"b\n"), "\"a\\\"b\\n\"");
+    }
+}
@@ -0,0 +1,154 @@
+//! Arguments for `certificate::issue_certificate`, derived inside the enclave
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::crypto;
+use crate::models::ProofData;
+use crate::seal::SealResponse;
+
+const HOUSEHOLD_HASH_DOMAIN: &[u8] = b"greenshare.household.v1";
+const DERIVED_SALT_DOMAIN: &[u8] = b"greenshare.household-salt.v1";
+
+/// Canonical certificate payload, fields in `issue_certificate` argument order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CertificatePayload {
+    pub proof_id: Uuid,
+    pub proof_hash: String,
+    pub window_start: String,
+    pub window_end: String,
+    /// Total energy in micro-kWh
+    pub total_kwh: u64,
+    pub meter_count: u64,
+    pub household_hash: String,
+    pub seal_blob_id: String,
+}
+
+/// Salt for household hashes: `HOUSEHOLD_HASH_SALT`, otherwise derived from the enclave
+/// signing key so it never has to leave the enclave
+pub fn household_salt(config: &Config) -> Result<Vec<u8>, String> {
+    if let Some(salt) = &config.household_hash_salt {
+        return Ok(salt.as_bytes().to_vec());
+    }
+
+    let signing_key = config.enclave_signing_key.as_deref()
+        .ok_or("Neither HOUSEHOLD_HASH_SALT nor an enclave signing key is configured")?;
+    let key_bytes = hex::decode(signing_key.trim().trim_start_matches("0x"))
+        .map_err(|e| format!("Invalid enclave signing key: {}", e))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(DERIVED_SALT_DOMAIN);
+    hasher.update(key_bytes);
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Salted commitment to the set of meters behind a proof (order-independent)
+pub fn household_hash(salt: &[u8], meter_ids: &[String]) -> String {
+    let mut meter_ids: Vec<&String> = meter_ids.iter().collect();
+    meter_ids.sort();
+    meter_ids.dedup();
+
+    let mut hasher = Sha256::new();
+    hasher.update(HOUSEHOLD_HASH_DOMAIN);
+    hasher.update((salt.len() as u32).to_be_bytes());
+    hasher.update(salt);
+    for meter_id in meter_ids {
+        hasher.update((meter_id.len() as u32).to_be_bytes());
+        hasher.update(meter_id.as_bytes());
+    }
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Build the certificate payload for a proof that has been sealed
+pub fn build_payload(proof: &ProofData, receipt: &SealResponse, salt: &[u8]) -> Result<CertificatePayload, String> {
+    let seal_blob_id = receipt.blob_id.clone()
+        .filter(|_| receipt.success)
+        .ok_or_else(|| format!("Proof {} has no successful seal", proof.proof_id))?;
+
+    let total_kwh = proof.aggregate_micro_kwh();
+    if total_kwh == 0 {
+        return Err("Proof aggregate rounds to zero micro-kWh".to_string());
+    }
+
+    Ok(CertificatePayload {
+        proof_id: proof.proof_id,
+        proof_hash: crypto::proof_hash(proof),
+        window_start: proof.window_start.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        window_end: proof.window_end.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        total_kwh,
+        meter_count: proof.meter_ids.len() as u64,
+        household_hash: household_hash(salt, &proof.meter_ids),
+        seal_blob_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HashScheme;
+    use chrono::{TimeZone, Utc};
+
+    fn proof() -> ProofData {
+        ProofData {
+            proof_id: Uuid::new_v4(),
+            aggregate_kwh: 12.5,
+            merkle_root: "ab".repeat(32),
+            window_start: Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap(),
+            window_end: Utc.with_ymd_and_hms(2024, 1, 1, 11, 0, 0).unwrap(),
+            record_count: 4,
+            meter_ids: vec!["meter_b".to_string(), "meter_a".to_string()],
+            generated_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::Legacy,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
+        }
+    }
+
+    fn receipt(success: bool) -> SealResponse {
+        serde_json::from_value(serde_json::json!({
+            "success": success,
+            "blob_id": "sealed_blob",
+            "walrus_url": null,
+            "tx_digest": null,
+            "cost": null,
+            "sui_object_id": null,
+            "end_epoch": null,
+            "already_certified": false,
+            "content_blob_id": "content_blob",
+            "records_manifest_blob_id": null,
+            "seal_hash": null,
+            "seal_signature": null,
+            "error": null
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_household_hash_is_salted_and_order_independent() {
+        let ids = vec!["meter_a".to_string(), "meter_b".to_string()];
+        let reversed = vec!["meter_b".to_string(), "meter_a".to_string()];
+
+        assert_eq!(household_hash(b"salt", &ids), household_hash(b"salt", &reversed));
+        assert_ne!(household_hash(b"salt", &ids), household_hash(b"pepper", &ids));
+        assert!(!household_hash(b"salt", &ids).contains("meter"));
+    }
+
+    #[test]
+    fn test_build_payload() {
+        let proof = proof();
+        let payload = build_payload(&proof, &receipt(true), b"salt").unwrap();
+
+        assert_eq!(payload.window_start, "2024-01-01T10:00:00Z");
+        assert_eq!(payload.window_end, "2024-01-01T11:00:00Z");
+        assert_eq!(payload.total_kwh, 12_500_000);
+        assert_eq!(payload.meter_count, 2);
+        assert_eq!(payload.seal_blob_id, "sealed_blob");
+        assert_eq!(payload.proof_hash, crypto::proof_hash(&proof));
+
+        assert!(build_payload(&proof, &receipt(false), b"salt").is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Read-only view of the on-chain state the enclave's proofs end up in
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::config::Config;
+
+type ChainResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Queries against `sKWH::QuotaLedger` and `walrus_seal::SealRegistry`
+#[async_trait]
+pub trait ChainAdapter: Send + Sync {
+    /// `sKWH::is_proof_used`: whether a proof hash has been minted against
+    async fn is_proof_used(&self, proof_hash: &str) -> ChainResult<bool>;
+
+    /// Seal object registered for a proof ID in the `SealRegistry`, if any
+    async fn seal_registration(&self, proof_id: &str) -> ChainResult<Option<String>>;
+}
+
+/// Chain state for [`LocalChain`], also its JSON file format
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalChainState {
+    #[serde(default)]
+    pub used_proofs: HashSet<String>,
+    /// proof_id -> seal object address
+    #[serde(default)]
+    pub seals: HashMap<String, String>,
+}
+
+/// In-memory stand-in for the chain, optionally reloaded from a JSON file on every query
+#[derive(Debug, Default)]
+pub struct LocalChain {
+    state: RwLock<LocalChainState>,
+    path: Option<PathBuf>,
+}
+
+impl LocalChain {
+    pub fn new(state: LocalChainState) -> Self {
+        Self { state: RwLock::new(state), path: None }
+    }
+
+    /// Fake backed by a JSON file (missing file means empty chain)
+    pub fn from_file(path: impl AsRef<Path>) -> Self {
+        Self { state: RwLock::new(LocalChainState::default()), path: Some(path.as_ref().to_path_buf()) }
+    }
+
+    pub async fn mark_proof_used(&self, proof_hash: &str) {
+        self.state.write().await.used_proofs.insert(proof_hash.to_string());
+    }
+
+    pub async fn register_seal(&self, proof_id: &str, seal_object: &str) {
+        self.state.write().await.seals.insert(proof_id.to_string(), seal_object.to_string());
+    }
+
+    async fn refresh(&self) -> ChainResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let state = match tokio::fs::read_to_string(path).await {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => LocalChainState::default(),
+            Err(e) => return Err(e.into()),
+        };
+        *self.state.write().await = state;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for LocalChain {
+    async fn is_proof_used(&self, proof_hash: &str) -> ChainResult<bool> {
+        self.refresh().await?;
+        Ok(self.state.read().await.used_proofs.contains(proof_hash))
+    }
+
+    async fn seal_registration(&self, proof_id: &str) -> ChainResult<Option<String>> {
+        self.refresh().await?;
+        Ok(self.state.read().await.seals.get(proof_id).cloned())
+    }
+}
+
+/// Adapter reading the shared objects' tables over Sui JSON-RPC
+pub struct SuiRpcChain {
+    client: reqwest::Client,
+    rpc_url: String,
+    quota_ledger_id: String,
+    seal_registry_id: String,
+    /// Table IDs inside the shared objects, resolved on first use
+    table_ids: RwLock<HashMap<(String, &'static str), String>>,
+}
+
+impl SuiRpcChain {
+    pub fn new(rpc_url: &str, quota_ledger_id: &str, seal_registry_id: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            rpc_url: rpc_url.to_string(),
+            quota_ledger_id: quota_ledger_id.to_string(),
+            seal_registry_id: seal_registry_id.to_string(),
+            table_ids: RwLock::new(HashMap::new()),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> ChainResult<Value> {
+        let response: Value = self.client
+            .post(&self.rpc_url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            return Err(format!("{} failed: {}", method, error).into());
+        }
+        Ok(response["result"].clone())
+    }
+
+    /// UID of a `Table` field of a shared object
+    async fn table_id(&self, object_id: &str, field: &'static str) -> ChainResult<String> {
+        let key = (object_id.to_string(), field);
+        if let Some(id) = self.table_ids.read().await.get(&key) {
+            return Ok(id.clone());
+        }
+
+        let object = self.call("sui_getObject", json!([object_id, { "showContent": true }])).await?;
+        let id = object["data"]["content"]["fields"][field]["fields"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| format!("Object {} has no table field '{}'", object_id, field))?
+            .to_string();
+
+        self.table_ids.write().await.insert(key, id.clone());
+        Ok(id)
+    }
+
+    /// Value stored under a `String` key of a table, if present
+    async fn table_entry(&self, object_id: &str, field: &'static str, key: &str) -> ChainResult<Option<Value>> {
+        let table_id = self.table_id(object_id, field).await?;
+        let result = self.call(
+            "suix_getDynamicFieldObject",
+            json!([table_id, { "type": "0x1::string::String", "value": key }]),
+        ).await?;
+
+        if result.get("error").is_some_and(|error| !error.is_null()) {
+            return Ok(None); // dynamicFieldNotFound
+        }
+        Ok(Some(result["data"]["content"]["fields"]["value"].clone()))
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for SuiRpcChain {
+    async fn is_proof_used(&self, proof_hash: &str) -> ChainResult<bool> {
+        let entry = self.table_entry(&self.quota_ledger_id, "used_proofs", proof_hash).await?;
+        Ok(entry.is_some_and(|value| value.as_bool().unwrap_or(true)))
+    }
+
+    async fn seal_registration(&self, proof_id: &str) -> ChainResult<Option<String>> {
+        let entry = self.table_entry(&self.seal_registry_id, "proof_to_seal", proof_id).await?;
+        Ok(entry.and_then(|value| value.as_str().map(str::to_string)))
+    }
+}
+
+/// Adapter for the configured chain: the local stand-in when `CHAIN_STATE_FILE` is set,
+/// otherwise Sui RPC when the ledger and registry IDs are known
+pub fn from_config(config: &Config) -> Option<Arc<dyn ChainAdapter>> {
+    if let Some(path) = &config.chain_state_file {
+        return Some(Arc::new(LocalChain::from_file(path)));
+    }
+
+    match (&config.sui_rpc_url, &config.sui.quota_ledger_id, &config.sui_seal_registry_id) {
+        (Some(rpc_url), Some(quota_ledger_id), Some(seal_registry_id)) => {
+            Some(Arc::new(SuiRpcChain::new(rpc_url, quota_ledger_id, seal_registry_id)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_chain_from_file() {
+        let path = std::env::temp_dir().join(format!("chain_{}.json", uuid::Uuid::new_v4()));
+        let chain = LocalChain::from_file(&path);
+        assert!(!chain.is_proof_used("0xabc").await.unwrap());
+
+        let state = LocalChainState {
+            used_proofs: HashSet::from(["0xabc".to_string()]),
+            seals: HashMap::from([("proof-1".to_string(), "0x5ea1".to_string())]),
+        };
+        tokio::fs::write(&path, serde_json::to_string(&state).unwrap()).await.unwrap();
+
+        assert!(chain.is_proof_used("0xabc").await.unwrap());
+        assert_eq!(chain.seal_registration("proof-1").await.unwrap().as_deref(), Some("0x5ea1"));
+        assert_eq!(chain.seal_registration("proof-2").await.unwrap(), None);
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
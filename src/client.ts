@@ -9,11 +9,35 @@ export interface ROFLClientConfig {
   retryDelay?: number;
 }
 
+/** Versioned API prefix; the unprefixed routes are deprecated (see `GET /api/v1/openapi.json`) */
+export const API_PREFIX = '/api/v1';
+
+/** `IngestResponse` in the enclave's OpenAPI document */
 export interface ROFLResponse {
   success: boolean;
-  message?: string;
-  timestamp?: number;
-  receipt_id?: string;
+  message: string;
+  /** RFC 3339 */
+  timestamp: string;
+  receipt_id: string;
+}
+
+/** `StatusResponse` in the enclave's OpenAPI document */
+export interface ROFLStatus {
+  status: string;
+  current_window: {
+    window_start: string;
+    window_end: string;
+    records_collected: number;
+    time_remaining_seconds: number;
+  } | null;
+  total_records_processed: number;
+  total_proofs_generated: number;
+  last_proof_generated: string | null;
+  configuration: {
+    agg_window_sec: number;
+    max_records_per_window: number;
+    enable_signature_verification: boolean;
+  };
 }
 
 export class ROFLClient {
@@ -84,7 +108,7 @@ export class ROFLClient {
       try {
         console.log(`🔄 Attempt ${attempt}/${this.config.retryAttempts} - Ingesting meter data...`);
         
-        const response = await this.client.post<ROFLResponse>('/ingest', data);
+        const response = await this.client.post<ROFLResponse>(`${API_PREFIX}/ingest`, data);
         
         console.log('✅ Successfully ingested meter data:', {
           meter_id: data.record.meter_id,
@@ -128,7 +152,7 @@ export class ROFLClient {
    */
   async healthCheck(): Promise<boolean> {
     try {
-      const response = await this.client.get('/health');
+      const response = await this.client.get(`${API_PREFIX}/health`);
       return response.status === 200;
     } catch (error) {
       console.warn('⚠️ Health check failed:', (error as Error).message);
@@ -139,9 +163,9 @@ export class ROFLClient {
   /**
    * Get enclave status/info
    */
-  async getStatus(): Promise<any> {
+  async getStatus(): Promise<ROFLStatus> {
     try {
-      const response = await this.client.get('/status');
+      const response = await this.client.get<ROFLStatus>(`${API_PREFIX}/status`);
       return response.data;
     } catch (error) {
       console.error('❌ Failed to get enclave status:', (error as Error).message);
@@ -2,13 +2,51 @@
 use serde::{Deserialize, Serialize};
 use std::env;
 
+use crate::envelope::Recipient;
+use crate::seal::{WalrusApi, WalrusPricing};
+use crate::sui::SuiMintConfig;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub host: String,
     pub port: u16,
     pub agg_window_sec: u64,
     pub output_dir: String,
-    pub seal_endpoint: Option<String>,
+    pub walrus_publisher_url: String,
+    pub walrus_gateway_url: String,
+    pub walrus_epochs: u32,
+    pub walrus_api: WalrusApi,
+    #[serde(skip_serializing, default)]
+    pub enclave_signing_key: Option<String>,
+    /// Parties sealed payloads are encrypted for; empty means seal in the clear
+    pub seal_recipients: Vec<Recipient>,
+    pub walrus_pricing: WalrusPricing,
+    /// Maximum estimated cost (FROST) of sealing one proof; unset means unlimited
+    pub seal_budget: Option<u64>,
+    /// Also seal the full verified record set of each proof
+    pub seal_records: bool,
+    pub seal_chunk_records: usize,
+    pub sui: SuiMintConfig,
+    pub sui_rpc_url: Option<String>,
+    pub sui_seal_registry_id: Option<String>,
+    /// JSON chain state for the local chain stand-in; takes precedence over Sui RPC
+    pub chain_state_file: Option<String>,
+    /// Seconds between chain reconciliation runs; 0 disables the job
+    pub reconcile_interval_sec: u64,
+    /// EIP-712 domain for EVM proof signatures
+    pub evm_chain_id: Option<u64>,
+    pub evm_verifying_contract: Option<String>,
+    /// Salt for certificate household hashes (derived from the signing key when unset)
+    #[serde(skip_serializing, default)]
+    pub household_hash_salt: Option<String>,
+    /// JSON array of registered meters and their signature schemes
+    pub meter_registry_file: Option<String>,
+    /// Reject meters that are neither registered, bound on first use, nor certified
+    pub require_registered_meters: bool,
+    /// Manufacturer/installer root keys and revoked device certificates
+    pub trust_anchors_file: Option<String>,
+    /// Concurrent signature verifications; defaults to the number of cores
+    pub verify_workers: Option<usize>,
     pub max_records_per_window: usize,
     pub outlier_threshold_multiplier: f64,
     pub enable_signature_verification: bool,
@@ -16,7 +54,7 @@ pub struct Config {
 
 impl Config {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Config {
+        let config = Config {
             host: env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
             port: env::var("PORT")
                 .unwrap_or_else(|_| "8080".to_string())
@@ -26,7 +64,81 @@ impl Config {
                 .parse()?,
             output_dir: env::var("OUTPUT_DIR")
                 .unwrap_or_else(|_| "./out".to_string()),
-            seal_endpoint: env::var("SEAL_ENDPOINT").ok(),
+            walrus_publisher_url: env::var("WALRUS_PUBLISHER_URL")
+                .unwrap_or_else(|_| "https://publisher-devnet.walrus.space".to_string()),
+            walrus_gateway_url: env::var("WALRUS_GATEWAY_URL")
+                .unwrap_or_else(|_| "https://aggregator-devnet.walrus.space".to_string()),
+            walrus_epochs: env::var("WALRUS_EPOCHS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()?,
+            walrus_api: env::var("WALRUS_API")
+                .unwrap_or_else(|_| "blobs".to_string())
+                .parse()?,
+            enclave_signing_key: optional_env("ENCLAVE_SIGNING_KEY")
+                .or_else(|| optional_env("WALRUS_PRIVATE_KEY")),
+            seal_recipients: env::var("SEAL_RECIPIENTS")
+                .unwrap_or_default()
+                .split(',')
+                .filter(|entry| !entry.trim().is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+            walrus_pricing: WalrusPricing {
+                encoding_overhead: env::var("WALRUS_ENCODING_OVERHEAD")
+                    .unwrap_or_else(|_| "5.0".to_string())
+                    .parse()?,
+                metadata_bytes: env::var("WALRUS_METADATA_BYTES")
+                    .unwrap_or_else(|_| "67108864".to_string())
+                    .parse()?,
+                unit_bytes: env::var("WALRUS_UNIT_BYTES")
+                    .unwrap_or_else(|_| "1048576".to_string())
+                    .parse()?,
+                storage_price_per_unit: env::var("WALRUS_STORAGE_PRICE")
+                    .unwrap_or_else(|_| "100000".to_string())
+                    .parse()?,
+                write_price_per_unit: env::var("WALRUS_WRITE_PRICE")
+                    .unwrap_or_else(|_| "20000".to_string())
+                    .parse()?,
+            },
+            seal_budget: optional_env("SEAL_BUDGET")
+                .map(|budget| budget.parse())
+                .transpose()?,
+            seal_records: env::var("SEAL_RECORDS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()?,
+            seal_chunk_records: env::var("SEAL_CHUNK_RECORDS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()?,
+            sui: SuiMintConfig {
+                package_id: optional_env("SUI_PACKAGE_ID"),
+                treasury_cap_id: optional_env("SUI_TREASURY_CAP_ID"),
+                treasury_cap_version: optional_env("SUI_TREASURY_CAP_VERSION")
+                    .map(|version| version.parse())
+                    .transpose()?,
+                treasury_cap_digest: optional_env("SUI_TREASURY_CAP_DIGEST"),
+                quota_ledger_id: optional_env("SUI_QUOTA_LEDGER_ID"),
+                quota_ledger_initial_version: optional_env("SUI_QUOTA_LEDGER_INITIAL_VERSION")
+                    .map(|version| version.parse())
+                    .transpose()?,
+            },
+            sui_rpc_url: optional_env("SUI_RPC_URL"),
+            sui_seal_registry_id: optional_env("SUI_SEAL_REGISTRY_ID"),
+            chain_state_file: optional_env("CHAIN_STATE_FILE"),
+            reconcile_interval_sec: env::var("RECONCILE_INTERVAL_SEC")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()?,
+            evm_chain_id: optional_env("EVM_CHAIN_ID")
+                .map(|chain_id| chain_id.parse())
+                .transpose()?,
+            evm_verifying_contract: optional_env("EVM_VERIFYING_CONTRACT"),
+            household_hash_salt: optional_env("HOUSEHOLD_HASH_SALT"),
+            meter_registry_file: optional_env("METER_REGISTRY_FILE"),
+            require_registered_meters: env::var("REQUIRE_REGISTERED_METERS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()?,
+            trust_anchors_file: optional_env("TRUST_ANCHORS_FILE"),
+            verify_workers: optional_env("VERIFY_WORKERS")
+                .map(|workers| workers.parse())
+                .transpose()?,
             max_records_per_window: env::var("MAX_RECORDS_PER_WINDOW")
                 .unwrap_or_else(|_| "1000".to_string())
                 .parse()?,
@@ -36,6 +148,55 @@ impl Config {
             enable_signature_verification: env::var("ENABLE_SIGNATURE_VERIFICATION")
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()?,
-        })
+        };
+
+        // Sealed record sets are public by design; refuse to leak what encryption is meant to hide
+        if config.seal_records && !config.seal_recipients.is_empty() {
+            return Err("SEAL_RECORDS publishes raw meter readings and cannot be combined with SEAL_RECIPIENTS".into());
+        }
+
+        Ok(config)
+    }
+}
+
+/// The values `from_env` falls back to when nothing is set
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 8080,
+            agg_window_sec: 3600,
+            output_dir: "./out".to_string(),
+            walrus_publisher_url: "https://publisher-devnet.walrus.space".to_string(),
+            walrus_gateway_url: "https://aggregator-devnet.walrus.space".to_string(),
+            walrus_epochs: 5,
+            walrus_api: WalrusApi::Blobs,
+            enclave_signing_key: None,
+            seal_recipients: Vec::new(),
+            walrus_pricing: WalrusPricing::default(),
+            seal_budget: None,
+            seal_records: false,
+            seal_chunk_records: 1000,
+            sui: SuiMintConfig::default(),
+            sui_rpc_url: None,
+            sui_seal_registry_id: None,
+            chain_state_file: None,
+            reconcile_interval_sec: 600,
+            evm_chain_id: None,
+            evm_verifying_contract: None,
+            household_hash_salt: None,
+            meter_registry_file: None,
+            require_registered_meters: false,
+            trust_anchors_file: None,
+            verify_workers: None,
+            max_records_per_window: 1000,
+            outlier_threshold_multiplier: 3.0,
+            enable_signature_verification: true,
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Read an optional variable, treating an empty value (`KEY=` in `.env`) as unset
+fn optional_env(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.trim().is_empty())
+}
@@ -1,9 +1,21 @@
 // FILE: src/crypto.rs
-use secp256k1::{PublicKey, Signature, Message, Secp256k1, ecdsa::RecoverableSignature};
+use secp256k1::{Message, Secp256k1};
 use sha2::{Sha256, Digest};
 use sha3::Keccak256;
 use hex;
-use crate::models::MeterRecord;
+use crate::canonical;
+use crate::evm;
+use crate::merkle;
+use crate::models::{self, HashScheme, MeterRecord, ProofData, SignatureScheme};
+use crate::signer::{self, RecoverableSig, SignatureError};
+
+/// EIP-712 domain meters sign under; no chain or contract since records never go on-chain directly
+pub const METER_DOMAIN_NAME: &str = "GreenShare Meter";
+pub const METER_DOMAIN_VERSION: &str = "1";
+
+const METER_DOMAIN_TYPE: &str = "EIP712Domain(string name,string version)";
+const METER_RECORD_TYPE: &str =
+    "MeterRecord(string meterId,int64 timestamp,uint64 kwhDeltaMicro,string nonce)";
 
 pub struct CryptoService {
     secp: Secp256k1<secp256k1::All>,
@@ -16,32 +28,87 @@ impl CryptoService {
         }
     }
 
+    /// Message meters sign, in the encoding the record names
+    pub fn create_message(&self, record: &MeterRecord) -> String {
+        canonical::meter_record_message(record)
+    }
+
     /// Create deterministic message hash for meter record (compatible with TypeScript version)
     pub fn create_message_hash(&self, record: &MeterRecord) -> Result<[u8; 32], Box<dyn std::error::Error>> {
-        // Create the same JSON structure as TypeScript version
-        let message = serde_json::json!({
-            "meter_id": record.meter_id,
-            "timestamp": record.timestamp,
-            "kwh_delta": record.kwh_delta,
-            "nonce": record.nonce
-        });
-        
-        let message_str = serde_json::to_string(&message)?;
+        let message_str = self.create_message(record);
         let mut hasher = Sha256::new();
         hasher.update(message_str.as_bytes());
         Ok(hasher.finalize().into())
     }
 
-    /// Create keccak256 hash of record for Merkle tree
+    /// EIP-712 digest of a meter record; energy is signed in micro-kWh
+    pub fn create_typed_data_hash(&self, record: &MeterRecord) -> Result<[u8; 32], Box<dyn std::error::Error + Send + Sync>> {
+        if record.kwh_delta < 0.0 {
+            return Err("EIP-712 records need a non-negative kwh_delta".into());
+        }
+
+        let mut domain = signer::keccak256(METER_DOMAIN_TYPE.as_bytes()).to_vec();
+        domain.extend_from_slice(&signer::keccak256(METER_DOMAIN_NAME.as_bytes()));
+        domain.extend_from_slice(&signer::keccak256(METER_DOMAIN_VERSION.as_bytes()));
+
+        let mut encoded = signer::keccak256(METER_RECORD_TYPE.as_bytes()).to_vec();
+        encoded.extend_from_slice(&signer::keccak256(record.meter_id.as_bytes()));
+        // int64, sign-extended to 32 bytes
+        let fill = if record.timestamp < 0 { 0xff } else { 0x00 };
+        encoded.extend_from_slice(&[fill; 24]);
+        encoded.extend_from_slice(&record.timestamp.to_be_bytes());
+        encoded.extend_from_slice(&[0u8; 24]);
+        encoded.extend_from_slice(&models::kwh_to_micro(record.kwh_delta).to_be_bytes());
+        encoded.extend_from_slice(&signer::keccak256(record.nonce.as_bytes()));
+
+        Ok(evm::typed_data_digest(&signer::keccak256(&domain), &signer::keccak256(&encoded)))
+    }
+
+    /// Digest a meter signed under the given scheme
+    pub fn meter_signing_digest(&self, record: &MeterRecord, scheme: SignatureScheme)
+        -> Result<[u8; 32], Box<dyn std::error::Error + Send + Sync>> {
+        match scheme {
+            SignatureScheme::Legacy => self.create_message_hash(record).map_err(|e| e.to_string().into()),
+            SignatureScheme::Eip191 => Ok(signer::eip191_hash(self.create_message(record).as_bytes())),
+            SignatureScheme::Eip712 => self.create_typed_data_hash(record),
+        }
+    }
+
+    /// Recover the checksummed Ethereum address that signed a meter record
+    pub fn recover_meter_signer(&self, record: &MeterRecord, signature_hex: &str, scheme: SignatureScheme)
+        -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let signature = hex::decode(signature_hex.strip_prefix("0x").unwrap_or(signature_hex))
+            .map_err(|_| SignatureError::InvalidHex)?;
+        self.recover_meter_address(record, &signature, scheme)
+    }
+
+    /// [`Self::recover_meter_signer`] over raw signature bytes, parsed strictly by
+    /// [`RecoverableSig::from_bytes`]. A successful recovery is the verification, so
+    /// this is the only EC operation per record.
+    pub fn recover_meter_address(&self, record: &MeterRecord, signature: &[u8], scheme: SignatureScheme)
+        -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let signature = RecoverableSig::from_bytes(signature)?;
+        let digest = self.meter_signing_digest(record, scheme)?;
+        let address = signer::public_key_to_address(&signature.recover(&digest)?);
+        Ok(signer::to_checksum_address(&address))
+    }
+
+    /// Merkle leaf of a record under `scheme`
+    pub fn hash_record(&self, record: &MeterRecord, scheme: HashScheme) -> Result<String, Box<dyn std::error::Error>> {
+        match scheme {
+            HashScheme::Legacy => self.create_record_hash(record),
+            HashScheme::V1 | HashScheme::V2 => {
+                let mut hasher = Keccak256::new();
+                hasher.update([merkle::LEAF_TAG]);
+                hasher.update(canonical::canonical_meter_record(record).as_bytes());
+                Ok(hex::encode(hasher.finalize()))
+            }
+        }
+    }
+
+    /// Create keccak256 hash of record for Merkle tree (legacy scheme)
     pub fn create_record_hash(&self, record: &MeterRecord) -> Result<String, Box<dyn std::error::Error>> {
-        let message = serde_json::json!({
-            "meter_id": record.meter_id,
-            "timestamp": record.timestamp,
-            "kwh_delta": record.kwh_delta,
-            "nonce": record.nonce
-        });
-        
-        let message_str = serde_json::to_string(&message)?;
+        let message_str = canonical::legacy_meter_record(record);
         let mut hasher = Keccak256::new();
         hasher.update(message_str.as_bytes());
         let hash = hasher.finalize();
@@ -50,80 +117,17 @@ impl CryptoService {
 
     /// Verify ECDSA signature against meter record
     pub fn verify_signature(&self, record: &MeterRecord, signature_hex: &str) -> Result<bool, Box<dyn std::error::Error>> {
-        // Create message hash
-        let message_hash = self.create_message_hash(record)?;
-        let message = Message::from_slice(&message_hash)?;
-
-        // Parse signature (format: 0x + 64 bytes r + 64 bytes s + 2 bytes v)
-        let sig_hex = signature_hex.strip_prefix("0x").unwrap_or(signature_hex);
-        
-        if sig_hex.len() != 130 {
-            return Ok(false);
-        }
-
-        let r_hex = &sig_hex[0..64];
-        let s_hex = &sig_hex[64..128];
-        let v_hex = &sig_hex[128..130];
-
-        let r_bytes = hex::decode(r_hex)?;
-        let s_bytes = hex::decode(s_hex)?;
-        let recovery_id = u8::from_str_radix(v_hex, 16)?;
-
-        // Create recoverable signature
-        let mut sig_bytes = [0u8; 64];
-        sig_bytes[0..32].copy_from_slice(&r_bytes);
-        sig_bytes[32..64].copy_from_slice(&s_bytes);
-
-        let recovery_id = secp256k1::ecdsa::RecoveryId::from_i32(recovery_id as i32)?;
-        let recoverable_sig = RecoverableSignature::from_compact(&sig_bytes, recovery_id)?;
-
-        // Recover public key and verify
-        match self.secp.recover_ecdsa(&message, &recoverable_sig) {
-            Ok(_public_key) => {
-                // Convert to non-recoverable signature for verification
-                let (_, signature) = recoverable_sig.serialize_compact();
-                let sig = Signature::from_compact(&signature)?;
-                
-                // Recover public key again for verification
-                let public_key = self.secp.recover_ecdsa(&message, &recoverable_sig)?;
-                
-                // Verify signature
-                match self.secp.verify_ecdsa(&message, &sig, &public_key) {
-                    Ok(_) => Ok(true),
-                    Err(_) => Ok(false),
-                }
-            }
-            Err(_) => Ok(false),
-        }
+        // Recovery only succeeds for a valid signature; checking it again against the
+        // recovered key would repeat the same EC work
+        Ok(self.recover_meter_signer(record, signature_hex, SignatureScheme::Legacy).is_ok())
     }
 
     /// Extract public key from signature and record
     pub fn recover_public_key(&self, record: &MeterRecord, signature_hex: &str) -> Result<String, Box<dyn std::error::Error>> {
         let message_hash = self.create_message_hash(record)?;
-        let message = Message::from_slice(&message_hash)?;
-
-        let sig_hex = signature_hex.strip_prefix("0x").unwrap_or(signature_hex);
-        
-        if sig_hex.len() != 130 {
-            return Err("Invalid signature length".into());
-        }
-
-        let r_hex = &sig_hex[0..64];
-        let s_hex = &sig_hex[64..128];
-        let v_hex = &sig_hex[128..130];
-
-        let r_bytes = hex::decode(r_hex)?;
-        let s_bytes = hex::decode(s_hex)?;
-        let recovery_id = u8::from_str_radix(v_hex, 16)?;
-
-        let mut sig_bytes = [0u8; 64];
-        sig_bytes[0..32].copy_from_slice(&r_bytes);
-        sig_bytes[32..64].copy_from_slice(&s_bytes);
+        let signature = RecoverableSig::from_hex(signature_hex)?.to_recoverable()?;
 
-        let recovery_id = secp256k1::ecdsa::RecoveryId::from_i32(recovery_id as i32)?;
-        let recoverable_sig = RecoverableSignature::from_compact(&sig_bytes, recovery_id)?;
-
-        let public_key = self.secp.recover_ecdsa(&message, &recoverable_sig)?;
+        let public_key = self.secp.recover_ecdsa(&Message::from_digest(message_hash), &signature)?;
         Ok(hex::encode(public_key.serialize_uncompressed()))
     }
 
@@ -133,21 +137,77 @@ impl CryptoService {
             return vec![false; kwh_values.len()];
         }
 
-        // Calculate mean and standard deviation
-        let mean = kwh_values.iter().sum::<f64>() / kwh_values.len() as f64;
-        let variance = kwh_values.iter()
-            .map(|x| (x - mean).powi(2))
-            .sum::<f64>() / kwh_values.len() as f64;
-        let std_dev = variance.sqrt();
-
-        // Mark outliers (values beyond threshold * std_dev from mean)
-        let threshold = threshold_multiplier * std_dev;
+        // Compare each value against the mean and standard deviation of the others, so a single
+        // spike cannot inflate the spread enough to hide itself in a small window. Deviations are
+        // taken from the overall mean: `x - mean of others = d * n / (n - 1)` and the others'
+        // squared deviations sum to `Σd² - d² * n / (n - 1)`.
+        let n = kwh_values.len() as f64;
+        let mean = kwh_values.iter().sum::<f64>() / n;
+        let squares: f64 = kwh_values.iter().map(|x| (x - mean).powi(2)).sum();
         kwh_values.iter()
-            .map(|&value| (value - mean).abs() > threshold)
+            .map(|&value| {
+                let deviation = (value - mean) * n / (n - 1.0);
+                let variance = ((squares - (value - mean).powi(2) * n / (n - 1.0)) / (n - 1.0)).max(0.0);
+                // Differences below the micro-kWh resolution of proofs are rounding noise
+                deviation.abs() > threshold_multiplier * variance.sqrt() && deviation.abs() >= 0.5e-6
+            })
             .collect()
     }
 }
 
+/// Tag of proof hash preimages that also commit to the hash scheme and sum root
+pub const PROOF_HASH_V2_TAG: &str = "greenshare.proof.v2";
+
+/// Keccak-256 commitment to the public fields of a proof, as 0x-prefixed hex.
+/// Legacy proofs (no hash scheme, no sum root) keep the original preimage
+/// `proof_id|merkle_root|aggregate micro-kWh|window_start ms|window_end ms|record_count`;
+/// all others hash `greenshare.proof.v2|<that>|hash scheme number|sum root hash` (empty without one).
+pub fn proof_hash(proof: &ProofData) -> String {
+    let mut preimage = format!(
+        "{}|{}|{}|{}|{}|{}",
+        proof.proof_id,
+        proof.merkle_root,
+        proof.aggregate_micro_kwh(),
+        proof.window_start.timestamp_millis(),
+        proof.window_end.timestamp_millis(),
+        proof.record_count,
+    );
+    if !proof.hash_scheme.is_legacy() || proof.sum_root.is_some() {
+        preimage = format!(
+            "{}|{}|{}|{}",
+            PROOF_HASH_V2_TAG,
+            preimage,
+            proof.hash_scheme.number(),
+            proof.sum_root.as_ref().map_or("", |sum_root| sum_root.hash.as_str()),
+        );
+    }
+
+    let mut hasher = Keccak256::new();
+    hasher.update(preimage.as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Tag of accumulator leaf preimages
+pub const PROOF_LEAF_V1_TAG: &str = "greenshare.mmr.leaf.v1";
+
+/// Digest a finalized proof is appended to the accumulator under, committing to everything the
+/// proof carries once finalized: keccak of `greenshare.mmr.leaf.v1|proof hash|hash scheme number|
+/// sum root hash|state root`, commitments the proof lacks left empty, as 0x-prefixed hex.
+pub fn proof_leaf_hash(proof: &ProofData) -> String {
+    let preimage = format!(
+        "{}|{}|{}|{}|{}",
+        PROOF_LEAF_V1_TAG,
+        proof_hash(proof),
+        proof.hash_scheme.number(),
+        proof.sum_root.as_ref().map_or("", |sum_root| sum_root.hash.as_str()),
+        proof.state_root.as_deref().unwrap_or(""),
+    );
+
+    let mut hasher = Keccak256::new();
+    hasher.update(preimage.as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
 impl Default for CryptoService {
     fn default() -> Self {
         Self::new()
@@ -157,6 +217,7 @@ impl Default for CryptoService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::RecordEncoding;
 
     #[test]
     fn test_message_hash_consistency() {
@@ -166,6 +227,7 @@ mod tests {
             timestamp: 1640995200000,
             kwh_delta: 1.234,
             nonce: "test_nonce".to_string(),
+            encoding: RecordEncoding::V1,
         };
 
         let hash1 = crypto.create_message_hash(&record).unwrap();
@@ -182,10 +244,131 @@ mod tests {
             timestamp: 1640995200000,
             kwh_delta: 1.234,
             nonce: "test_nonce".to_string(),
+            encoding: RecordEncoding::V1,
         };
 
         let hash = crypto.create_record_hash(&record).unwrap();
         assert_eq!(hash.len(), 64); // keccak256 produces 32 bytes = 64 hex chars
+        assert_eq!(crypto.hash_record(&record, HashScheme::Legacy).unwrap(), hash);
+
+        // v1 leaves are the tagged canonical encoding, never a valid node preimage
+        let mut preimage = vec![merkle::LEAF_TAG];
+        preimage.extend_from_slice(canonical::canonical_meter_record(&record).as_bytes());
+        assert_eq!(crypto.hash_record(&record, HashScheme::V1).unwrap(), hex::encode(Keccak256::digest(&preimage)));
+    }
+
+    #[test]
+    fn test_proof_hash_commits_to_aggregate() {
+        use crate::sum_tree::SumRoot;
+        use chrono::{TimeZone, Utc};
+
+        let mut proof = ProofData {
+            proof_id: "6f1c2a9e-4b7d-4c1e-9a3f-2d5b8e0c7a14".parse().unwrap(),
+            aggregate_kwh: 123.45,
+            merkle_root: "0x1234567890abcdef".to_string(),
+            window_start: Utc.timestamp_millis_opt(1640995200000).unwrap(),
+            window_end: Utc.timestamp_millis_opt(1640998800000).unwrap(),
+            record_count: 10,
+            meter_ids: vec!["meter_001".to_string()],
+            generated_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::Legacy,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
+        };
+
+        let hash = proof_hash(&proof);
+        assert!(hash.starts_with("0x"));
+        assert_eq!(hash.len(), 66);
+
+        // Meter IDs are not part of the commitment, the aggregate is
+        proof.meter_ids.clear();
+        assert_eq!(proof_hash(&proof), hash);
+        proof.aggregate_kwh = 123.46;
+        assert_ne!(proof_hash(&proof), hash);
+        let legacy = proof_hash(&proof);
+        assert_eq!(legacy, format!("0x{}", hex::encode(Keccak256::digest(
+            "6f1c2a9e-4b7d-4c1e-9a3f-2d5b8e0c7a14|0x1234567890abcdef|123460000|1640995200000|1640998800000|10"
+        ))));
+
+        // The hash scheme and sum root are committed to once a proof has either
+        proof.hash_scheme = HashScheme::V2;
+        let scheme_hash = proof_hash(&proof);
+        assert_ne!(scheme_hash, legacy);
+        proof.sum_root = Some(SumRoot { hash: "22".repeat(32), total_micro_kwh: 123_460_000 });
+        let sum_hash = proof_hash(&proof);
+        assert_ne!(sum_hash, scheme_hash);
+        proof.sum_root = Some(SumRoot { hash: "33".repeat(32), total_micro_kwh: 123_460_000 });
+        assert_ne!(proof_hash(&proof), sum_hash);
+
+        // Accumulator leaves also commit to the state root
+        let leaf = proof_leaf_hash(&proof);
+        assert_ne!(leaf, proof_hash(&proof));
+        proof.state_root = Some("44".repeat(32));
+        assert_ne!(proof_leaf_hash(&proof), leaf);
+    }
+
+    #[test]
+    fn test_meter_signature_schemes_recover_signer() {
+        let crypto = CryptoService::new();
+        let meter = signer::EnclaveSigner::from_hex(&format!("0x{}", "42".repeat(32))).unwrap();
+        let record = MeterRecord {
+            meter_id: "test_meter".to_string(),
+            timestamp: 1640995200000,
+            kwh_delta: 1.234,
+            nonce: "test_nonce".to_string(),
+            encoding: RecordEncoding::V1,
+        };
+
+        for scheme in [SignatureScheme::Legacy, SignatureScheme::Eip191, SignatureScheme::Eip712] {
+            let digest = crypto.meter_signing_digest(&record, scheme).unwrap();
+            let signature = format!("0x{}", hex::encode(meter.sign_digest(&digest)));
+
+            assert_eq!(crypto.recover_meter_signer(&record, &signature, scheme).unwrap(), meter.address());
+        }
+
+        // personal_sign as done by wallets
+        let signature = meter.sign_message(crypto.create_message(&record).as_bytes());
+        assert_eq!(crypto.recover_meter_signer(&record, &signature, SignatureScheme::Eip191).unwrap(), meter.address());
+
+        // A signature under one scheme does not carry over to another
+        let digest = crypto.meter_signing_digest(&record, SignatureScheme::Eip712).unwrap();
+        let signature = format!("0x{}", hex::encode(meter.sign_digest(&digest)));
+        assert_ne!(crypto.recover_meter_signer(&record, &signature, SignatureScheme::Eip191).unwrap(), meter.address());
+        assert!(crypto.recover_meter_signer(&record, "0x1234", SignatureScheme::Eip712).is_err());
+
+        // Pre-v1 signatures verify against untagged records only
+        let legacy_record = MeterRecord { encoding: RecordEncoding::Legacy, ..record.clone() };
+        let digest: [u8; 32] = Sha256::digest(canonical::legacy_meter_record(&record).as_bytes()).into();
+        let signature = format!("0x{}", hex::encode(meter.sign_digest(&digest)));
+        assert_eq!(crypto.recover_meter_signer(&legacy_record, &signature, SignatureScheme::Legacy).unwrap(), meter.address());
+        assert_ne!(crypto.recover_meter_signer(&record, &signature, SignatureScheme::Legacy).unwrap(), meter.address());
+    }
+
+    #[test]
+    fn test_meter_signature_vectors() {
+        // Shared with tests/crypto.test.ts, signed there by `signMeterRecord`
+        let crypto = CryptoService::new();
+        let vectors: Vec<serde_json::Value> = serde_json::from_str(include_str!("../tests/vectors/meter-signature-v1.json")).unwrap();
+        assert!(!vectors.is_empty());
+
+        for vector in vectors {
+            let record: MeterRecord = serde_json::from_value(vector["record"].clone()).unwrap();
+            let signature = vector["signature"].as_str().unwrap();
+            let meter = signer::EnclaveSigner::from_hex(vector["private_key"].as_str().unwrap()).unwrap();
+            assert_eq!(meter.address(), vector["address"].as_str().unwrap());
+
+            assert_eq!(
+                crypto.recover_meter_signer(&record, signature, SignatureScheme::Legacy).unwrap(),
+                meter.address(),
+                "{}", vector["name"]
+            );
+
+            // Both sides sign deterministically (RFC 6979) and low-S, so the bytes agree
+            let digest = crypto.meter_signing_digest(&record, SignatureScheme::Legacy).unwrap();
+            assert_eq!(RecoverableSig::from_hex(signature).unwrap().to_bytes(), meter.sign_digest(&digest));
+        }
     }
 
     #[test]
@@ -196,5 +379,7 @@ mod tests {
         
         assert_eq!(outliers.len(), 5);
         assert!(outliers[3]); // 10.0 should be marked as outlier
+        assert_eq!(outliers.iter().filter(|&&outlier| outlier).count(), 1);
+        assert!(crypto.detect_outliers(&[0.1, 0.1, 0.1], 1.0).iter().all(|&outlier| !outlier));
     }
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 // FILE: src/crypto.ts
 import { ec as EC } from 'elliptic';
 import crypto from 'crypto';
+import { ethers } from 'ethers';
 
 // Initialize secp256k1 curve
 const ec = new EC('secp256k1');
@@ -10,13 +11,49 @@ export interface MeterRecord {
   timestamp: number;
   kwh_delta: number;
   nonce: string;
+  /**
+   * Must be METER_RECORD_ENCODING for records signed with the functions below; the enclave
+   * reads untagged records as pre-v1 JSON
+   */
+  encoding?: typeof METER_RECORD_ENCODING;
 }
 
+export type SignatureScheme = 'legacy' | 'eip191' | 'eip712';
+
 export interface SignedMeterData {
   record: MeterRecord;
   sig: string;
+  /** Defaults to 'legacy' on the enclave */
+  scheme?: SignatureScheme;
+  /** Device certificate from a trust anchor, for meters that were never registered */
+  certificate?: DeviceCertificate;
+}
+
+/** Trust anchor signature over a meter key (see `attestation::DeviceCertificate`) */
+export interface DeviceCertificate {
+  serial: string;
+  issuer: string;
+  meter_id: string;
+  scheme?: 'secp256k1-recoverable' | 'secp256k1' | 'ed25519' | 'p256';
+  public_key: string;
+  capacity_kw: number;
+  installation?: Record<string, unknown>;
+  not_before: number;
+  not_after: number;
+  signature: string;
 }
 
+/** EIP-712 domain and types meters sign under (see `CryptoService::create_typed_data_hash`) */
+export const METER_EIP712_DOMAIN = { name: 'GreenShare Meter', version: '1' };
+export const METER_EIP712_TYPES = {
+  MeterRecord: [
+    { name: 'meterId', type: 'string' },
+    { name: 'timestamp', type: 'int64' },
+    { name: 'kwhDeltaMicro', type: 'uint64' },
+    { name: 'nonce', type: 'string' }
+  ]
+};
+
 /**
  * Generate a new ECDSA key pair for meter
  */
@@ -68,29 +105,59 @@ export function generateNonce(): string {
   return crypto.randomBytes(16).toString('hex');
 }
 
+/** Version tag embedded in every canonical meter record message */
+export const METER_RECORD_ENCODING = 'greenshare.meter-record.v1';
+
 /**
- * Create deterministic message hash for signing
+ * RFC 8785 (JCS) serialization: keys sorted by UTF-16 code units, no whitespace.
+ * Must match `canonical::jcs` in the enclave (see tests/vectors/meter-record-v1.json)
  */
-export function createMessageHash(record: MeterRecord): string {
-  const message = JSON.stringify({
+export function canonicalize(value: unknown): string {
+  if (value === null || typeof value !== 'object') {
+    return JSON.stringify(value);
+  }
+  if (Array.isArray(value)) {
+    return `[${value.map(canonicalize).join(',')}]`;
+  }
+
+  const object = value as Record<string, unknown>;
+  const keys = Object.keys(object).filter(key => object[key] !== undefined).sort();
+  return `{${keys.map(key => `${JSON.stringify(key)}:${canonicalize(object[key])}`).join(',')}}`;
+}
+
+/**
+ * The message a meter signs: JCS of the record plus the `encoding` version tag
+ */
+export function canonicalMeterRecord(record: MeterRecord): string {
+  return canonicalize({
+    encoding: METER_RECORD_ENCODING,
     meter_id: record.meter_id,
     timestamp: record.timestamp,
     kwh_delta: record.kwh_delta,
     nonce: record.nonce
   });
-  
+}
+
+/**
+ * Create deterministic message hash for signing
+ */
+export function createMessageHash(record: MeterRecord): string {
+  const message = canonicalMeterRecord(record);
+
   return crypto.createHash('sha256').update(message, 'utf8').digest('hex');
 }
 
 /**
- * Sign meter record with ECDSA
+ * Sign meter record with ECDSA; `s` is kept in the lower half of the curve order, as the
+ * enclave rejects high-S signatures
  */
 export function signMeterRecord(record: MeterRecord, privateKeyHex: string): string {
   try {
     const keyPair = ec.keyFromPrivate(privateKeyHex);
     const messageHash = createMessageHash(record);
     
-    const signature = keyPair.sign(messageHash);
+    // `canonical` negates a high `s` and flips `recoveryParam` to match
+    const signature = keyPair.sign(messageHash, { canonical: true });
     const r = signature.r.toString('hex').padStart(64, '0');
     const s = signature.s.toString('hex').padStart(64, '0');
     const v = (signature.recoveryParam || 0).toString(16).padStart(2, '0');
@@ -101,6 +168,36 @@ export function signMeterRecord(record: MeterRecord, privateKeyHex: string): str
   }
 }
 
+/**
+ * Sign meter record with `personal_sign` over the canonical record (scheme 'eip191')
+ */
+export async function signMeterRecordEip191(record: MeterRecord, signer: ethers.Signer): Promise<string> {
+  return signer.signMessage(canonicalMeterRecord(record));
+}
+
+/**
+ * EIP-712 value of a meter record; energy is signed in micro-kWh
+ */
+export function meterRecordTypedData(record: MeterRecord) {
+  if (record.kwh_delta < 0) {
+    throw new Error('EIP-712 records need a non-negative kwh_delta');
+  }
+
+  return {
+    meterId: record.meter_id,
+    timestamp: record.timestamp,
+    kwhDeltaMicro: Math.round(record.kwh_delta * 1_000_000),
+    nonce: record.nonce
+  };
+}
+
+/**
+ * Sign meter record as EIP-712 typed data (scheme 'eip712')
+ */
+export async function signMeterRecordEip712(record: MeterRecord, signer: ethers.Signer): Promise<string> {
+  return signer.signTypedData(METER_EIP712_DOMAIN, METER_EIP712_TYPES, meterRecordTypedData(record));
+}
+
 /**
  * Verify signature (for testing purposes)
  */
@@ -133,7 +230,8 @@ export function createSignedMeterData(
     meter_id: meterId,
     timestamp: Date.now(),
     kwh_delta: kwhDelta,
-    nonce: generateNonce()
+    nonce: generateNonce(),
+    encoding: METER_RECORD_ENCODING
   };
   
   const signature = signMeterRecord(record, privateKeyHex);
@@ -142,4 +240,43 @@ export function createSignedMeterData(
     record,
     sig: signature
   };
-}
\ No newline at end of file
+}
+/** Sibling of the last node of an odd level (`merkle::EMPTY_NODE`) */
+const MERKLE_EMPTY_NODE = '0x' + '00'.repeat(32);
+
+function prefixedHex(value: string): string {
+  return (value.startsWith('0x') ? value : `0x${value}`).toLowerCase();
+}
+
+/** Positional v2 node hash: keccak256(0x01 || left || right) */
+function merkleNodeV2(left: string, right: string): string {
+  return ethers.keccak256(ethers.concat(['0x01', left, right]));
+}
+
+/**
+ * Root of a `greenshare.merkle.v2` tree over hex leaf hashes (see tests/vectors/merkle-v2.json)
+ */
+export function merkleRootV2(leaves: string[]): string {
+  let level = leaves.map(prefixedHex);
+  while (level.length > 1) {
+    if (level.length % 2 === 1) level.push(MERKLE_EMPTY_NODE);
+    const next: string[] = [];
+    for (let i = 0; i < level.length; i += 2) {
+      next.push(merkleNodeV2(level[i], level[i + 1]));
+    }
+    level = next;
+  }
+  return level[0].slice(2);
+}
+
+/**
+ * Check a `greenshare.merkle.v2` proof of the leaf at `index`
+ */
+export function verifyMerkleProofV2(leaf: string, proof: string[], root: string, index: number): boolean {
+  let current = prefixedHex(leaf);
+  for (const sibling of proof) {
+    current = index % 2 === 0 ? merkleNodeV2(current, prefixedHex(sibling)) : merkleNodeV2(prefixedHex(sibling), current);
+    index = Math.floor(index / 2);
+  }
+  return current === prefixedHex(root);
+}
@@ -0,0 +1,291 @@
+//! Sealed payload envelopes: AES-256-GCM ciphertext, a data key wrapped per recipient via
+//! secp256k1 ECDH, and a public header bound to it as associated data
+
+use aes_gcm::{Aes256Gcm, Nonce};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload, consts::U12};
+use aes_gcm::aead::rand_core::RngCore;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use secp256k1::{PublicKey, Secp256k1, SecretKey, ecdh::SharedSecret};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+use crate::crypto;
+use crate::seal::SealRequest;
+
+/// Identifier of the envelope format, stored alongside every ciphertext
+pub const ENVELOPE_SCHEME: &str = "aes-256-gcm+secp256k1-ecies-v1";
+
+const KEK_DOMAIN: &[u8] = b"greenshare.seal.kek.v1";
+
+/// Party a sealed envelope's data key is wrapped for (auditor, regulator, owner, ...)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recipient {
+    pub label: String,
+    /// Hex-encoded secp256k1 public key (compressed or uncompressed)
+    pub public_key: String,
+}
+
+impl FromStr for Recipient {
+    type Err = String;
+
+    /// Parse `label:public_key_hex`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (label, public_key) = s.trim().split_once(':')
+            .ok_or_else(|| format!("Invalid seal recipient '{}' (expected label:public_key)", s))?;
+        parse_public_key(public_key)?;
+
+        Ok(Recipient {
+            label: label.to_string(),
+            public_key: public_key.trim_start_matches("0x").to_lowercase(),
+        })
+    }
+}
+
+/// Fields of a sealed proof that stay readable without a key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublicHeader {
+    pub proof_hash: String,
+    pub merkle_root: String,
+    pub aggregate_kwh: f64,
+}
+
+/// Data key wrapped for a single recipient
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrappedKey {
+    pub label: String,
+    pub public_key: String,
+    pub ephemeral_public_key: String,
+    pub nonce: String,
+    pub wrapped_key: String,
+}
+
+/// Encrypted seal envelope as uploaded to Walrus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedEnvelope {
+    pub scheme: String,
+    pub header: PublicHeader,
+    pub nonce: String,
+    pub ciphertext: String,
+    pub recipients: Vec<WrappedKey>,
+}
+
+/// Encrypt a seal envelope under a fresh data key wrapped for each recipient.
+/// The public header is bound to the ciphertext as associated data.
+pub fn encrypt_envelope(seal_request: &SealRequest, recipients: &[Recipient])
+    -> Result<EncryptedEnvelope, Box<dyn std::error::Error + Send + Sync>> {
+
+    if recipients.is_empty() {
+        return Err("Cannot encrypt envelope without recipients".into());
+    }
+
+    let proof = &seal_request.proof_data;
+    let header = PublicHeader {
+        proof_hash: crypto::proof_hash(proof),
+        merkle_root: proof.merkle_root.clone(),
+        aggregate_kwh: proof.aggregate_kwh,
+    };
+    let aad = serde_json::to_vec(&header)?;
+
+    let data_key = Aes256Gcm::generate_key(&mut OsRng);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(seal_request)?;
+    let ciphertext = Aes256Gcm::new(&data_key)
+        .encrypt(&nonce, Payload { msg: &plaintext, aad: &aad })
+        .map_err(|_| "Envelope encryption failed")?;
+
+    let recipients = recipients.iter()
+        .map(|recipient| wrap_key(&data_key[..], recipient, header.proof_hash.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(EncryptedEnvelope {
+        scheme: ENVELOPE_SCHEME.to_string(),
+        header,
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+        recipients,
+    })
+}
+
+/// Decrypt an envelope with a recipient's secret key (hex)
+pub fn decrypt_envelope(envelope: &EncryptedEnvelope, secret_key_hex: &str)
+    -> Result<SealRequest, Box<dyn std::error::Error + Send + Sync>> {
+
+    if envelope.scheme != ENVELOPE_SCHEME {
+        return Err(format!("Unsupported envelope scheme '{}'", envelope.scheme).into());
+    }
+
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&hex::decode(secret_key_hex.trim_start_matches("0x"))?)?;
+    let public_key = secret_key.public_key(&secp);
+
+    let wrapped = envelope.recipients.iter()
+        .find(|r| parse_public_key(&r.public_key).map(|pk| pk == public_key).unwrap_or(false))
+        .ok_or("Key is not a recipient of this envelope")?;
+    let data_key = unwrap_key(wrapped, &secret_key, envelope.header.proof_hash.as_bytes())?;
+
+    let aad = serde_json::to_vec(&envelope.header)?;
+    let nonce = decode_nonce(&envelope.nonce)?;
+    let plaintext = Aes256Gcm::new_from_slice(&data_key)?
+        .decrypt(&nonce, Payload { msg: &BASE64.decode(&envelope.ciphertext)?, aad: &aad })
+        .map_err(|_| "Envelope decryption failed")?;
+
+    let seal_request: SealRequest = serde_json::from_slice(&plaintext)?;
+    if crypto::proof_hash(&seal_request.proof_data) != envelope.header.proof_hash {
+        return Err("Decrypted proof does not match envelope header".into());
+    }
+
+    Ok(seal_request)
+}
+
+fn decode_nonce(encoded: &str) -> Result<Nonce<U12>, Box<dyn std::error::Error + Send + Sync>> {
+    let bytes: [u8; 12] = BASE64.decode(encoded)?
+        .try_into()
+        .map_err(|_| "Invalid nonce length")?;
+    Ok(bytes.into())
+}
+
+fn parse_public_key(public_key_hex: &str) -> Result<PublicKey, String> {
+    let bytes = hex::decode(public_key_hex.trim_start_matches("0x"))
+        .map_err(|e| format!("Invalid recipient public key: {}", e))?;
+    PublicKey::from_slice(&bytes).map_err(|e| format!("Invalid recipient public key: {}", e))
+}
+
+/// Key-encryption key from an ECDH shared secret, bound to both public keys
+fn derive_kek(shared: &SharedSecret, ephemeral: &PublicKey, recipient: &PublicKey) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(KEK_DOMAIN);
+    hasher.update(shared.secret_bytes());
+    hasher.update(ephemeral.serialize());
+    hasher.update(recipient.serialize());
+    hasher.finalize().into()
+}
+
+fn wrap_key(data_key: &[u8], recipient: &Recipient, aad: &[u8])
+    -> Result<WrappedKey, Box<dyn std::error::Error + Send + Sync>> {
+
+    let secp = Secp256k1::new();
+    let recipient_key = parse_public_key(&recipient.public_key)?;
+
+    let mut ephemeral_bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut ephemeral_bytes);
+    let ephemeral_secret = SecretKey::from_slice(&ephemeral_bytes)?;
+    let ephemeral_public = ephemeral_secret.public_key(&secp);
+
+    let shared = SharedSecret::new(&recipient_key, &ephemeral_secret);
+    let kek = derive_kek(&shared, &ephemeral_public, &recipient_key);
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let wrapped = Aes256Gcm::new(&kek.into())
+        .encrypt(&nonce, Payload { msg: data_key, aad })
+        .map_err(|_| "Key wrapping failed")?;
+
+    Ok(WrappedKey {
+        label: recipient.label.clone(),
+        public_key: recipient.public_key.clone(),
+        ephemeral_public_key: hex::encode(ephemeral_public.serialize()),
+        nonce: BASE64.encode(nonce),
+        wrapped_key: BASE64.encode(wrapped),
+    })
+}
+
+fn unwrap_key(wrapped: &WrappedKey, secret_key: &SecretKey, aad: &[u8])
+    -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+
+    let secp = Secp256k1::new();
+    let ephemeral_public = parse_public_key(&wrapped.ephemeral_public_key)?;
+    let shared = SharedSecret::new(&ephemeral_public, secret_key);
+    let kek = derive_kek(&shared, &ephemeral_public, &secret_key.public_key(&secp));
+
+    let nonce = decode_nonce(&wrapped.nonce)?;
+    let data_key = Aes256Gcm::new(&kek.into())
+        .decrypt(&nonce, Payload { msg: &BASE64.decode(&wrapped.wrapped_key)?, aad })
+        .map_err(|_| "Key unwrapping failed")?;
+
+    Ok(data_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{HashScheme, ProofData};
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    const AUDITOR_KEY: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+    const REGULATOR_KEY: &str = "0202020202020202020202020202020202020202020202020202020202020202";
+    const OUTSIDER_KEY: &str = "0303030303030303030303030303030303030303030303030303030303030303";
+
+    fn recipient(label: &str, secret_hex: &str) -> Recipient {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&hex::decode(secret_hex).unwrap()).unwrap();
+        format!("{}:{}", label, hex::encode(secret.public_key(&secp).serialize())).parse().unwrap()
+    }
+
+    fn seal_request() -> SealRequest {
+        SealRequest {
+            proof_data: ProofData {
+                proof_id: Uuid::new_v4(),
+                aggregate_kwh: 42.5,
+                merkle_root: "ab".repeat(32),
+                window_start: Utc::now(),
+                window_end: Utc::now(),
+                record_count: 3,
+                meter_ids: vec!["household_7".to_string()],
+                generated_at: Utc::now(),
+                version: "1.0.0".to_string(),
+                hash_scheme: HashScheme::Legacy,
+                sum_root: None,
+                state_root: None,
+                mmr: None,
+            },
+            metadata: HashMap::new(),
+            walrus_cid: None,
+            records_manifest: None,
+            seal_hash: None,
+            seal_signature: None,
+        }
+    }
+
+    #[test]
+    fn test_envelope_roundtrip_for_each_recipient() {
+        let request = seal_request();
+        let recipients = vec![recipient("auditor", AUDITOR_KEY), recipient("regulator", REGULATOR_KEY)];
+        let envelope = encrypt_envelope(&request, &recipients).unwrap();
+
+        for key in [AUDITOR_KEY, REGULATOR_KEY] {
+            let opened = decrypt_envelope(&envelope, key).unwrap();
+            assert_eq!(opened.proof_data.proof_id, request.proof_data.proof_id);
+            assert_eq!(opened.proof_data.meter_ids, request.proof_data.meter_ids);
+        }
+
+        assert!(decrypt_envelope(&envelope, OUTSIDER_KEY).is_err());
+    }
+
+    #[test]
+    fn test_envelope_hides_meter_ids() {
+        let envelope = encrypt_envelope(&seal_request(), &[recipient("owner", AUDITOR_KEY)]).unwrap();
+        let uploaded = serde_json::to_string(&envelope).unwrap();
+
+        assert!(!uploaded.contains("household_7"));
+        assert_eq!(envelope.header.aggregate_kwh, 42.5);
+        assert_eq!(envelope.header.merkle_root, "ab".repeat(32));
+    }
+
+    #[test]
+    fn test_tampered_header_is_rejected() {
+        let mut envelope = encrypt_envelope(&seal_request(), &[recipient("auditor", AUDITOR_KEY)]).unwrap();
+        envelope.header.aggregate_kwh = 4250.0;
+
+        assert!(decrypt_envelope(&envelope, AUDITOR_KEY).is_err());
+    }
+
+    #[test]
+    fn test_recipient_parsing() {
+        assert!("auditor".parse::<Recipient>().is_err());
+        assert!("auditor:zz".parse::<Recipient>().is_err());
+        assert_eq!(recipient("owner", AUDITOR_KEY).label, "owner");
+    }
+}
@@ -0,0 +1,238 @@
+//! ABI encoding and EIP-712 signatures of proofs for EVM verifier contracts
+//! (see `contracts/RoflProofVerifier.sol`)
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use uuid::Uuid;
+
+use crate::models::ProofData;
+use crate::signer::{self, EnclaveSigner, keccak256};
+
+pub const DOMAIN_NAME: &str = "GreenShare ROFL";
+/// Bumped from "1" when `RoflProof` gained `sumRoot` and `hashScheme`
+pub const DOMAIN_VERSION: &str = "2";
+
+const EIP712_DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const ROFL_PROOF_TYPE: &str =
+    "RoflProof(bytes16 proofId,bytes32 merkleRoot,uint256 aggregateMicroKwh,uint64 windowStart,uint64 windowEnd,uint32 recordCount,bytes32 sumRoot,uint8 hashScheme)";
+
+/// EIP-712 domain the proof signature is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvmDomain {
+    pub chain_id: u64,
+    pub verifying_contract: [u8; 20],
+}
+
+impl EvmDomain {
+    pub fn new(chain_id: u64, verifying_contract: &str) -> Result<Self, String> {
+        let bytes = hex::decode(verifying_contract.trim_start_matches("0x"))
+            .map_err(|_| format!("Invalid verifying contract '{}'", verifying_contract))?;
+        let verifying_contract: [u8; 20] = bytes.try_into()
+            .map_err(|_| format!("Invalid verifying contract '{}'", verifying_contract))?;
+
+        Ok(Self { chain_id, verifying_contract })
+    }
+
+    pub fn separator(&self) -> [u8; 32] {
+        domain_separator(DOMAIN_NAME, DOMAIN_VERSION, self.chain_id, &self.verifying_contract)
+    }
+}
+
+/// Proof in ABI form together with the enclave's EIP-712 signature over it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SignedEvmProof {
+    pub proof_id: Uuid,
+    pub chain_id: u64,
+    pub verifying_contract: String,
+    /// `abi.encode(RoflProof)`
+    pub encoded: String,
+    pub struct_hash: String,
+    pub domain_separator: String,
+    /// `keccak256(0x1901 || domainSeparator || structHash)`
+    pub digest: String,
+    /// 65-byte `r || s || v` signature (v in {27, 28}) for `ecrecover`
+    pub signature: String,
+    pub signer: String,
+}
+
+fn word_u64(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn word_address(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+/// `keccak256(abi.encode(typeHash(EIP712Domain), name, version, chainId, verifyingContract))`
+pub fn domain_separator(name: &str, version: &str, chain_id: u64, verifying_contract: &[u8; 20]) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(5 * 32);
+    encoded.extend_from_slice(&keccak256(EIP712_DOMAIN_TYPE.as_bytes()));
+    encoded.extend_from_slice(&keccak256(name.as_bytes()));
+    encoded.extend_from_slice(&keccak256(version.as_bytes()));
+    encoded.extend_from_slice(&word_u64(chain_id));
+    encoded.extend_from_slice(&word_address(verifying_contract));
+    keccak256(&encoded)
+}
+
+fn word_hash(name: &str, hash: &str) -> Result<[u8; 32], String> {
+    hex::decode(hash.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("{} '{}' is not 32 bytes", name, hash))
+}
+
+/// `abi.encode(RoflProof)`: eight static 32-byte words; `sumRoot` is zero for proofs without one
+pub fn abi_encode_proof(proof: &ProofData) -> Result<Vec<u8>, String> {
+    let merkle_root = word_hash("Merkle root", &proof.merkle_root)?;
+    let sum_root = match &proof.sum_root {
+        Some(sum_root) => word_hash("Sum root", &sum_root.hash)?,
+        None => [0u8; 32],
+    };
+    let window_start = u64::try_from(proof.window_start.timestamp()).map_err(|_| "Window start before 1970")?;
+    let window_end = u64::try_from(proof.window_end.timestamp()).map_err(|_| "Window end before 1970")?;
+    let record_count = u32::try_from(proof.record_count).map_err(|_| "Record count exceeds uint32")?;
+
+    // bytes16 is left-aligned in its word
+    let mut proof_id = [0u8; 32];
+    proof_id[..16].copy_from_slice(proof.proof_id.as_bytes());
+
+    let mut encoded = Vec::with_capacity(8 * 32);
+    encoded.extend_from_slice(&proof_id);
+    encoded.extend_from_slice(&merkle_root);
+    encoded.extend_from_slice(&word_u64(proof.aggregate_micro_kwh()));
+    encoded.extend_from_slice(&word_u64(window_start));
+    encoded.extend_from_slice(&word_u64(window_end));
+    encoded.extend_from_slice(&word_u64(record_count as u64));
+    encoded.extend_from_slice(&sum_root);
+    encoded.extend_from_slice(&word_u64(proof.hash_scheme.number() as u64));
+    Ok(encoded)
+}
+
+/// EIP-712 `hashStruct(RoflProof)`; all members are static, so this is the typehash plus the ABI words
+pub fn proof_struct_hash(proof: &ProofData) -> Result<[u8; 32], String> {
+    let mut encoded = keccak256(ROFL_PROOF_TYPE.as_bytes()).to_vec();
+    encoded.extend_from_slice(&abi_encode_proof(proof)?);
+    Ok(keccak256(&encoded))
+}
+
+/// Digest a contract passes to `ecrecover`
+pub fn typed_data_digest(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    let mut message = Vec::with_capacity(66);
+    message.extend_from_slice(b"\x19\x01");
+    message.extend_from_slice(domain_separator);
+    message.extend_from_slice(struct_hash);
+    keccak256(&message)
+}
+
+/// ABI-encode a proof and sign its EIP-712 digest with the enclave key
+pub fn sign_proof(signer: &EnclaveSigner, proof: &ProofData, domain: &EvmDomain) -> Result<SignedEvmProof, String> {
+    let encoded = abi_encode_proof(proof)?;
+    let struct_hash = proof_struct_hash(proof)?;
+    let separator = domain.separator();
+    let digest = typed_data_digest(&separator, &struct_hash);
+
+    Ok(SignedEvmProof {
+        proof_id: proof.proof_id,
+        chain_id: domain.chain_id,
+        verifying_contract: signer::to_checksum_address(&domain.verifying_contract),
+        encoded: format!("0x{}", hex::encode(encoded)),
+        struct_hash: format!("0x{}", hex::encode(struct_hash)),
+        domain_separator: format!("0x{}", hex::encode(separator)),
+        digest: format!("0x{}", hex::encode(digest)),
+        signature: format!("0x{}", hex::encode(signer.sign_digest(&digest))),
+        signer: signer.address(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HashScheme;
+    use crate::sum_tree::SumRoot;
+    use chrono::{TimeZone, Utc};
+
+    const TEST_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    fn proof() -> ProofData {
+        ProofData {
+            proof_id: Uuid::parse_str("6f1c2a9e-4b7d-4c1e-9a3f-2d5b8e0c7a14").unwrap(),
+            aggregate_kwh: 123.45,
+            merkle_root: "11".repeat(32),
+            window_start: Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap(),
+            window_end: Utc.with_ymd_and_hms(2024, 1, 1, 11, 0, 0).unwrap(),
+            record_count: 10,
+            meter_ids: vec!["meter_001".to_string()],
+            generated_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::Legacy,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
+        }
+    }
+
+    /// Current proofs also commit to their hash scheme and sum root
+    fn committed_proof() -> ProofData {
+        ProofData {
+            hash_scheme: HashScheme::V2,
+            sum_root: Some(SumRoot { hash: "22".repeat(32), total_micro_kwh: 123_450_000 }),
+            ..proof()
+        }
+    }
+
+    #[test]
+    fn test_domain_separator_matches_eip712_example() {
+        // "Ether Mail" domain from the EIP-712 specification
+        let contract: [u8; 20] = hex::decode("CcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC").unwrap().try_into().unwrap();
+        let separator = domain_separator("Ether Mail", "1", 1, &contract);
+
+        assert_eq!(hex::encode(separator), "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f");
+    }
+
+    #[test]
+    fn test_abi_encoding_layout() {
+        let encoded = abi_encode_proof(&proof()).unwrap();
+
+        assert_eq!(encoded.len(), 8 * 32);
+        assert_eq!(&encoded[..16], Uuid::parse_str("6f1c2a9e-4b7d-4c1e-9a3f-2d5b8e0c7a14").unwrap().as_bytes());
+        assert_eq!(&encoded[16..32], &[0u8; 16]);
+        assert_eq!(&encoded[32..64], &[0x11u8; 32]);
+        assert_eq!(&encoded[88..96], &123_450_000u64.to_be_bytes());
+        assert_eq!(&encoded[184..192], &10u64.to_be_bytes());
+        assert_eq!(&encoded[192..], &[0u8; 64]);
+
+        let encoded = abi_encode_proof(&committed_proof()).unwrap();
+        assert_eq!(&encoded[192..224], &[0x22u8; 32]);
+        assert_eq!(encoded[255], 2);
+        assert_ne!(proof_struct_hash(&committed_proof()).unwrap(), proof_struct_hash(&proof()).unwrap());
+
+        let mut bad_root = proof();
+        bad_root.merkle_root = "0x1234".to_string();
+        assert!(abi_encode_proof(&bad_root).is_err());
+    }
+
+    #[test]
+    fn test_signature_recovers_to_enclave() {
+        // Values shared with test/RoflProofVerifier.t.sol
+        let signer = EnclaveSigner::from_hex(TEST_KEY).unwrap();
+        let domain = EvmDomain::new(48899, "0x5615dEB798BB3E4dFa0139dFa1b3D433Cc23b72f").unwrap();
+        let signed = sign_proof(&signer, &committed_proof(), &domain).unwrap();
+
+        let digest: [u8; 32] = hex::decode(&signed.digest[2..]).unwrap().try_into().unwrap();
+        let signature: [u8; 65] = hex::decode(&signed.signature[2..]).unwrap().try_into().unwrap();
+        let recovered = signer::recover_address(&digest, &signature).unwrap();
+
+        assert_eq!(signer::to_checksum_address(&recovered), signer.address());
+        assert!(signature[64] == 27 || signature[64] == 28);
+        assert_eq!(signed.digest, VECTOR_DIGEST);
+        assert_eq!(signed.signature, VECTOR_SIGNATURE);
+    }
+
+    const VECTOR_DIGEST: &str = "0x5703795eeab3e04654a4a3487adb41554c3d12057a7f51a0070f1484e2d72808";
+    const VECTOR_SIGNATURE: &str = "0x92d15b75a415d85055d71e6419c28773a99993eb54db5605b42e0a8c7e2d51ae00e74d45153ec7d0514fc346ccdc7476ee930e32a6548ed47be212d9c21ac5a21b";
+}
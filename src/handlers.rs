@@ -1,5 +1,6 @@
 // FILE: src/handlers.rs
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{web, Handler, FromRequest, HttpResponse, Responder, Result, Route};
+use actix_web::http::Method;
 use chrono::Utc;
 use log::{info, warn, error};
 use std::sync::Arc;
@@ -9,7 +10,18 @@ use uuid::Uuid;
 use crate::config::Config;
 use crate::models::*;
 use crate::aggregator::DataAggregator;
-use crate::seal::SealService;
+use crate::attestation::{CertificateError, DeviceCertificate};
+use crate::ingest::VerifierPool;
+use crate::seal::{self, BudgetExceeded, WalrusClient};
+use crate::sui::MintTxBuilder;
+use crate::certificate;
+use crate::evm::{self, EvmDomain};
+use crate::merkle::MerkleTree;
+use crate::openapi;
+use crate::sum_tree::MerkleSumTree;
+use crate::signer::EnclaveSigner;
+use crate::quota;
+use crate::registry::{KeyRotation, RotationError};
 
 /// Health check endpoint
 pub async fn health_check() -> Result<HttpResponse> {
@@ -19,13 +31,18 @@ pub async fn health_check() -> Result<HttpResponse> {
         uptime_seconds: std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
-            .as_secs()
+            .as_secs(),
         version: "1.0.0".to_string(),
     };
     
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// OpenAPI 3 document of the versioned API
+pub async fn get_openapi() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(openapi::document()))
+}
+
 /// Get enclave status and statistics
 pub async fn get_status(
     aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
@@ -55,6 +72,7 @@ pub async fn get_status(
 pub async fn ingest_data(
     payload: web::Json<SignedMeterData>,
     aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+    verifier: web::Data<VerifierPool>,
 ) -> Result<HttpResponse> {
     let data = payload.into_inner();
     
@@ -72,9 +90,35 @@ pub async fn ingest_data(
         }));
     }
 
-    // Process the record
-    let mut aggregator = aggregator.lock().await;
-    match aggregator.process_record(data.record, data.sig).await {
+    // A device certificate vouches for the key of a meter that was never registered
+    if let Some(certificate) = data.certificate {
+        if let Err(e) = verifier.enroll(data.record.meter_id.clone(), certificate).await {
+            warn!("Rejected device certificate for meter {}: {}", data.record.meter_id, e);
+            let (mut status_code, error_code) = certificate_error_status(e.as_ref());
+            if error_code == "INVALID_CERTIFICATE" {
+                aggregator.lock().await.record_signature_rejection();
+            }
+            return Ok(status_code.json(ErrorResponse {
+                error: "Failed to process meter data".to_string(),
+                code: error_code.to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e.to_string() })),
+            }));
+        }
+    }
+
+    // Verify on the worker pool, then take the lock only to insert into the window
+    let result = match verifier.verify(data.record, data.sig, data.scheme).await {
+        Ok(verified) => aggregator.lock().await.insert_record(verified).await,
+        Err(e) => {
+            if e.to_string().starts_with("Invalid signature") {
+                aggregator.lock().await.record_signature_rejection();
+            }
+            Err(e)
+        }
+    };
+
+    match result {
         Ok(receipt_id) => {
             let response = IngestResponse {
                 success: true,
@@ -88,7 +132,7 @@ pub async fn ingest_data(
             error!("Failed to process meter record: {}", e);
             
             // Determine appropriate error code and status
-            let (status_code, error_code) = if e.to_string().contains("Invalid signature") {
+            let (mut status_code, error_code) = if e.to_string().contains("Invalid signature") {
                 (HttpResponse::Unauthorized(), "INVALID_SIGNATURE")
             } else if e.to_string().contains("Duplicate record") {
                 (HttpResponse::Conflict(), "DUPLICATE_RECORD")
@@ -138,6 +182,635 @@ pub async fn get_latest_proof(
     }
 }
 
+/// Build the unsigned Sui `mint_from_proof` transaction for a stored proof
+pub async fn get_sui_mint_tx(
+    path: web::Path<Uuid>,
+    query: web::Query<SuiMintTxQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse> {
+    let proof_id = path.into_inner();
+
+    let builder = match MintTxBuilder::from_config(&config.sui) {
+        Ok(builder) => builder,
+        Err(e) => {
+            warn!("Sui mint transaction requested but not configured: {}", e);
+            return Ok(HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                error: "Sui minting is not configured".to_string(),
+                code: "SUI_NOT_CONFIGURED".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e })),
+            }));
+        }
+    };
+
+    let proof = match load_proof(&*aggregator.lock().await, &proof_id).await {
+        Ok(proof) => proof,
+        Err(response) => return Ok(response),
+    };
+
+    let builder = match (query.treasury_cap_version, &query.treasury_cap_digest) {
+        (Some(version), Some(digest)) => builder.with_treasury_cap_ref(version, digest),
+        (None, None) => Ok(builder),
+        _ => Err("treasury_cap_version and treasury_cap_digest must be given together".to_string()),
+    };
+
+    match builder.and_then(|builder| builder.build(&proof, &query.recipient)) {
+        Ok(tx) => {
+            info!("Built Sui mint transaction for proof {} ({} micro-sKWH)", proof_id, tx.amount);
+            Ok(HttpResponse::Ok().json(tx))
+        }
+        Err(e) => {
+            Ok(HttpResponse::BadRequest().json(ErrorResponse {
+                error: "Cannot build mint transaction".to_string(),
+                code: "INVALID_REQUEST".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e })),
+            }))
+        }
+    }
+}
+
+/// Certificate issuance payload for a sealed proof
+pub async fn get_certificate_payload(
+    path: web::Path<Uuid>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse> {
+    let proof_id = path.into_inner();
+
+    let salt = match certificate::household_salt(&config) {
+        Ok(salt) => salt,
+        Err(e) => {
+            warn!("Certificate payload requested without a household salt: {}", e);
+            return Ok(HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                error: "Household hashing is not configured".to_string(),
+                code: "HOUSEHOLD_SALT_NOT_CONFIGURED".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e })),
+            }));
+        }
+    };
+
+    let aggregator = aggregator.lock().await;
+    let proof = match load_proof(&aggregator, &proof_id).await {
+        Ok(proof) => proof,
+        Err(response) => return Ok(response),
+    };
+    let receipt = aggregator.get_seal_receipt(&proof_id).await;
+    drop(aggregator);
+
+    match receipt {
+        Ok(Some(receipt)) => match certificate::build_payload(&proof, &receipt, &salt) {
+            Ok(payload) => Ok(HttpResponse::Ok().json(payload)),
+            Err(e) => Ok(HttpResponse::Conflict().json(ErrorResponse {
+                error: "Cannot build certificate payload".to_string(),
+                code: "PROOF_NOT_SEALED".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e })),
+            })),
+        },
+        Ok(None) => Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: format!("Proof {} has not been sealed", proof_id),
+            code: "PROOF_NOT_SEALED".to_string(),
+            timestamp: Utc::now(),
+            details: None,
+        })),
+        Err(e) => {
+            error!("Failed to retrieve seal receipt of proof {}: {}", proof_id, e);
+            Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "Failed to retrieve seal receipt".to_string(),
+                code: "RETRIEVAL_ERROR".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e.to_string() })),
+            }))
+        }
+    }
+}
+
+/// ABI-encoded proof with an EIP-712 enclave signature for EVM verifier contracts
+pub async fn get_evm_proof(
+    path: web::Path<Uuid>,
+    query: web::Query<EvmProofQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse> {
+    let proof_id = path.into_inner();
+
+    let signer = match EnclaveSigner::from_config(&config) {
+        Ok(Some(signer)) => signer,
+        Ok(None) => {
+            return Ok(HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                error: "Enclave signing key is not configured".to_string(),
+                code: "SIGNER_NOT_CONFIGURED".to_string(),
+                timestamp: Utc::now(),
+                details: None,
+            }));
+        }
+        Err(e) => {
+            error!("Invalid enclave signing key: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "Invalid enclave signing key".to_string(),
+                code: "SIGNER_ERROR".to_string(),
+                timestamp: Utc::now(),
+                details: None,
+            }));
+        }
+    };
+
+    let chain_id = query.chain_id.or(config.evm_chain_id);
+    let verifying_contract = query.verifying_contract.as_deref().or(config.evm_verifying_contract.as_deref());
+    let domain = match (chain_id, verifying_contract) {
+        (Some(chain_id), Some(contract)) => match EvmDomain::new(chain_id, contract) {
+            Ok(domain) => domain,
+            Err(e) => {
+                return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+                    error: "Invalid EIP-712 domain".to_string(),
+                    code: "INVALID_REQUEST".to_string(),
+                    timestamp: Utc::now(),
+                    details: Some(serde_json::json!({ "message": e })),
+                }));
+            }
+        },
+        _ => {
+            return Ok(HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                error: "EIP-712 domain is not configured".to_string(),
+                code: "EVM_NOT_CONFIGURED".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({
+                    "message": "Set EVM_CHAIN_ID and EVM_VERIFYING_CONTRACT or pass chain_id and verifying_contract"
+                })),
+            }));
+        }
+    };
+
+    let proof = match load_proof(&*aggregator.lock().await, &proof_id).await {
+        Ok(proof) => proof,
+        Err(response) => return Ok(response),
+    };
+
+    match evm::sign_proof(&signer, &proof, &domain) {
+        Ok(signed) => Ok(HttpResponse::Ok().json(signed)),
+        Err(e) => {
+            error!("Failed to encode proof {} for EVM: {}", proof_id, e);
+            Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "Cannot encode proof for EVM".to_string(),
+                code: "ENCODING_ERROR".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e })),
+            }))
+        }
+    }
+}
+
+/// One inclusion proof for many records of a proof, selected by leaf index or meter
+pub async fn get_multiproof(
+    path: web::Path<Uuid>,
+    query: web::Query<MultiProofQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+) -> Result<HttpResponse> {
+    let proof_id = path.into_inner();
+
+    let (proof, records) = {
+        let aggregator = aggregator.lock().await;
+        let proof = match load_proof(&aggregator, &proof_id).await {
+            Ok(proof) => proof,
+            Err(response) => return Ok(response),
+        };
+        match aggregator.get_records_by_id(&proof_id).await {
+            Ok(Some(records)) => (proof, records),
+            Ok(None) => {
+                return Ok(HttpResponse::NotFound().json(ErrorResponse {
+                    error: format!("Records of proof {} were not kept", proof_id),
+                    code: "RECORDS_NOT_FOUND".to_string(),
+                    timestamp: Utc::now(),
+                    details: None,
+                }));
+            }
+            Err(e) => {
+                error!("Failed to load records for proof {}: {}", proof_id, e);
+                return Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                    error: "Failed to retrieve proof records".to_string(),
+                    code: "RETRIEVAL_ERROR".to_string(),
+                    timestamp: Utc::now(),
+                    details: Some(serde_json::json!({ "message": e.to_string() })),
+                }));
+            }
+        }
+    };
+
+    let indices: Result<Vec<usize>, String> = match (&query.indices, &query.meter_ids) {
+        (Some(indices), _) => indices.split(',')
+            .map(|index| index.trim().parse().map_err(|_| format!("Invalid leaf index {}", index)))
+            .collect(),
+        (None, Some(meter_ids)) => {
+            let meter_ids: Vec<&str> = meter_ids.split(',').map(str::trim).collect();
+            Ok(records.iter().enumerate()
+                .filter(|(_, verified)| meter_ids.contains(&verified.record.meter_id.as_str()))
+                .map(|(index, _)| index)
+                .collect())
+        }
+        (None, None) => Err("Pass indices or meter_ids".to_string()),
+    };
+
+    let multiproof = indices.and_then(|indices| {
+        let record_hashes = records.iter().map(|r| &r.record_hash);
+        let tree = MerkleTree::with_scheme(record_hashes, proof.hash_scheme).map_err(|e| e.to_string())?;
+        if tree.root != proof.merkle_root {
+            error!("Stored records of proof {} do not match its Merkle root", proof_id);
+            return Err("Stored records do not match the Merkle root".to_string());
+        }
+        tree.generate_multiproof(&indices).map_err(|e| e.to_string())
+    });
+
+    match multiproof {
+        Ok(multiproof) => Ok(HttpResponse::Ok().json(MultiProofResponse {
+            proof_id,
+            merkle_root: proof.merkle_root,
+            hash_scheme: proof.hash_scheme,
+            multiproof,
+        })),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "Cannot build multi-proof".to_string(),
+            code: "INVALID_REQUEST".to_string(),
+            timestamp: Utc::now(),
+            details: Some(serde_json::json!({ "message": e })),
+        })),
+    }
+}
+
+/// Sum tree path of one record: proves it was counted in the proof's published total
+pub async fn get_sum_proof(
+    path: web::Path<Uuid>,
+    query: web::Query<SumProofQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+) -> Result<HttpResponse> {
+    let proof_id = path.into_inner();
+
+    let (proof, records) = {
+        let aggregator = aggregator.lock().await;
+        let proof = match load_proof(&aggregator, &proof_id).await {
+            Ok(proof) => proof,
+            Err(response) => return Ok(response),
+        };
+        match aggregator.get_records_by_id(&proof_id).await {
+            Ok(Some(records)) => (proof, records),
+            Ok(None) => {
+                return Ok(HttpResponse::NotFound().json(ErrorResponse {
+                    error: format!("Records of proof {} were not kept", proof_id),
+                    code: "RECORDS_NOT_FOUND".to_string(),
+                    timestamp: Utc::now(),
+                    details: None,
+                }));
+            }
+            Err(e) => {
+                error!("Failed to load records for proof {}: {}", proof_id, e);
+                return Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                    error: "Failed to retrieve proof records".to_string(),
+                    code: "RETRIEVAL_ERROR".to_string(),
+                    timestamp: Utc::now(),
+                    details: Some(serde_json::json!({ "message": e.to_string() })),
+                }));
+            }
+        }
+    };
+
+    let Some(sum_root) = proof.sum_root else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Proof {} does not commit to a sum tree", proof_id),
+            code: "SUM_TREE_NOT_AVAILABLE".to_string(),
+            timestamp: Utc::now(),
+            details: None,
+        }));
+    };
+
+    let sum_proof = MerkleSumTree::from_records(&records).and_then(|tree| {
+        if tree.root() != sum_root {
+            error!("Stored records of proof {} do not match its sum root", proof_id);
+            return Err("Stored records do not match the sum root".into());
+        }
+        tree.generate_proof(query.index)
+    });
+
+    match sum_proof {
+        Ok(sum_proof) => Ok(HttpResponse::Ok().json(SumProofResponse {
+            proof_id,
+            sum_root,
+            proof: sum_proof,
+        })),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "Cannot build sum proof".to_string(),
+            code: "INVALID_REQUEST".to_string(),
+            timestamp: Utc::now(),
+            details: Some(serde_json::json!({ "message": e.to_string() })),
+        })),
+    }
+}
+
+/// Root of the proof accumulator, now or at an earlier size
+pub async fn get_mmr_root(
+    query: web::Query<MmrQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+) -> Result<HttpResponse> {
+    let mut aggregator = aggregator.lock().await;
+    let root = aggregator.accumulator().await.and_then(|mmr| {
+        let size = query.size.unwrap_or(mmr.len());
+        Ok(MmrRootResponse { size, root: mmr.root_at(size)? })
+    });
+
+    match root {
+        Ok(root) => Ok(HttpResponse::Ok().json(root)),
+        Err(e) => Ok(mmr_error(e)),
+    }
+}
+
+/// Inclusion proof of one finalized proof in the accumulator
+pub async fn get_mmr_inclusion_proof(
+    path: web::Path<u64>,
+    query: web::Query<MmrQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+) -> Result<HttpResponse> {
+    let leaf_index = path.into_inner();
+    let mut aggregator = aggregator.lock().await;
+    let proof = aggregator.accumulator().await
+        .and_then(|mmr| mmr.inclusion_proof(leaf_index, query.size.unwrap_or(mmr.len())));
+
+    match proof {
+        Ok(proof) => Ok(HttpResponse::Ok().json(proof)),
+        Err(e) => Ok(mmr_error(e)),
+    }
+}
+
+/// Proof that the accumulator at `to` leaves only appended to the one at `from` leaves
+pub async fn get_mmr_consistency_proof(
+    query: web::Query<MmrConsistencyQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+) -> Result<HttpResponse> {
+    let mut aggregator = aggregator.lock().await;
+    let proof = aggregator.accumulator().await
+        .and_then(|mmr| mmr.consistency_proof(query.from, query.to.unwrap_or(mmr.len())));
+
+    match proof {
+        Ok(proof) => Ok(HttpResponse::Ok().json(proof)),
+        Err(e) => Ok(mmr_error(e)),
+    }
+}
+
+/// A stored proof, or the 404/500 response to answer with
+async fn load_proof(aggregator: &DataAggregator, proof_id: &Uuid) -> std::result::Result<ProofData, HttpResponse> {
+    match aggregator.get_proof_by_id(proof_id).await {
+        Ok(Some(proof)) => Ok(proof),
+        Ok(None) => Err(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Proof {} not found", proof_id),
+            code: "PROOF_NOT_FOUND".to_string(),
+            timestamp: Utc::now(),
+            details: None,
+        })),
+        Err(e) => {
+            error!("Failed to retrieve proof by ID {}: {}", proof_id, e);
+            Err(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "Failed to retrieve proof".to_string(),
+                code: "RETRIEVAL_ERROR".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e.to_string() })),
+            }))
+        }
+    }
+}
+
+fn mmr_error(e: Box<dyn std::error::Error + Send + Sync>) -> HttpResponse {
+    warn!("Cannot answer accumulator request: {}", e);
+    HttpResponse::BadRequest().json(ErrorResponse {
+        error: "Cannot build accumulator proof".to_string(),
+        code: "INVALID_REQUEST".to_string(),
+        timestamp: Utc::now(),
+        details: Some(serde_json::json!({ "message": e.to_string() })),
+    })
+}
+
+/// Signed `batch_set_quotas` arguments for every finalized window
+pub async fn get_quota_batches(
+    query: web::Query<QuotaBatchQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse> {
+    let signer = match EnclaveSigner::from_config(&config) {
+        Ok(Some(signer)) => signer,
+        Ok(None) => {
+            return Ok(HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                error: "Enclave signing key is not configured".to_string(),
+                code: "SIGNER_NOT_CONFIGURED".to_string(),
+                timestamp: Utc::now(),
+                details: None,
+            }));
+        }
+        Err(e) => {
+            error!("Invalid enclave signing key: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "Invalid enclave signing key".to_string(),
+                code: "SIGNER_ERROR".to_string(),
+                timestamp: Utc::now(),
+                details: None,
+            }));
+        }
+    };
+
+    let proofs = {
+        let mut aggregator = aggregator.lock().await;
+        match aggregator.list_proofs().await {
+            Ok(proofs) => proofs,
+            Err(e) => {
+                error!("Failed to list proofs: {}", e);
+                return Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                    error: "Failed to retrieve proofs".to_string(),
+                    code: "RETRIEVAL_ERROR".to_string(),
+                    timestamp: Utc::now(),
+                    details: Some(serde_json::json!({ "message": e.to_string() })),
+                }));
+            }
+        }
+    };
+
+    let entries: Vec<_> = quota::quota_entries(&proofs)
+        .into_iter()
+        .filter(|entry| query.since.is_none_or(|since| entry.window_start >= since))
+        .collect();
+    let batch_size = query.batch_size.unwrap_or(quota::DEFAULT_BATCH_SIZE);
+
+    match quota::sign_batches(&signer, entries, batch_size) {
+        Ok(batches) => {
+            info!("Exported {} signed quota batches", batches.len());
+            Ok(HttpResponse::Ok().json(batches))
+        }
+        Err(e) => Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "Cannot build quota batches".to_string(),
+            code: "INVALID_REQUEST".to_string(),
+            timestamp: Utc::now(),
+            details: Some(serde_json::json!({ "message": e })),
+        })),
+    }
+}
+
+/// Latest chain reconciliation report
+pub async fn get_reconciliation(
+    query: web::Query<ReconciliationQuery>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+) -> Result<HttpResponse> {
+    let aggregator = aggregator.lock().await;
+
+    match aggregator.get_reconciliation_report().await {
+        Ok(Some(mut report)) => {
+            if query.divergent {
+                report.proofs.retain(|proof| !proof.divergences.is_empty());
+            }
+            Ok(HttpResponse::Ok().json(report))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: "No reconciliation has run yet".to_string(),
+            code: "NO_RECONCILIATION".to_string(),
+            timestamp: Utc::now(),
+            details: None,
+        })),
+        Err(e) => {
+            error!("Failed to retrieve reconciliation report: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "Failed to retrieve reconciliation report".to_string(),
+                code: "RETRIEVAL_ERROR".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e.to_string() })),
+            }))
+        }
+    }
+}
+
+/// Hand a meter over to a new key, signed by its current key
+pub async fn rotate_meter_key(
+    path: web::Path<String>,
+    payload: web::Json<KeyRotation>,
+    verifier: web::Data<VerifierPool>,
+) -> Result<HttpResponse> {
+    let meter_id = path.into_inner();
+    let rotation = payload.into_inner();
+
+    if rotation.meter_id != meter_id {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "Rotation is for another meter".to_string(),
+            code: "VALIDATION_ERROR".to_string(),
+            timestamp: Utc::now(),
+            details: Some(serde_json::json!({ "meter_id": rotation.meter_id })),
+        }));
+    }
+
+    let pool = verifier.get_ref().clone();
+    let result = web::block(move || pool.registry().rotate(rotation)).await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    match result {
+        Ok(history) => {
+            info!("Meter {} rotated to key #{}", meter_id, history.len());
+            Ok(HttpResponse::Ok().json(history))
+        }
+        Err(e) => {
+            warn!("Rejected key rotation for meter {}: {}", meter_id, e);
+            let (mut status_code, error_code) = match e {
+                RotationError::InvalidSignature(_) => (HttpResponse::Unauthorized(), "INVALID_SIGNATURE"),
+                RotationError::Stale(_) => (HttpResponse::Conflict(), "STALE_ROTATION"),
+                RotationError::Replayed { .. } => (HttpResponse::Conflict(), "REPLAYED_ROTATION"),
+                RotationError::Unbound(_) => (HttpResponse::Conflict(), "NO_BOUND_KEY"),
+                RotationError::Invalid(_) => (HttpResponse::BadRequest(), "VALIDATION_ERROR"),
+                RotationError::Storage(_) => (HttpResponse::InternalServerError(), "STORAGE_ERROR"),
+            };
+
+            Ok(status_code.json(ErrorResponse {
+                error: "Key rotation rejected".to_string(),
+                code: error_code.to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e.to_string() })),
+            }))
+        }
+    }
+}
+
+/// Adopt the key a trust anchor's device certificate vouches for
+pub async fn enroll_meter_certificate(
+    path: web::Path<String>,
+    payload: web::Json<DeviceCertificate>,
+    verifier: web::Data<VerifierPool>,
+) -> Result<HttpResponse> {
+    let meter_id = path.into_inner();
+
+    match verifier.enroll(meter_id.clone(), payload.into_inner()).await {
+        Ok(history) => {
+            info!("Meter {} enrolled by device certificate", meter_id);
+            Ok(HttpResponse::Ok().json(history))
+        }
+        Err(e) => {
+            warn!("Rejected device certificate for meter {}: {}", meter_id, e);
+            let (mut status_code, error_code) = certificate_error_status(e.as_ref());
+            Ok(status_code.json(ErrorResponse {
+                error: "Device certificate rejected".to_string(),
+                code: error_code.to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e.to_string() })),
+            }))
+        }
+    }
+}
+
+/// Status of a failed enrollment: the certificate was rejected unless it could not be stored
+fn certificate_error_status(e: &(dyn std::error::Error + 'static)) -> (actix_web::HttpResponseBuilder, &'static str) {
+    match e.downcast_ref::<CertificateError>() {
+        Some(CertificateError::Storage(_)) => (HttpResponse::InternalServerError(), "STORAGE_ERROR"),
+        _ => (HttpResponse::Unauthorized(), "INVALID_CERTIFICATE"),
+    }
+}
+
+/// Key history of a meter, oldest first
+pub async fn get_meter_keys(
+    path: web::Path<String>,
+    verifier: web::Data<VerifierPool>,
+) -> Result<HttpResponse> {
+    let meter_id = path.into_inner();
+
+    match verifier.registry().history(&meter_id) {
+        Some(history) => Ok(HttpResponse::Ok().json(history)),
+        None => Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Meter {} has no known keys", meter_id),
+            code: "METER_NOT_FOUND".to_string(),
+            timestamp: Utc::now(),
+            details: None,
+        })),
+    }
+}
+
+/// Cumulative state of a meter with an inclusion proof, or a non-inclusion proof for a meter
+/// that never had a reading in a finalized window
+pub async fn get_meter_state(
+    path: web::Path<String>,
+    aggregator: web::Data<Arc<Mutex<DataAggregator>>>,
+) -> Result<HttpResponse> {
+    let meter_id = path.into_inner();
+    let mut aggregator = aggregator.lock().await;
+
+    match aggregator.meter_states().await {
+        Ok(meter_states) => Ok(HttpResponse::Ok().json(MeterStateResponse {
+            state_root: meter_states.root(),
+            state: meter_states.get(&meter_id).cloned(),
+            proof: meter_states.prove(&meter_id),
+            meter_id,
+        })),
+        Err(e) => {
+            error!("Failed to load meter state: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                error: "Failed to load meter state".to_string(),
+                code: "RETRIEVAL_ERROR".to_string(),
+                timestamp: Utc::now(),
+                details: Some(serde_json::json!({ "message": e.to_string() })),
+            }))
+        }
+    }
+}
+
 /// Seal proof to Walrus/Seal endpoint
 pub async fn seal_proof(
     payload: web::Json<SealRequest>,
@@ -145,23 +818,16 @@ pub async fn seal_proof(
     config: web::Data<Config>,
 ) -> Result<HttpResponse> {
     let request = payload.into_inner();
-    let seal_service = SealService::new(config.seal_endpoint.clone());
+    let seal_service = WalrusClient::from_config(&config);
     
     // Get proof to seal
     let proof = if let Some(proof_id) = request.proof_id {
         // Get proof by ID from aggregator
-        let aggregator = aggregator.lock().await;
-        aggregator.get_proof_by_id(&proof_id).await.map_err(|e| {
-            error!("Failed to retrieve proof by ID {}: {}", proof_id, e);
-            e
-        })?
         info!("Sealing specific proof: {}", proof_id);
-        return Ok(HttpResponse::NotImplemented().json(ErrorResponse {
-            error: "Sealing specific proof by ID not yet implemented".to_string(),
-            code: "NOT_IMPLEMENTED".to_string(),
-            timestamp: Utc::now(),
-            details: None,
-        }));
+        match load_proof(&*aggregator.lock().await, &proof_id).await {
+            Ok(proof) => proof,
+            Err(response) => return Ok(response),
+        }
     } else if request.force_latest {
         // Get latest proof
         let aggregator = aggregator.lock().await;
@@ -194,32 +860,157 @@ pub async fn seal_proof(
         }));
     };
 
+    // Load the full record set when it is sealed too
+    let records = if config.seal_records {
+        let aggregator = aggregator.lock().await;
+        match aggregator.get_records_by_id(&proof.proof_id).await {
+            Ok(Some(records)) => Some(records),
+            Ok(None) => {
+                warn!("No stored records for proof {}, sealing summary only", proof.proof_id);
+                None
+            }
+            Err(e) => {
+                error!("Failed to load records for proof {}: {}", proof.proof_id, e);
+                return Ok(HttpResponse::InternalServerError().json(ErrorResponse {
+                    error: "Failed to retrieve proof records".to_string(),
+                    code: "RETRIEVAL_ERROR".to_string(),
+                    timestamp: Utc::now(),
+                    details: Some(serde_json::json!({ "message": e.to_string() })),
+                }));
+            }
+        }
+    } else {
+        None
+    };
+
     // Seal the proof
     info!("Sealing proof {} to Walrus/Seal endpoint", proof.proof_id);
-    match seal_service.seal_proof(&proof).await {
-        Ok(seal_response) => {
+    match seal_service.seal_proof_with_records(&proof, records.as_deref()).await {
+        Ok(seal_response) if seal_response.success => {
             info!("Successfully sealed proof {}", proof.proof_id);
+            if let Err(e) = aggregator.lock().await.save_seal_receipt(&proof.proof_id, &seal_response).await {
+                warn!("Failed to save seal receipt for proof {}: {}", proof.proof_id, e);
+            }
             Ok(HttpResponse::Ok().json(SealResponse {
                 success: true,
                 message: "Proof sealed successfully".to_string(),
                 proof_id: Some(proof.proof_id),
-                seal_endpoint: config.seal_endpoint.clone(),
-                seal_response: Some(seal_response),
+                seal_endpoint: Some(config.walrus_publisher_url.clone()),
+                seal_response: serde_json::to_value(&seal_response).ok(),
+            }))
+        }
+        Ok(seal_response) => {
+            let reason = seal_response.error.clone().unwrap_or_default();
+            error!("Failed to seal proof {}: {}", proof.proof_id, reason);
+            Ok(HttpResponse::InternalServerError().json(SealResponse {
+                success: false,
+                message: format!("Failed to seal proof: {}", reason),
+                proof_id: Some(proof.proof_id),
+                seal_endpoint: Some(config.walrus_publisher_url.clone()),
+                seal_response: serde_json::to_value(&seal_response).ok(),
             }))
         }
         Err(e) => {
             error!("Failed to seal proof {}: {}", proof.proof_id, e);
-            Ok(HttpResponse::InternalServerError().json(SealResponse {
+            let mut status_code = if e.is::<BudgetExceeded>() {
+                HttpResponse::PayloadTooLarge()
+            } else {
+                HttpResponse::InternalServerError()
+            };
+            Ok(status_code.json(SealResponse {
                 success: false,
                 message: format!("Failed to seal proof: {}", e),
                 proof_id: Some(proof.proof_id),
-                seal_endpoint: config.seal_endpoint.clone(),
+                seal_endpoint: Some(config.walrus_publisher_url.clone()),
                 seal_response: None,
             }))
         }
     }
 }
 
+/// Estimate the Walrus storage cost of sealing a payload
+pub async fn estimate_seal_cost(
+    query: web::Query<SealEstimateQuery>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse> {
+    let epochs = query.epochs.unwrap_or(config.walrus_epochs);
+    if epochs == 0 {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "epochs must be at least 1".to_string(),
+            code: "INVALID_REQUEST".to_string(),
+            timestamp: Utc::now(),
+            details: None,
+        }));
+    }
+
+    // Priced like the budget check of `/seal`, except for record chunks, which depend on the
+    // stored records of a proof
+    let estimate = config.walrus_pricing.estimate(query.bytes, epochs);
+    let seal_cost = config.walrus_pricing.plan_cost(&seal::seal_blob_plan(query.bytes, &[]), epochs);
+    let within_budget = config.seal_budget.is_none_or(|budget| seal_cost <= budget);
+
+    Ok(HttpResponse::Ok().json(SealEstimateResponse {
+        estimate,
+        seal_cost,
+        budget: config.seal_budget,
+        within_budget,
+    }))
+}
+
+/// Every route served under [`openapi::API_PREFIX`]: method, path and handler
+pub fn api_routes() -> Vec<(Method, &'static str, Route)> {
+    vec![
+        route(Method::POST, "/ingest", ingest_data),
+        route(Method::GET, "/health", health_check),
+        route(Method::GET, "/status", get_status),
+        route(Method::GET, "/openapi.json", get_openapi),
+        route(Method::GET, "/proofs/latest", get_latest_proof),
+        route(Method::GET, "/proofs/{id}/sui/mint-tx", get_sui_mint_tx),
+        route(Method::GET, "/proofs/{id}/certificate", get_certificate_payload),
+        route(Method::GET, "/proofs/{id}/evm", get_evm_proof),
+        route(Method::GET, "/proofs/{id}/multiproof", get_multiproof),
+        route(Method::GET, "/proofs/{id}/sum-proof", get_sum_proof),
+        route(Method::GET, "/mmr", get_mmr_root),
+        route(Method::GET, "/mmr/consistency", get_mmr_consistency_proof),
+        route(Method::GET, "/mmr/{leaf_index}", get_mmr_inclusion_proof),
+        route(Method::GET, "/quotas", get_quota_batches),
+        route(Method::GET, "/reconciliation", get_reconciliation),
+        route(Method::POST, "/meters/{id}/rotate", rotate_meter_key),
+        route(Method::GET, "/meters/{id}/keys", get_meter_keys),
+        route(Method::POST, "/meters/{id}/certificate", enroll_meter_certificate),
+        route(Method::GET, "/meters/{id}/state", get_meter_state),
+        route(Method::POST, "/seal", seal_proof),
+        route(Method::GET, "/seal/estimate", estimate_seal_cost),
+    ]
+}
+
+fn route<F, Args>(method: Method, path: &'static str, handler: F) -> (Method, &'static str, Route)
+where
+    F: Handler<Args>,
+    Args: FromRequest + 'static,
+    F::Output: Responder + 'static,
+{
+    (method.clone(), path, web::method(method).to(handler))
+}
+
+/// Mount the `/api/v1` routes (without the prefix, for use inside its scope)
+pub fn configure_api(cfg: &mut web::ServiceConfig) {
+    for (_, path, route) in api_routes() {
+        cfg.route(path, route);
+    }
+}
+
+/// Mount every [`openapi::LEGACY_ROUTES`] entry with the handlers of its `/api/v1` successor
+pub fn configure_legacy(cfg: &mut web::ServiceConfig) {
+    let mut routes = api_routes();
+    for &legacy in openapi::LEGACY_ROUTES {
+        while let Some(index) = routes.iter().position(|(_, path, _)| *path == legacy) {
+            let (_, path, route) = routes.swap_remove(index);
+            cfg.route(path, route);
+        }
+    }
+}
+
 /// Validate meter record data
 fn validate_meter_record(record: &MeterRecord) -> Result<(), String> {
     // Check required fields
@@ -273,6 +1064,40 @@ fn validate_meter_record(record: &MeterRecord) -> Result<(), String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeSet;
+
+    #[actix_web::test]
+    async fn test_document_matches_mounted_routes() {
+        use actix_web::{test, App};
+
+        let documented: BTreeSet<(String, String)> = openapi::document()["paths"].as_object().unwrap()
+            .iter()
+            .flat_map(|(path, item)| item.as_object().unwrap().keys().map(move |method| (method.to_uppercase(), path.clone())))
+            .collect();
+        let mut routes: BTreeSet<(String, String)> = api_routes().into_iter()
+            .map(|(method, path, _)| (method.to_string(), format!("{}{}", openapi::API_PREFIX, path)))
+            .collect();
+        routes.extend(api_routes().into_iter()
+            .filter(|(_, path, _)| openapi::LEGACY_ROUTES.contains(path))
+            .map(|(method, path, _)| (method.to_string(), path.to_string())));
+        assert_eq!(documented, routes);
+
+        // Every documented operation is mounted: without app data it fails in an extractor, not in routing
+        let app = test::init_service(App::new()
+            .service(web::scope(openapi::API_PREFIX).configure(configure_api))
+            .service(web::scope("").configure(configure_legacy))).await;
+        for (method, path) in &documented {
+            let uri = path.replace("{id}", &Uuid::nil().to_string()).replace("{leaf_index}", "0");
+            let request = test::TestRequest::default()
+                .method(Method::from_bytes(method.as_bytes()).unwrap())
+                .uri(&uri)
+                .to_request();
+            let status = test::call_service(&app, request).await.status();
+            assert!(status != 404 && status != 405, "{} {} answered {}", method, path, status);
+        }
+        let request = test::TestRequest::get().uri("/mmr").to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), 404);
+    }
 
     #[test]
     fn test_validate_meter_record_valid() {
@@ -281,6 +1106,7 @@ mod tests {
             timestamp: Utc::now().timestamp_millis(),
             kwh_delta: 1.5,
             nonce: "1234567890abcdef1234567890abcdef".to_string(),
+            encoding: RecordEncoding::V1,
         };
         
         assert!(validate_meter_record(&record).is_ok());
@@ -293,6 +1119,7 @@ mod tests {
             timestamp: Utc::now().timestamp_millis(),
             kwh_delta: 1.5,
             nonce: "1234567890abcdef1234567890abcdef".to_string(),
+            encoding: RecordEncoding::V1,
         };
         
         assert!(validate_meter_record(&record).is_err());
@@ -305,6 +1132,7 @@ mod tests {
             timestamp: Utc::now().timestamp_millis(),
             kwh_delta: -1.0,
             nonce: "1234567890abcdef1234567890abcdef".to_string(),
+            encoding: RecordEncoding::V1,
         };
         
         assert!(validate_meter_record(&record).is_err());
@@ -317,6 +1145,7 @@ mod tests {
             timestamp: Utc::now().timestamp_millis(),
             kwh_delta: 1.5,
             nonce: "invalid_nonce".to_string(),
+            encoding: RecordEncoding::V1,
         };
         
         assert!(validate_meter_record(&record).is_err());
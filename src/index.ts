@@ -1,7 +1,7 @@
 // FILE: src/index.ts
 import dotenv from 'dotenv';
 import { createSignedMeterData, importPrivateKeyFromPEM } from './crypto';
-import { ROFLClient } from './client';
+import { API_PREFIX, ROFLClient } from './client';
 
 // Load environment variables
 dotenv.config();
@@ -137,7 +137,7 @@ class SmartMeterSimulator {
       
       console.log(`🚀 Starting Smart Meter Simulator...`);
       console.log(`📊 Sending readings every ${this.config.intervalMs}ms`);
-      console.log(`🎯 Target endpoint: ${this.config.roflEndpoint}/ingest`);
+      console.log(`🎯 Target endpoint: ${this.config.roflEndpoint}${API_PREFIX}/ingest`);
       console.log('Press Ctrl+C to stop\n');
 
       // Send initial reading immediately
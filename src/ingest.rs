@@ -0,0 +1,145 @@
+//! Signature verification for ingest, run on blocking workers outside the aggregator lock
+
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+use crate::attestation::DeviceCertificate;
+use crate::config::Config;
+use crate::crypto::CryptoService;
+use crate::models::{HashScheme, MeterRecord, SignatureScheme, VerifiedRecord};
+use crate::registry::{KeyEpoch, MeterRegistry};
+
+type IngestResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Bounded pool of blocking workers verifying meter signatures; cheap to clone
+#[derive(Clone)]
+pub struct VerifierPool {
+    crypto: Arc<CryptoService>,
+    registry: Arc<MeterRegistry>,
+    permits: Arc<Semaphore>,
+    workers: usize,
+    enabled: bool,
+}
+
+impl VerifierPool {
+    /// Pool running at most `workers` verifications at once
+    pub fn new(registry: MeterRegistry, workers: usize, enabled: bool) -> Self {
+        let workers = workers.max(1);
+        Self {
+            crypto: Arc::new(CryptoService::new()),
+            registry: Arc::new(registry),
+            permits: Arc::new(Semaphore::new(workers)),
+            workers,
+            enabled,
+        }
+    }
+
+    /// Pool sized by `VERIFY_WORKERS`, one worker per core by default
+    pub fn from_config(config: &Config, registry: MeterRegistry) -> Self {
+        let workers = config.verify_workers.unwrap_or_else(|| {
+            std::thread::available_parallelism().map(|cores| cores.get()).unwrap_or(1)
+        });
+        Self::new(registry, workers, config.enable_signature_verification)
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn registry(&self) -> &MeterRegistry {
+        &self.registry
+    }
+
+    /// Verify a record on a blocking worker; waits for a free worker when all are busy
+    pub async fn verify(&self, record: MeterRecord, signature: String, scheme: SignatureScheme)
+        -> IngestResult<VerifiedRecord> {
+        let permit = self.permits.clone().acquire_owned().await?;
+        let pool = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            pool.verify_now(record, signature, scheme)
+        }).await?
+    }
+
+    /// Check a meter's device certificate on a blocking worker and adopt the key it vouches for
+    pub async fn enroll(&self, meter_id: String, certificate: DeviceCertificate) -> IngestResult<Vec<KeyEpoch>> {
+        let permit = self.permits.clone().acquire_owned().await?;
+        let registry = self.registry.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            registry.enroll(&meter_id, certificate).map_err(Into::into)
+        }).await?
+    }
+
+    /// Verify a record on the calling thread
+    pub fn verify_now(&self, record: MeterRecord, signature: String, scheme: SignatureScheme)
+        -> IngestResult<VerifiedRecord> {
+        // The meter is identified by the key that signed; its first key is bound on first use
+        let signer = if self.enabled {
+            let signer = self.registry.verify_and_bind(&self.crypto, &record, &signature, scheme)
+                .map_err(|e| format!("Invalid signature: {}", e))?;
+            Some(signer)
+        } else {
+            None
+        };
+
+        let record_hash = self.crypto.hash_record(&record, HashScheme::CURRENT).map_err(|e| e.to_string())?;
+        Ok(VerifiedRecord {
+            record,
+            signature,
+            scheme,
+            signer,
+            verification_timestamp: Utc::now(),
+            record_hash,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RecordEncoding;
+    use crate::signer::EnclaveSigner;
+
+    fn record(nonce: &str) -> MeterRecord {
+        MeterRecord {
+            meter_id: "meter_001".to_string(),
+            timestamp: 1640995200000,
+            kwh_delta: 0.5,
+            nonce: nonce.to_string(),
+            encoding: RecordEncoding::V1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pool_verifies_concurrently() {
+        let pool = VerifierPool::new(MeterRegistry::default(), 2, true);
+        let meter = EnclaveSigner::from_hex(&"42".repeat(32)).unwrap();
+
+        let tasks: Vec<_> = (0..8).map(|i| {
+            let pool = pool.clone();
+            let record = record(&format!("nonce_{}", i));
+            let digest = CryptoService::new().meter_signing_digest(&record, SignatureScheme::Eip712).unwrap();
+            let signature = hex::encode(meter.sign_digest(&digest));
+            tokio::spawn(async move { pool.verify(record, signature, SignatureScheme::Eip712).await })
+        }).collect();
+
+        for task in tasks {
+            let verified = task.await.unwrap().unwrap();
+            assert_eq!(verified.signer.as_deref(), Some(meter.address().as_str()));
+        }
+        assert_eq!(pool.permits.available_permits(), 2);
+
+        let error = pool.verify(record("bad"), "0x1234".to_string(), SignatureScheme::Eip712).await.unwrap_err();
+        assert!(error.to_string().starts_with("Invalid signature"));
+        let error = pool.verify(record("bad"), "0xzz".to_string(), SignatureScheme::Eip712).await.unwrap_err();
+        assert_eq!(error.to_string(), "Invalid signature: signature is not valid hex");
+
+        let unchecked = VerifierPool::new(MeterRegistry::default(), 1, false);
+        let verified = unchecked.verify(record("bad"), "0x1234".to_string(), SignatureScheme::Eip712).await.unwrap();
+        assert_eq!(verified.signer, None);
+    }
+}
@@ -0,0 +1,28 @@
+//! TEE aggregation and verification of smart meter data, shared by the server binary,
+//! integration tests and benchmarks
+
+pub mod config;
+pub mod crypto;
+pub mod handlers;
+pub mod models;
+pub mod aggregator;
+pub mod merkle;
+pub mod seal;
+pub mod signer;
+pub mod canonical;
+pub mod envelope;
+pub mod record_set;
+pub mod sui;
+pub mod certificate;
+pub mod evm;
+pub mod quota;
+pub mod chain;
+pub mod reconcile;
+pub mod schemes;
+pub mod registry;
+pub mod ingest;
+pub mod attestation;
+pub mod sum_tree;
+pub mod mmr;
+pub mod state_tree;
+pub mod openapi;
@@ -1,22 +1,19 @@
 // FILE: src/main.rs
 use actix_cors::Cors;
 use actix_web::{web, App, HttpServer, middleware::Logger};
+use actix_web::dev::Service;
 use dotenv::dotenv;
 use env_logger::Env;
-use log::info;
+use log::{info, warn};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
-mod config;
-mod crypto;
-mod handlers;
-mod models;
-mod aggregator;
-mod merkle;
-mod seal;
-
-use config::Config;
-use aggregator::DataAggregator;
+use rofl_enclave::{attestation, chain, handlers, openapi, reconcile, registry};
+use rofl_enclave::config::Config;
+use rofl_enclave::aggregator::DataAggregator;
+use rofl_enclave::ingest::VerifierPool;
+use rofl_enclave::signer::EnclaveSigner;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -33,18 +30,58 @@ async fn main() -> std::io::Result<()> {
     info!("🚀 Starting ROFL Enclave on {}", bind_address);
     info!("📊 Aggregation window: {} seconds", config.agg_window_sec);
     info!("📁 Output directory: {}", config.output_dir);
+    if std::env::var_os("SEAL_ENDPOINT").is_some() {
+        warn!("⚠️ SEAL_ENDPOINT is ignored, proofs are sealed through WALRUS_PUBLISHER_URL");
+    }
+    match EnclaveSigner::from_config(&config).expect("Invalid enclave signing key") {
+        Some(signer) => info!("✍️ Seal signer: {}", signer.address()),
+        None => warn!("⚠️ No ENCLAVE_SIGNING_KEY configured, seals will be unsigned"),
+    }
     
     // Create output directory if it doesn't exist
     tokio::fs::create_dir_all(&config.output_dir).await?;
     
     // Initialize shared state
+    let trust = match &config.trust_anchors_file {
+        Some(path) => attestation::TrustStore::from_file(path).expect("Invalid trust anchors"),
+        None => attestation::TrustStore::default(),
+    };
+    info!("🏭 {} trust anchors for device certificates", trust.len());
+    let registry = match &config.meter_registry_file {
+        Some(path) => registry::MeterRegistry::from_file(path).expect("Invalid meter registry"),
+        None => registry::MeterRegistry::default(),
+    };
+    // Enrolled certificates are checked against the trust store on replay
+    let registry = registry
+        .registered_only(config.require_registered_meters)
+        .with_trust_store(trust)
+        .with_binding_log(format!("{}/meter_bindings.json", config.output_dir))
+        .expect("Invalid meter binding log")
+        .with_certificate_log(format!("{}/meter_certificates.json", config.output_dir))
+        .expect("Invalid meter certificate log")
+        .with_rotation_log(format!("{}/key_rotations.json", config.output_dir))
+        .expect("Invalid key rotation log");
+    info!("🔑 {} registered meters", registry.len());
+    let verifier = VerifierPool::from_config(&config, registry);
+    info!("🔏 {} signature verification workers", verifier.workers());
     let aggregator = Arc::new(Mutex::new(DataAggregator::new(config.clone())));
+
+    // Reconcile archived proofs with their mint and seal state on-chain
+    match chain::from_config(&config) {
+        Some(chain) if config.reconcile_interval_sec > 0 => {
+            info!("🔁 Chain reconciliation every {} seconds", config.reconcile_interval_sec);
+            reconcile::spawn(aggregator.clone(), chain, Duration::from_secs(config.reconcile_interval_sec));
+        }
+        Some(_) => info!("Chain reconciliation disabled (RECONCILE_INTERVAL_SEC=0)"),
+        None => warn!("⚠️ No chain adapter configured, proofs will not be reconciled"),
+    }
     
     // Start HTTP server
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(config.clone()))
             .app_data(web::Data::new(aggregator.clone()))
+            .app_data(web::Data::new(verifier.clone()))
             .wrap(Logger::default())
             .wrap(
                 Cors::default()
@@ -52,20 +89,26 @@ async fn main() -> std::io::Result<()> {
                     .allow_any_method()
                     .allow_any_header()
             )
+            .service(web::scope(openapi::API_PREFIX).configure(handlers::configure_api))
+            // Legacy routes (without /api/v1 prefix), answered with deprecation headers
             .service(
-                web::scope("/api/v1")
-                    .route("/ingest", web::post().to(handlers::ingest_data))
-                    .route("/health", web::get().to(handlers::health_check))
-                    .route("/status", web::get().to(handlers::get_status))
-                    .route("/proofs/latest", web::get().to(handlers::get_latest_proof))
-                    .route("/seal", web::post().to(handlers::seal_proof))
+                web::scope("")
+                    .wrap_fn(|req, srv| {
+                        let headers = openapi::deprecation_headers(req.path());
+                        let response = srv.call(req);
+                        async move {
+                            let mut response = response.await?;
+                            // Only routes that exist have a successor
+                            if response.request().match_pattern().is_some() {
+                                for (name, value) in headers {
+                                    response.headers_mut().insert(name, value);
+                                }
+                            }
+                            Ok(response)
+                        }
+                    })
+                    .configure(handlers::configure_legacy)
             )
-            // Legacy routes (without /api/v1 prefix)
-            .route("/ingest", web::post().to(handlers::ingest_data))
-            .route("/health", web::get().to(handlers::health_check))
-            .route("/status", web::get().to(handlers::get_status))
-            .route("/proofs/latest", web::get().to(handlers::get_latest_proof))
-            .route("/seal", web::post().to(handlers::seal_proof))
     })
     .bind(&bind_address)?
     .run()
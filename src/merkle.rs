@@ -1,121 +1,357 @@
 // FILE: src/merkle.rs
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use sha3::{Keccak256, Digest};
+use std::collections::VecDeque;
 use hex;
 
-#[derive(Debug, Clone)]
-pub struct MerkleTree {
-    pub root: String,
+use crate::models::HashScheme;
+
+/// Domain tags of [`HashScheme::V1`]: a leaf preimage can never be read as an internal node
+pub const LEAF_TAG: u8 = 0x00;
+pub const NODE_TAG: u8 = 0x01;
+
+/// Sibling of the last node of an odd level under [`HashScheme::V2`]
+pub const EMPTY_NODE: [u8; 32] = [0u8; 32];
+
+/// A leaf or internal node; hex only appears at the API boundary
+pub type Node = [u8; 32];
+
+/// Inclusion proof of several leaves at once, laid out like OpenZeppelin `multiProofVerify`.
+/// Only legacy trees verify with stock `multiProofVerify`; v1 tags its nodes, and v2 also
+/// hashes by position and needs `indices`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MultiProof {
+    /// Proven leaves in ascending leaf order, the order `multiProofVerify` consumes them in
     pub leaves: Vec<String>,
-    pub tree: Vec<Vec<String>>,
+    pub indices: Vec<usize>,
+    /// Sibling hashes the leaves cannot produce themselves
+    pub proof: Vec<String>,
+    /// Per hashing step: `true` pairs two known nodes, `false` takes the next proof hash
+    pub proof_flags: Vec<bool>,
 }
 
-impl MerkleTree {
-    /// Build Merkle tree from record hashes
-    pub fn new(mut hashes: Vec<String>) -> Result<Self, Box<dyn std::error::Error>> {
-        if hashes.is_empty() {
-            return Err("Cannot build Merkle tree from empty hash list".into());
+fn decode_node(hash: &str) -> Result<Node, Box<dyn std::error::Error>> {
+    hex::decode(hash)?.try_into().map_err(|_| "Merkle nodes are 32 bytes".into())
+}
+
+fn padding(scheme: HashScheme, last: &Node) -> Node {
+    match scheme {
+        HashScheme::V2 => EMPTY_NODE,
+        HashScheme::Legacy | HashScheme::V1 => *last,
+    }
+}
+
+/// Hash two children together using Keccak256, tagged as a node under `scheme`
+pub fn hash_pair(scheme: HashScheme, left: &Node, right: &Node) -> Node {
+    let mut hasher = Keccak256::new();
+    if scheme != HashScheme::Legacy {
+        hasher.update([NODE_TAG]);
+    }
+
+    // v2 hashes children in position; older schemes sort them
+    let (first, second) = if scheme == HashScheme::V2 || left <= right { (left, right) } else { (right, left) };
+    hasher.update(first);
+    hasher.update(second);
+    hasher.finalize().into()
+}
+
+/// Builds a tree one leaf at a time, hashing every pair as soon as both children are known,
+/// so a window never has to be held as hex or rehashed level by level
+#[derive(Debug, Clone)]
+pub struct MerkleBuilder {
+    scheme: HashScheme,
+    levels: Vec<Vec<Node>>,
+}
+
+impl MerkleBuilder {
+    pub fn new(scheme: HashScheme) -> Self {
+        Self::with_capacity(scheme, 0)
+    }
+
+    /// Reserve room for `leaves` leaves and every level above them
+    pub fn with_capacity(scheme: HashScheme, leaves: usize) -> Self {
+        let mut levels = vec![Vec::with_capacity(leaves)];
+        let mut width = leaves;
+        while width > 1 {
+            width = width.div_ceil(2);
+            levels.push(Vec::with_capacity(width));
         }
+        Self { scheme, levels }
+    }
 
-        let leaves = hashes.clone();
-        let mut tree = vec![hashes.clone()];
+    pub fn push(&mut self, leaf: Node) {
+        self.push_at(0, leaf);
+    }
 
-        // Build tree bottom-up
-        while hashes.len() > 1 {
-            // If odd number of hashes, duplicate the last one
-            if hashes.len() % 2 == 1 {
-                hashes.push(hashes.last().unwrap().clone());
-            }
+    /// Push a hex-encoded leaf
+    pub fn push_hex(&mut self, leaf: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.push(decode_node(leaf)?);
+        Ok(())
+    }
 
-            let mut next_level = Vec::new();
-            for chunk in hashes.chunks(2) {
-                let combined_hash = Self::hash_pair(&chunk[0], &chunk[1])?;
-                next_level.push(combined_hash);
+    /// Append `node` to `level`, carrying completed pairs upwards
+    fn push_at(&mut self, mut level: usize, mut node: Node) {
+        loop {
+            if level == self.levels.len() {
+                self.levels.push(Vec::new());
+            }
+            let nodes = &mut self.levels[level];
+            nodes.push(node);
+            if nodes.len() & 1 == 1 {
+                return;
             }
+            node = hash_pair(self.scheme, &nodes[nodes.len() - 2], &nodes[nodes.len() - 1]);
+            level += 1;
+        }
+    }
 
-            tree.push(next_level.clone());
-            hashes = next_level;
+    pub fn finish(mut self) -> Result<MerkleTree, Box<dyn std::error::Error>> {
+        if self.levels[0].is_empty() {
+            return Err("Cannot build Merkle tree from empty hash list".into());
         }
 
-        let root = hashes.into_iter().next().unwrap();
+        // Close odd levels bottom-up: v2 pads with the zero hash, older schemes duplicate the
+        // last node (which makes [a, b, c] and [a, b, c, c] share a root)
+        let mut level = 0;
+        while self.levels[level].len() > 1 || self.levels.get(level + 1).is_some_and(|above| !above.is_empty()) {
+            if self.levels[level].len() & 1 == 1 {
+                let last = *self.levels[level].last().unwrap();
+                self.push_at(level + 1, hash_pair(self.scheme, &last, &padding(self.scheme, &last)));
+            }
+            level += 1;
+        }
+        self.levels.truncate(level + 1);
+
+        let mut offsets = Vec::with_capacity(self.levels.len() + 1);
+        let mut nodes = Vec::with_capacity(self.levels.iter().map(Vec::len).sum());
+        for level in &self.levels {
+            offsets.push(nodes.len());
+            nodes.extend_from_slice(level);
+        }
+        offsets.push(nodes.len());
 
         Ok(MerkleTree {
-            root,
-            leaves,
-            tree,
+            root: hex::encode(nodes[nodes.len() - 1]),
+            scheme: self.scheme,
+            nodes,
+            offsets,
         })
     }
+}
 
-    /// Hash two strings together using Keccak256
-    fn hash_pair(left: &str, right: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let mut hasher = Keccak256::new();
-        
-        // Ensure consistent ordering by comparing lexicographically
-        if left <= right {
-            hasher.update(hex::decode(left)?);
-            hasher.update(hex::decode(right)?);
-        } else {
-            hasher.update(hex::decode(right)?);
-            hasher.update(hex::decode(left)?);
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    pub root: String,
+    pub scheme: HashScheme,
+    /// Every level bottom-up in one array, leaves first; odd levels are stored unpadded
+    nodes: Vec<Node>,
+    /// Start of each level in `nodes`, followed by the end of the root level
+    offsets: Vec<usize>,
+}
+
+impl MerkleTree {
+    /// Build a legacy (untagged) Merkle tree from record hashes
+    pub fn new(hashes: Vec<String>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_scheme(hashes, HashScheme::Legacy)
+    }
+
+    /// Build a Merkle tree from hex record hashes computed under `scheme`
+    pub fn with_scheme<I>(hashes: I, scheme: HashScheme) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let hashes = hashes.into_iter();
+        let mut builder = MerkleBuilder::with_capacity(scheme, hashes.size_hint().0);
+        for hash in hashes {
+            builder.push_hex(hash.as_ref())?;
         }
-        
-        let result = hasher.finalize();
-        Ok(hex::encode(result))
+        builder.finish()
+    }
+
+    fn level(&self, level: usize) -> &[Node] {
+        &self.nodes[self.offsets[level]..self.offsets[level + 1]]
+    }
+
+    /// Number of levels, leaves and root included
+    fn height(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.offsets[1]
+    }
+
+    pub fn leaf(&self, index: usize) -> Option<String> {
+        self.level(0).get(index).map(hex::encode)
+    }
+
+    /// Hash two hex child hashes together, tagged as a node under `scheme`
+    pub fn hash_node(scheme: HashScheme, left: &str, right: &str) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(hex::encode(hash_pair(scheme, &decode_node(left)?, &decode_node(right)?)))
     }
 
     /// Generate Merkle proof for a specific leaf
     pub fn generate_proof(&self, leaf_index: usize) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        if leaf_index >= self.leaves.len() {
+        if leaf_index >= self.leaf_count() {
             return Err("Leaf index out of bounds".into());
         }
 
-        let mut proof = Vec::new();
+        let mut proof = Vec::with_capacity(self.height() - 1);
         let mut current_index = leaf_index;
 
-        // Traverse up the tree, collecting sibling hashes
-        for level in &self.tree[..self.tree.len() - 1] {
-            let sibling_index = if current_index % 2 == 0 {
-                current_index + 1
-            } else {
-                current_index - 1
-            };
+        // Traverse up the tree, collecting sibling hashes; a lone last node is paired with its padding
+        for level in 0..self.height() - 1 {
+            let nodes = self.level(level);
+            let sibling = nodes.get(current_index ^ 1).copied()
+                .unwrap_or_else(|| padding(self.scheme, &nodes[current_index]));
+            proof.push(hex::encode(sibling));
+            current_index /= 2;
+        }
+
+        Ok(proof)
+    }
+
+    /// Generate one proof for a set of leaves, sharing the hashes their paths have in common
+    pub fn generate_multiproof(&self, leaf_indices: &[usize]) -> Result<MultiProof, Box<dyn std::error::Error>> {
+        let mut indices = leaf_indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        match indices.last() {
+            None => return Err("No leaves to prove".into()),
+            Some(&last) if last >= self.leaf_count() => return Err("Leaf index out of bounds".into()),
+            Some(_) => {}
+        }
+
+        let mut proof = Vec::new();
+        let mut proof_flags = Vec::new();
+        let mut known = indices.clone();
 
-            if sibling_index < level.len() {
-                proof.push(level[sibling_index].clone());
+        // Level by level, in the order the verifier's queue hands the nodes out
+        for level in 0..self.height() - 1 {
+            let nodes = self.level(level);
+            let mut parents = Vec::new();
+            let mut position = 0;
+            while position < known.len() {
+                let index = known[position];
+                let sibling_index = index ^ 1;
+
+                if known.get(position + 1) == Some(&sibling_index) {
+                    proof_flags.push(true);
+                    position += 2;
+                } else {
+                    proof_flags.push(false);
+                    let sibling = nodes.get(sibling_index).copied()
+                        .unwrap_or_else(|| padding(self.scheme, &nodes[index]));
+                    proof.push(hex::encode(sibling));
+                    position += 1;
+                }
+                parents.push(index / 2);
             }
+            known = parents;
+        }
 
-            current_index /= 2;
+        Ok(MultiProof {
+            leaves: indices.iter().map(|&index| hex::encode(self.level(0)[index])).collect(),
+            indices,
+            proof,
+            proof_flags,
+        })
+    }
+
+    /// Verify a multi-proof of a tree built under `scheme`
+    pub fn verify_multiproof(scheme: HashScheme, multiproof: &MultiProof, root: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let MultiProof { leaves, indices, proof, proof_flags } = multiproof;
+        if leaves.len() != indices.len()
+            || leaves.len() + proof.len() != proof_flags.len() + 1
+            || !indices.windows(2).all(|pair| pair[0] < pair[1]) {
+            return Ok(false);
         }
 
-        Ok(proof)
+        // Queue of (hash, level, index); a step pairs the front node with its sibling
+        let mut queue = VecDeque::with_capacity(leaves.len());
+        for (leaf, &index) in leaves.iter().zip(indices) {
+            queue.push_back((decode_node(leaf)?, 0, index));
+        }
+        let proof = proof.iter().map(|hash| decode_node(hash)).collect::<Result<Vec<_>, _>>()?;
+        let mut proof = proof.into_iter();
+
+        for &flag in proof_flags {
+            let Some((hash, level, index)) = queue.pop_front() else { return Ok(false) };
+            let sibling = if flag {
+                match queue.pop_front() {
+                    Some((sibling, sibling_level, sibling_index)) if sibling_level == level && sibling_index == index ^ 1 => sibling,
+                    _ => return Ok(false),
+                }
+            } else {
+                match proof.next() {
+                    Some(sibling) => sibling,
+                    None => return Ok(false),
+                }
+            };
+
+            let parent = if index & 1 == 0 {
+                hash_pair(scheme, &hash, &sibling)
+            } else {
+                hash_pair(scheme, &sibling, &hash)
+            };
+            queue.push_back((parent, level + 1, index / 2));
+        }
+
+        match (queue.pop_front(), queue.is_empty()) {
+            (Some((hash, _, 0)), true) => Ok(hex::encode(hash) == root),
+            _ => Ok(false),
+        }
     }
 
-    /// Verify a Merkle proof
+    /// Verify a legacy Merkle proof
     pub fn verify_proof(
         leaf_hash: &str,
         proof: &[String],
         root: &str,
         leaf_index: usize,
+        leaf_count: usize,
     ) -> Result<bool, Box<dyn std::error::Error>> {
-        let mut current_hash = leaf_hash.to_string();
+        Self::verify_scheme_proof(HashScheme::Legacy, leaf_hash, proof, root, leaf_index, leaf_count)
+    }
+
+    /// Verify a Merkle proof of a tree of `leaf_count` leaves built under `scheme`.
+    /// Every scheme has one sibling per level, so a proof of any other length is refused.
+    pub fn verify_scheme_proof(
+        scheme: HashScheme,
+        leaf_hash: &str,
+        proof: &[String],
+        root: &str,
+        leaf_index: usize,
+        leaf_count: usize,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let depth = leaf_count.next_power_of_two().trailing_zeros() as usize;
+        if leaf_index >= leaf_count || proof.len() != depth {
+            return Ok(false);
+        }
+
+        let mut current_hash = decode_node(leaf_hash)?;
         let mut current_index = leaf_index;
 
         for sibling_hash in proof {
-            current_hash = if current_index % 2 == 0 {
-                Self::hash_pair(&current_hash, sibling_hash)?
+            let sibling = decode_node(sibling_hash)?;
+            current_hash = if current_index & 1 == 0 {
+                hash_pair(scheme, &current_hash, &sibling)
             } else {
-                Self::hash_pair(sibling_hash, &current_hash)?
+                hash_pair(scheme, &sibling, &current_hash)
             };
             current_index /= 2;
         }
 
-        Ok(current_hash == root)
+        Ok(hex::encode(current_hash) == root)
     }
 
     /// Get tree statistics
     pub fn stats(&self) -> MerkleStats {
         MerkleStats {
-            total_leaves: self.leaves.len(),
-            tree_height: self.tree.len(),
+            total_leaves: self.leaf_count(),
+            tree_height: self.height(),
             root_hash: self.root.clone(),
         }
     }
@@ -134,9 +370,10 @@ mod tests {
 
     #[test]
     fn test_single_leaf_tree() {
-        let hashes = vec!["abcd".to_string()];
+        let hashes = vec!["ab".repeat(32)];
         let tree = MerkleTree::new(hashes.clone()).unwrap();
-        assert_eq!(tree.root, "abcd");
+        assert_eq!(tree.root, hashes[0]);
+        assert!(MerkleTree::new(vec!["abcd".to_string()]).is_err());
     }
 
     #[test]
@@ -146,8 +383,8 @@ mod tests {
             "b".repeat(64),
         ];
         let tree = MerkleTree::new(hashes).unwrap();
-        assert_eq!(tree.leaves.len(), 2);
-        assert_ne!(tree.root, tree.leaves[0]);
+        assert_eq!(tree.leaf_count(), 2);
+        assert_ne!(tree.root, tree.leaf(0).unwrap());
     }
 
     #[test]
@@ -158,7 +395,7 @@ mod tests {
             "c".repeat(64),
         ];
         let tree = MerkleTree::new(hashes).unwrap();
-        assert_eq!(tree.leaves.len(), 3);
+        assert_eq!(tree.leaf_count(), 3);
     }
 
     #[test]
@@ -176,10 +413,11 @@ mod tests {
         
         // Verify proof
         let is_valid = MerkleTree::verify_proof(
-            &tree.leaves[0],
+            &hashes[0],
             &proof,
             &tree.root,
             0,
+            hashes.len(),
         ).unwrap();
         
         assert!(is_valid);
@@ -190,8 +428,8 @@ mod tests {
         let hash1 = "a".repeat(64);
         let hash2 = "b".repeat(64);
         
-        let result1 = MerkleTree::hash_pair(&hash1, &hash2).unwrap();
-        let result2 = MerkleTree::hash_pair(&hash2, &hash1).unwrap();
+        let result1 = MerkleTree::hash_node(HashScheme::Legacy, &hash1, &hash2).unwrap();
+        let result2 = MerkleTree::hash_node(HashScheme::Legacy, &hash2, &hash1).unwrap();
         
         // Should be the same due to consistent ordering
         assert_eq!(result1, result2);
@@ -203,4 +441,180 @@ mod tests {
         let result = MerkleTree::new(hashes);
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_tagged_nodes() {
+        let hashes: Vec<String> = ["a", "b", "c", "d"].iter().map(|c| c.repeat(64)).collect();
+        let legacy = MerkleTree::new(hashes.clone()).unwrap();
+        let tagged = MerkleTree::with_scheme(&hashes, HashScheme::V1).unwrap();
+        assert_ne!(legacy.root, tagged.root);
+
+        let mut preimage = vec![NODE_TAG];
+        preimage.extend(hex::decode(&hashes[0]).unwrap());
+        preimage.extend(hex::decode(&hashes[1]).unwrap());
+        assert_eq!(tagged.level(1)[0], Node::from(Keccak256::digest(&preimage)));
+
+        for (index, hash) in hashes.iter().enumerate() {
+            let proof = tagged.generate_proof(index).unwrap();
+            assert!(MerkleTree::verify_scheme_proof(HashScheme::V1, hash, &proof, &tagged.root, index, hashes.len()).unwrap());
+            assert!(!MerkleTree::verify_proof(hash, &proof, &tagged.root, index, hashes.len()).unwrap());
+        }
+
+        // The last leaf of an odd level is proven against its duplicate, at full height
+        let odd = MerkleTree::with_scheme(&hashes[..3], HashScheme::V1).unwrap();
+        let proof = odd.generate_proof(2).unwrap();
+        assert_eq!(proof[0], hashes[2]);
+        assert!(MerkleTree::verify_scheme_proof(HashScheme::V1, &hashes[2], &proof, &odd.root, 2, 3).unwrap());
+        assert!(!MerkleTree::verify_scheme_proof(HashScheme::V1, &hashes[2], &proof[1..], &odd.root, 2, 3).unwrap());
+        assert!(!MerkleTree::verify_scheme_proof(HashScheme::V1, &hashes[2], &proof, &odd.root, 3, 3).unwrap());
+    }
+
+    #[test]
+    fn test_v2_odd_levels_and_positions() {
+        let hashes: Vec<String> = ["a", "b", "c"].iter().map(|c| c.repeat(64)).collect();
+        let mut padded = hashes.clone();
+        padded.push("c".repeat(64));
+
+        // Duplicating the last node lets a longer leaf list share the root; v2 does not
+        assert_eq!(MerkleTree::new(hashes.clone()).unwrap().root, MerkleTree::new(padded.clone()).unwrap().root);
+        let tree = MerkleTree::with_scheme(hashes.clone(), HashScheme::V2).unwrap();
+        assert_ne!(tree.root, MerkleTree::with_scheme(padded, HashScheme::V2).unwrap().root);
+
+        let swapped = vec![hashes[1].clone(), hashes[0].clone(), hashes[2].clone()];
+        assert_ne!(tree.root, MerkleTree::with_scheme(swapped, HashScheme::V2).unwrap().root);
+
+        // Every proof is full height, the lone last leaf included, and pins its position
+        for (index, hash) in hashes.iter().enumerate() {
+            let proof = tree.generate_proof(index).unwrap();
+            assert_eq!(proof.len(), 2);
+            assert!(MerkleTree::verify_scheme_proof(HashScheme::V2, hash, &proof, &tree.root, index, hashes.len()).unwrap());
+        }
+        let proof = tree.generate_proof(0).unwrap();
+        assert!(!MerkleTree::verify_scheme_proof(HashScheme::V2, &hashes[0], &proof, &tree.root, 1, hashes.len()).unwrap());
+    }
+
+    #[test]
+    fn test_v2_vectors() {
+        let vectors: Vec<serde_json::Value> = serde_json::from_str(include_str!("../tests/vectors/merkle-v2.json")).unwrap();
+        for vector in vectors {
+            let leaves: Vec<String> = serde_json::from_value(vector["leaves"].clone()).unwrap();
+            let proofs: Vec<Vec<String>> = serde_json::from_value(vector["proofs"].clone()).unwrap();
+            let root = vector["root"].as_str().unwrap();
+
+            let tree = MerkleTree::with_scheme(leaves.clone(), HashScheme::V2).unwrap();
+            assert_eq!(tree.root, root, "{}", vector["name"]);
+            for (index, expected) in proofs.iter().enumerate() {
+                assert_eq!(&tree.generate_proof(index).unwrap(), expected, "{} leaf {}", vector["name"], index);
+                assert!(MerkleTree::verify_scheme_proof(HashScheme::V2, &leaves[index], expected, root, index, leaves.len()).unwrap());
+            }
+        }
+    }
+
+    /// OpenZeppelin `processMultiProof`: no positions, commutative node hash
+    fn oz_process_multiproof(scheme: HashScheme, multiproof: &MultiProof) -> String {
+        let mut hashes = Vec::new();
+        let (mut leaf_pos, mut hash_pos, mut proof_pos) = (0, 0, 0);
+        let mut next = |hashes: &Vec<String>| {
+            if leaf_pos < multiproof.leaves.len() {
+                leaf_pos += 1;
+                multiproof.leaves[leaf_pos - 1].clone()
+            } else {
+                hash_pos += 1;
+                hashes[hash_pos - 1].clone()
+            }
+        };
+
+        for &flag in &multiproof.proof_flags {
+            let a = next(&hashes);
+            let b = if flag {
+                next(&hashes)
+            } else {
+                proof_pos += 1;
+                multiproof.proof[proof_pos - 1].clone()
+            };
+            hashes.push(MerkleTree::hash_node(scheme, &a, &b).unwrap());
+        }
+        hashes.pop().unwrap_or_else(|| multiproof.leaves[0].clone())
+    }
+
+    #[test]
+    fn test_multiproofs() {
+        let hashes: Vec<String> = (0..7u8).map(|i| hex::encode(Keccak256::digest([i]))).collect();
+
+        for scheme in [HashScheme::Legacy, HashScheme::V1, HashScheme::V2] {
+            let tree = MerkleTree::with_scheme(hashes.clone(), scheme).unwrap();
+
+            for indices in [vec![6, 0, 3, 4], vec![5], vec![0, 1, 2, 3, 4, 5, 6], vec![6]] {
+                let multiproof = tree.generate_multiproof(&indices).unwrap();
+                assert!(multiproof.indices.windows(2).all(|pair| pair[0] < pair[1]));
+                assert!(MerkleTree::verify_multiproof(scheme, &multiproof, &tree.root).unwrap(), "{:?} {:?}", scheme, indices);
+
+                // Sorted-pair trees verify exactly like multiProofVerify
+                if scheme != HashScheme::V2 {
+                    assert_eq!(oz_process_multiproof(scheme, &multiproof), tree.root);
+                }
+            }
+
+            // Shared path hashes are sent once
+            let multiproof = tree.generate_multiproof(&[0, 1, 2, 3]).unwrap();
+            assert_eq!(multiproof.proof.len(), 1);
+            let single: usize = (0..4).map(|index| tree.generate_proof(index).unwrap().len()).sum();
+            assert!(multiproof.proof.len() < single);
+
+            let mut tampered = tree.generate_multiproof(&[1, 4]).unwrap();
+            tampered.leaves[0] = hashes[2].clone();
+            assert!(!MerkleTree::verify_multiproof(scheme, &tampered, &tree.root).unwrap());
+            let mut reflagged = tree.generate_multiproof(&[1, 4]).unwrap();
+            reflagged.proof_flags.reverse();
+            assert!(!MerkleTree::verify_multiproof(scheme, &reflagged, &tree.root).unwrap());
+        }
+
+        // Positions are part of a v2 proof
+        let tree = MerkleTree::with_scheme(hashes.clone(), HashScheme::V2).unwrap();
+        let mut moved = tree.generate_multiproof(&[1, 4]).unwrap();
+        moved.indices = vec![0, 4];
+        assert!(!MerkleTree::verify_multiproof(HashScheme::V2, &moved, &tree.root).unwrap());
+
+        assert!(tree.generate_multiproof(&[]).is_err());
+        assert!(tree.generate_multiproof(&[7]).is_err());
+    }
+
+    /// The level-by-level hex construction the flat tree replaced
+    fn reference_root(scheme: HashScheme, mut level: Vec<String>) -> String {
+        while level.len() > 1 {
+            if level.len() & 1 == 1 {
+                let last = decode_node(level.last().unwrap()).unwrap();
+                level.push(hex::encode(padding(scheme, &last)));
+            }
+            level = level.chunks(2).map(|pair| MerkleTree::hash_node(scheme, &pair[0], &pair[1]).unwrap()).collect();
+        }
+        level.remove(0)
+    }
+
+    #[test]
+    fn test_streaming_matches_level_by_level() {
+        for scheme in [HashScheme::Legacy, HashScheme::V1, HashScheme::V2] {
+            for count in 1..=33u32 {
+                let leaves: Vec<Node> = (0..count).map(|i| Keccak256::digest(i.to_be_bytes()).into()).collect();
+                let hashes: Vec<String> = leaves.iter().map(hex::encode).collect();
+
+                let tree = MerkleTree::with_scheme(&hashes, scheme).unwrap();
+                assert_eq!(tree.root, reference_root(scheme, hashes.clone()), "{:?} {}", scheme, count);
+                assert_eq!(tree.stats().tree_height, (count as f64).log2().ceil() as usize + 1);
+
+                // Pushing raw nodes into an unsized builder gives the same tree
+                let mut builder = MerkleBuilder::new(scheme);
+                leaves.iter().for_each(|leaf| builder.push(*leaf));
+                assert_eq!(builder.finish().unwrap().root, tree.root);
+
+                for (index, hash) in hashes.iter().enumerate() {
+                    let proof = tree.generate_proof(index).unwrap();
+                    assert_eq!(proof.len(), tree.stats().tree_height - 1);
+                    let valid = MerkleTree::verify_scheme_proof(scheme, hash, &proof, &tree.root, index, hashes.len()).unwrap();
+                    assert!(valid, "{:?} {} leaf {}", scheme, count, index);
+                }
+            }
+        }
+        assert!(MerkleBuilder::new(HashScheme::V2).finish().is_err());
+    }
+}
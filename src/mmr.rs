@@ -0,0 +1,318 @@
+//! Merkle Mountain Range over the digests of finalized proofs: an append-only accumulator
+//! whose roots let auditors check that proof history has only been appended to
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sha3::{Digest, Keccak256};
+
+use crate::merkle::{LEAF_TAG, NODE_TAG};
+
+type MmrResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Root preimage: `keccak(0x04 || leaf count (u64 big-endian) || peaks left to right)`
+pub const PEAKS_TAG: u8 = 0x04;
+
+/// Where a proof sits in the accumulator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MmrPosition {
+    pub leaf_index: u64,
+    /// Accumulator root once the proof was appended (`leaf_index + 1` leaves)
+    pub root: String,
+}
+
+/// Path from one proof digest to the root of an accumulator of `size` leaves
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub size: u64,
+    /// Siblings bottom-up within the leaf's mountain
+    pub siblings: Vec<String>,
+    /// Every mountain peak, left to right
+    pub peaks: Vec<String>,
+}
+
+/// Evidence that the accumulator of `new_size` leaves extends the one of `old_size` leaves
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConsistencyProof {
+    pub old_size: u64,
+    pub new_size: u64,
+    pub old_peaks: Vec<String>,
+    /// Per old peak, its siblings up to the new peak containing it
+    pub paths: Vec<Vec<String>>,
+    pub new_peaks: Vec<String>,
+}
+
+type Node = [u8; 32];
+
+/// `(first leaf, height)` of every mountain of an accumulator of `size` leaves, left to right
+fn mountains(size: u64) -> Vec<(u64, u32)> {
+    let mut start = 0;
+    (0..u64::BITS).rev()
+        .filter(|height| size & (1 << height) != 0)
+        .map(|height| {
+            let mountain = (start, height);
+            start += 1 << height;
+            mountain
+        })
+        .collect()
+}
+
+fn hash_leaf(digest: &str) -> MmrResult<Node> {
+    let mut hasher = Keccak256::new();
+    hasher.update([LEAF_TAG]);
+    hasher.update(hex::decode(digest.trim_start_matches("0x"))?);
+    Ok(hasher.finalize().into())
+}
+
+fn hash_node(left: &Node, right: &Node) -> Node {
+    let mut hasher = Keccak256::new();
+    hasher.update([NODE_TAG]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn bag_peaks(size: u64, peaks: &[Node]) -> Node {
+    let mut hasher = Keccak256::new();
+    hasher.update([PEAKS_TAG]);
+    hasher.update(size.to_be_bytes());
+    for peak in peaks {
+        hasher.update(peak);
+    }
+    hasher.finalize().into()
+}
+
+/// Climb from a node at `local` index of its level, taking `siblings` bottom-up
+fn climb(mut node: Node, mut local: u64, siblings: &[String]) -> MmrResult<Node> {
+    for sibling in siblings {
+        let sibling = decode_node(sibling)?;
+        node = if local & 1 == 0 { hash_node(&node, &sibling) } else { hash_node(&sibling, &node) };
+        local >>= 1;
+    }
+    Ok(node)
+}
+
+fn decode_node(node: &str) -> MmrResult<Node> {
+    hex::decode(node)?.try_into().map_err(|_| "MMR nodes are 32 bytes".into())
+}
+
+fn decode_nodes(nodes: &[String]) -> MmrResult<Vec<Node>> {
+    nodes.iter().map(|node| decode_node(node)).collect()
+}
+
+fn encode_nodes(nodes: &[Node]) -> Vec<String> {
+    nodes.iter().map(hex::encode).collect()
+}
+
+/// The accumulator: proof digests in the order they were finalized
+#[derive(Debug, Clone, Default)]
+pub struct Mmr {
+    digests: Vec<String>,
+    leaves: Vec<Node>,
+}
+
+impl Mmr {
+    /// Rebuild from the stored digests
+    pub fn from_digests(digests: Vec<String>) -> MmrResult<Self> {
+        let leaves = digests.iter().map(|digest| hash_leaf(digest)).collect::<MmrResult<_>>()?;
+        Ok(Self { digests, leaves })
+    }
+
+    pub fn digests(&self) -> &[String] {
+        &self.digests
+    }
+
+    pub fn len(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn append(&mut self, digest: &str) -> MmrResult<MmrPosition> {
+        self.leaves.push(hash_leaf(digest)?);
+        self.digests.push(digest.to_string());
+        Ok(MmrPosition { leaf_index: self.len() - 1, root: self.root_at(self.len())? })
+    }
+
+    /// Root of the accumulator when it held its first `size` leaves
+    pub fn root_at(&self, size: u64) -> MmrResult<String> {
+        Ok(hex::encode(bag_peaks(size, &self.peaks(size)?)))
+    }
+
+    pub fn inclusion_proof(&self, leaf_index: u64, size: u64) -> MmrResult<InclusionProof> {
+        let peaks = self.peaks(size)?;
+        if leaf_index >= size {
+            return Err(format!("Leaf {} is not in an accumulator of {} leaves", leaf_index, size).into());
+        }
+
+        let (start, height) = mountains(size).into_iter()
+            .find(|(start, height)| leaf_index < start + (1 << height))
+            .unwrap();
+        let local = leaf_index - start;
+        let siblings = (0..height)
+            .map(|level| self.subtree(start + (((local >> level) ^ 1) << level), level))
+            .collect::<Vec<_>>();
+
+        Ok(InclusionProof { leaf_index, size, siblings: encode_nodes(&siblings), peaks: encode_nodes(&peaks) })
+    }
+
+    pub fn consistency_proof(&self, old_size: u64, new_size: u64) -> MmrResult<ConsistencyProof> {
+        if old_size > new_size {
+            return Err("Accumulators only grow: old_size must not exceed new_size".into());
+        }
+        let old_peaks = self.peaks(old_size)?;
+        let new_peaks = self.peaks(new_size)?;
+        let new_mountains = mountains(new_size);
+
+        let paths = mountains(old_size).into_iter()
+            .map(|(start, height)| {
+                let (new_start, new_height) = *new_mountains.iter()
+                    .find(|(new_start, new_height)| start < new_start + (1 << new_height))
+                    .unwrap();
+                let local = (start - new_start) >> height;
+                let siblings: Vec<Node> = (height..new_height)
+                    .map(|level| self.subtree(new_start + (((local >> (level - height)) ^ 1) << level), level))
+                    .collect();
+                encode_nodes(&siblings)
+            })
+            .collect();
+
+        Ok(ConsistencyProof {
+            old_size,
+            new_size,
+            old_peaks: encode_nodes(&old_peaks),
+            paths,
+            new_peaks: encode_nodes(&new_peaks),
+        })
+    }
+
+    fn peaks(&self, size: u64) -> MmrResult<Vec<Node>> {
+        if size == 0 || size > self.len() {
+            return Err(format!("Accumulator has {} leaves, no root at size {}", self.len(), size).into());
+        }
+        Ok(mountains(size).into_iter().map(|(start, height)| self.subtree(start, height)).collect())
+    }
+
+    /// Root of the perfect subtree of `2^height` leaves from `start`
+    fn subtree(&self, start: u64, height: u32) -> Node {
+        let mut level: Vec<Node> = self.leaves[start as usize..(start + (1 << height)) as usize].to_vec();
+        while level.len() > 1 {
+            level = level.chunks(2).map(|pair| hash_node(&pair[0], &pair[1])).collect();
+        }
+        level[0]
+    }
+}
+
+impl InclusionProof {
+    /// Whether `digest` is leaf `leaf_index` of the accumulator with root `root`
+    pub fn verify(&self, digest: &str, root: &str) -> MmrResult<bool> {
+        let mountains = mountains(self.size);
+        let peaks = decode_nodes(&self.peaks)?;
+        let Some(position) = mountains.iter().position(|(start, height)| self.leaf_index < start + (1 << height)) else {
+            return Ok(false);
+        };
+        let (start, height) = mountains[position];
+        if peaks.len() != mountains.len() || self.siblings.len() != height as usize {
+            return Ok(false);
+        }
+
+        let peak = climb(hash_leaf(digest)?, self.leaf_index - start, &self.siblings)?;
+        Ok(peak == peaks[position] && hex::encode(bag_peaks(self.size, &peaks)) == root)
+    }
+}
+
+impl ConsistencyProof {
+    /// Whether the accumulator with `new_root` only appended to the one with `old_root`
+    pub fn verify(&self, old_root: &str, new_root: &str) -> MmrResult<bool> {
+        let old_mountains = mountains(self.old_size);
+        let new_mountains = mountains(self.new_size);
+        let old_peaks = decode_nodes(&self.old_peaks)?;
+        let new_peaks = decode_nodes(&self.new_peaks)?;
+        if self.old_size == 0 || self.old_size > self.new_size
+            || old_peaks.len() != old_mountains.len()
+            || self.paths.len() != old_mountains.len()
+            || new_peaks.len() != new_mountains.len() {
+            return Ok(false);
+        }
+        if hex::encode(bag_peaks(self.old_size, &old_peaks)) != old_root
+            || hex::encode(bag_peaks(self.new_size, &new_peaks)) != new_root {
+            return Ok(false);
+        }
+
+        // Every old peak must sit inside the new mountain covering its leaves
+        for ((&(start, height), old_peak), path) in old_mountains.iter().zip(&old_peaks).zip(&self.paths) {
+            let Some(position) = new_mountains.iter().position(|(new_start, new_height)| start < new_start + (1 << new_height)) else {
+                return Ok(false);
+            };
+            let (new_start, new_height) = new_mountains[position];
+            if path.len() != (new_height - height) as usize
+                || climb(*old_peak, (start - new_start) >> height, path)? != new_peaks[position] {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(i: u64) -> String {
+        format!("0x{}", hex::encode(Keccak256::digest(i.to_be_bytes())))
+    }
+
+    #[test]
+    fn test_inclusion_and_consistency() {
+        let mut mmr = Mmr::default();
+        let mut roots = Vec::new();
+        for i in 0..11 {
+            let position = mmr.append(&digest(i)).unwrap();
+            assert_eq!(position.leaf_index, i);
+            roots.push(position.root);
+        }
+
+        for size in 1..=11 {
+            let root = &roots[size as usize - 1];
+            assert_eq!(&mmr.root_at(size).unwrap(), root);
+            for leaf in 0..size {
+                let proof = mmr.inclusion_proof(leaf, size).unwrap();
+                assert!(proof.verify(&digest(leaf), root).unwrap(), "leaf {} of {}", leaf, size);
+                assert!(!proof.verify(&digest(leaf + 1), root).unwrap());
+            }
+            for old_size in 1..=size {
+                let proof = mmr.consistency_proof(old_size, size).unwrap();
+                assert!(proof.verify(&roots[old_size as usize - 1], root).unwrap(), "{} -> {}", old_size, size);
+            }
+        }
+
+        // Survives a reload from the stored digests
+        let reloaded = Mmr::from_digests(mmr.digests().to_vec()).unwrap();
+        assert_eq!(reloaded.root_at(11).unwrap(), roots[10]);
+        assert!(mmr.inclusion_proof(11, 11).is_err());
+        assert!(mmr.consistency_proof(7, 3).is_err());
+    }
+
+    #[test]
+    fn test_rewritten_history_is_inconsistent() {
+        let mut honest = Mmr::default();
+        let mut rewritten = Mmr::default();
+        for i in 0..6 {
+            honest.append(&digest(i)).unwrap();
+        }
+        // Window 2 dropped, later windows shifted down
+        for i in [0, 1, 3, 4, 5, 6, 7] {
+            rewritten.append(&digest(i)).unwrap();
+        }
+
+        let old_root = honest.root_at(6).unwrap();
+        let proof = rewritten.consistency_proof(6, 7).unwrap();
+        assert!(!proof.verify(&old_root, &rewritten.root_at(7).unwrap()).unwrap());
+
+        // Nor can the honest old peaks be grafted onto the rewritten accumulator
+        let grafted = ConsistencyProof { old_peaks: honest.consistency_proof(6, 6).unwrap().old_peaks, ..proof };
+        assert!(!grafted.verify(&old_root, &rewritten.root_at(7).unwrap()).unwrap());
+    }
+}
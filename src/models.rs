@@ -1,26 +1,113 @@
 // FILE: src/models.rs
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::attestation::DeviceCertificate;
+use crate::merkle::MultiProof;
+use crate::mmr::MmrPosition;
+use crate::seal::CostEstimate;
+use crate::state_tree::{MeterState, StateProof};
+use crate::sum_tree::{SumProof, SumRoot};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MeterRecord {
     pub meter_id: String,
     pub timestamp: i64,
     pub kwh_delta: f64,
     pub nonce: String,
+    /// Bytes a Legacy or EIP-191 signature covers; omitted by meters signing the pre-v1 JSON
+    #[serde(default, skip_serializing_if = "RecordEncoding::is_legacy")]
+    pub encoding: RecordEncoding,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How a record is rendered into the bytes a Legacy or EIP-191 signature covers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum RecordEncoding {
+    /// `serde_json` rendering of the record, as signed before the canonical encoding
+    #[default]
+    #[serde(rename = "greenshare.meter-record.v0")]
+    Legacy,
+    /// JCS of the record plus the `encoding` tag, shared with `src/crypto.ts`
+    #[serde(rename = "greenshare.meter-record.v1")]
+    V1,
+}
+
+impl RecordEncoding {
+    pub fn is_legacy(&self) -> bool {
+        *self == RecordEncoding::Legacy
+    }
+}
+
+/// How a meter signed its record
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// sha256 over the record JSON, raw recovery byte
+    #[default]
+    Legacy,
+    /// `personal_sign` over the record JSON
+    Eip191,
+    /// EIP-712 typed `MeterRecord`
+    Eip712,
+}
+
+/// How a proof's record hashes and Merkle nodes are computed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum HashScheme {
+    /// keccak over the record JSON, untagged sorted-pair nodes (proofs without `hash_scheme`)
+    #[default]
+    #[serde(rename = "greenshare.merkle.v0")]
+    Legacy,
+    /// `keccak(0x00 || canonical record)` leaves, `keccak(0x01 || sorted pair)` nodes
+    #[serde(rename = "greenshare.merkle.v1")]
+    V1,
+    /// V1 leaves, positional `keccak(0x01 || left || right)` nodes, odd levels padded with
+    /// the zero hash instead of duplicating their last node
+    #[serde(rename = "greenshare.merkle.v2")]
+    V2,
+}
+
+impl HashScheme {
+    /// Scheme new proofs are generated with
+    pub const CURRENT: HashScheme = HashScheme::V2;
+
+    pub fn is_legacy(&self) -> bool {
+        *self == HashScheme::Legacy
+    }
+
+    /// `N` of `greenshare.merkle.vN`
+    pub fn number(&self) -> u8 {
+        match self {
+            HashScheme::Legacy => 0,
+            HashScheme::V1 => 1,
+            HashScheme::V2 => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SignedMeterData {
     pub record: MeterRecord,
     pub sig: String,
+    #[serde(default)]
+    pub scheme: SignatureScheme,
+    /// Trust anchor certificate for meters that were never registered individually
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<DeviceCertificate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerifiedRecord {
     pub record: MeterRecord,
     pub signature: String,
+    #[serde(default)]
+    pub scheme: SignatureScheme,
+    /// Signer identity: recovered Ethereum address, or the registered public key for
+    /// non-recoverable schemes (unset when verification is disabled)
+    #[serde(default)]
+    pub signer: Option<String>,
     pub verification_timestamp: DateTime<Utc>,
     pub record_hash: String,
 }
@@ -32,7 +119,7 @@ pub struct AggregationWindow {
     pub records: Vec<VerifiedRecord>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProofData {
     pub proof_id: Uuid,
     pub aggregate_kwh: f64,
@@ -43,9 +130,45 @@ pub struct ProofData {
     pub meter_ids: Vec<String>,
     pub generated_at: DateTime<Utc>,
     pub version: String,
+    /// Omitted for legacy proofs so they serialize exactly as before
+    #[serde(default, skip_serializing_if = "HashScheme::is_legacy")]
+    pub hash_scheme: HashScheme,
+    /// Merkle sum tree root committing to the records and their micro-kWh total
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sum_root: Option<SumRoot>,
+    /// Sparse Merkle root of every meter's cumulative state once this window was counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_root: Option<String>,
+    /// Position in the accumulator of every finalized proof's digest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mmr: Option<MmrPosition>,
+}
+
+/// Convert kWh to integer micro-kWh (the unit of on-chain amounts)
+pub fn kwh_to_micro(kwh: f64) -> u64 {
+    (kwh * 1_000_000.0).round().max(0.0) as u64
+}
+
+impl ProofData {
+    /// Aggregate energy in micro-kWh: the sum tree total when the proof commits to one
+    pub fn aggregate_micro_kwh(&self) -> u64 {
+        match &self.sum_root {
+            Some(sum_root) => sum_root.total_micro_kwh,
+            None => kwh_to_micro(self.aggregate_kwh),
+        }
+    }
+
+    /// Quota window key used on chain: `{start}/{end}` in RFC 3339 UTC, second precision
+    pub fn window_id(&self) -> String {
+        format!(
+            "{}/{}",
+            self.window_start.format("%Y-%m-%dT%H:%M:%SZ"),
+            self.window_end.format("%Y-%m-%dT%H:%M:%SZ"),
+        )
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct IngestResponse {
     pub success: bool,
     pub message: String,
@@ -53,7 +176,7 @@ pub struct IngestResponse {
     pub receipt_id: Uuid,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct HealthResponse {
     pub status: String,
     pub timestamp: DateTime<Utc>,
@@ -61,7 +184,7 @@ pub struct HealthResponse {
     pub version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct StatusResponse {
     pub status: String,
     pub current_window: Option<WindowStatus>,
@@ -71,7 +194,7 @@ pub struct StatusResponse {
     pub configuration: StatusConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct WindowStatus {
     pub window_start: DateTime<Utc>,
     pub window_end: DateTime<Utc>,
@@ -79,20 +202,20 @@ pub struct WindowStatus {
     pub time_remaining_seconds: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct StatusConfig {
     pub agg_window_sec: u64,
     pub max_records_per_window: usize,
     pub enable_signature_verification: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SealRequest {
     pub proof_id: Option<Uuid>,
     pub force_latest: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SealResponse {
     pub success: bool,
     pub message: String,
@@ -101,7 +224,114 @@ pub struct SealResponse {
     pub seal_response: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SealEstimateQuery {
+    pub bytes: u64,
+    pub epochs: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SealEstimateResponse {
+    /// Cost of one blob of the given size
+    #[serde(flatten)]
+    pub estimate: CostEstimate,
+    /// Cost of every blob sealing an envelope of the given size stores, record chunks excluded
+    pub seal_cost: u64,
+    pub budget: Option<u64>,
+    /// Whether `seal_cost` fits the budget. With `SEAL_RECORDS` on, `/seal` also charges the
+    /// record chunks and their manifest, so it may still refuse the seal.
+    pub within_budget: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SuiMintTxQuery {
+    pub recipient: String,
+    /// Fresher treasury cap reference than the configured one
+    pub treasury_cap_version: Option<u64>,
+    pub treasury_cap_digest: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EvmProofQuery {
+    /// Override `EVM_CHAIN_ID`
+    pub chain_id: Option<u64>,
+    /// Override `EVM_VERIFYING_CONTRACT`
+    pub verifying_contract: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct QuotaBatchQuery {
+    /// Only windows starting at or after this time
+    pub since: Option<DateTime<Utc>>,
+    pub batch_size: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MultiProofQuery {
+    /// Comma-separated leaf indices
+    pub indices: Option<String>,
+    /// Comma-separated meter IDs; proves every record of these meters
+    pub meter_ids: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MultiProofResponse {
+    pub proof_id: Uuid,
+    pub merkle_root: String,
+    pub hash_scheme: HashScheme,
+    #[serde(flatten)]
+    pub multiproof: MultiProof,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SumProofQuery {
+    /// Leaf index of the record
+    pub index: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SumProofResponse {
+    pub proof_id: Uuid,
+    pub sum_root: SumRoot,
+    pub proof: SumProof,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MmrQuery {
+    /// Accumulator size to prove against; defaults to the current size
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MmrConsistencyQuery {
+    pub from: u64,
+    /// Defaults to the current size
+    pub to: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MmrRootResponse {
+    pub size: u64,
+    pub root: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MeterStateResponse {
+    pub meter_id: String,
+    pub state_root: String,
+    /// `None` for meters that never had a reading in a finalized window
+    pub state: Option<MeterState>,
+    pub proof: StateProof,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReconciliationQuery {
+    /// Only list proofs that diverge from chain state
+    #[serde(default)]
+    pub divergent: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ErrorResponse {
     pub error: String,
     pub code: String,
@@ -0,0 +1,291 @@
+//! OpenAPI 3 description of the `/api/v1` routes, generated from the handler request and
+//! response types, and the deprecation policy of the legacy unprefixed routes
+
+use actix_web::http::header::{HeaderName, HeaderValue};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+use crate::attestation::DeviceCertificate;
+use crate::certificate::CertificatePayload;
+use crate::evm::SignedEvmProof;
+use crate::mmr::{ConsistencyProof, InclusionProof};
+use crate::models::*;
+use crate::quota::QuotaBatch;
+use crate::reconcile::ReconciliationReport;
+use crate::registry::{KeyEpoch, KeyRotation};
+use crate::sui::MintTransaction;
+
+/// Prefix of the versioned API
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Routes still served without [`API_PREFIX`]; they answer with deprecation headers
+pub const LEGACY_ROUTES: &[&str] = &["/ingest", "/health", "/status", "/proofs/latest", "/seal"];
+
+/// Legacy routes are deprecated from 2026-11-01 (RFC 9745 `Deprecation`, a Unix timestamp)
+pub const LEGACY_DEPRECATED_AT: i64 = 1_793_491_200;
+/// and may be removed after six months (RFC 8594 `Sunset`)
+pub const LEGACY_SUNSET: &str = "Sat, 01 May 2027 00:00:00 GMT";
+
+/// Headers every response of the legacy route `path` carries
+pub fn deprecation_headers(path: &str) -> [(HeaderName, HeaderValue); 3] {
+    let link = format!("<{}{}>; rel=\"successor-version\"", API_PREFIX, path);
+    [
+        (HeaderName::from_static("deprecation"), HeaderValue::from_str(&format!("@{}", LEGACY_DEPRECATED_AT)).unwrap()),
+        (HeaderName::from_static("sunset"), HeaderValue::from_static(LEGACY_SUNSET)),
+        (HeaderName::from_static("link"), HeaderValue::from_str(&link).unwrap_or_else(|_| HeaderValue::from_static(API_PREFIX))),
+    ]
+}
+
+/// The document served at `/api/v1/openapi.json`
+pub fn document() -> &'static Value {
+    static DOCUMENT: OnceLock<Value> = OnceLock::new();
+    DOCUMENT.get_or_init(build)
+}
+
+/// Paths and the component schemas their operations refer to
+struct ApiDoc {
+    gen: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+struct Operation<'a> {
+    doc: &'a mut ApiDoc,
+    method: &'static str,
+    path: &'static str,
+    operation: Map<String, Value>,
+}
+
+impl ApiDoc {
+    fn operation(&mut self, method: &'static str, path: &'static str, operation_id: &str, summary: &str) -> Operation<'_> {
+        let mut operation = Map::new();
+        operation.insert("operationId".to_string(), json!(operation_id));
+        operation.insert("summary".to_string(), json!(summary));
+        Operation { doc: self, method, path, operation }
+    }
+
+    fn get(&mut self, path: &'static str, operation_id: &str, summary: &str) -> Operation<'_> {
+        self.operation("get", path, operation_id, summary)
+    }
+
+    fn post(&mut self, path: &'static str, operation_id: &str, summary: &str) -> Operation<'_> {
+        self.operation("post", path, operation_id, summary)
+    }
+}
+
+impl Operation<'_> {
+    fn parameters(&mut self) -> &mut Vec<Value> {
+        self.operation.entry("parameters").or_insert_with(|| json!([])).as_array_mut().unwrap()
+    }
+
+    fn path_param<T: JsonSchema>(mut self, name: &str) -> Self {
+        let schema = self.doc.gen.subschema_for::<T>();
+        self.parameters().push(json!({ "name": name, "in": "path", "required": true, "schema": schema }));
+        self
+    }
+
+    /// One query parameter per field of `T`
+    fn query<T: JsonSchema>(mut self) -> Self {
+        let schema = serde_json::to_value(self.doc.gen.root_schema_for::<T>().schema).unwrap();
+        let required: Vec<&str> = schema["required"].as_array().into_iter().flatten()
+            .filter_map(Value::as_str)
+            .collect();
+
+        let mut parameters = Vec::new();
+        for (name, property) in schema["properties"].as_object().into_iter().flatten() {
+            let mut property = property.clone();
+            let description = property.as_object_mut().and_then(|property| property.remove("description"));
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&name.as_str()),
+                "schema": property,
+            });
+            if let Some(description) = description {
+                parameter["description"] = description;
+            }
+            parameters.push(parameter);
+        }
+        self.parameters().extend(parameters);
+        self
+    }
+
+    fn body<T: JsonSchema>(mut self) -> Self {
+        let schema = self.doc.gen.subschema_for::<T>();
+        self.operation.insert("requestBody".to_string(), json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema } },
+        }));
+        self
+    }
+
+    /// Successful response; every error answers with an [`ErrorResponse`]
+    fn response<T: JsonSchema>(mut self) {
+        let schema = self.doc.gen.subschema_for::<T>();
+        let error = self.doc.gen.subschema_for::<ErrorResponse>();
+        self.operation.insert("responses".to_string(), json!({
+            "200": { "description": "OK", "content": { "application/json": { "schema": schema } } },
+            "default": { "description": "Error", "content": { "application/json": { "schema": error } } },
+        }));
+
+        let path = self.doc.paths.entry(format!("{}{}", API_PREFIX, self.path)).or_insert_with(|| json!({}));
+        path[self.method] = Value::Object(self.operation);
+    }
+}
+
+fn build() -> Value {
+    let mut doc = ApiDoc {
+        gen: SchemaSettings::openapi3().into_generator(),
+        paths: Map::new(),
+    };
+
+    doc.post("/ingest", "ingest_data", "Submit a signed meter reading")
+        .body::<SignedMeterData>()
+        .response::<IngestResponse>();
+    doc.get("/health", "health_check", "Liveness and uptime")
+        .response::<HealthResponse>();
+    doc.get("/status", "get_status", "Current window and aggregation counters")
+        .response::<StatusResponse>();
+    doc.get("/openapi.json", "get_openapi", "This document")
+        .response::<Value>();
+    doc.get("/proofs/latest", "get_latest_proof", "Most recently finalized proof")
+        .response::<ProofData>();
+    doc.get("/proofs/{id}/sui/mint-tx", "get_sui_mint_tx", "Unsigned Sui mint transaction for a proof")
+        .path_param::<Uuid>("id")
+        .query::<SuiMintTxQuery>()
+        .response::<MintTransaction>();
+    doc.get("/proofs/{id}/certificate", "get_certificate_payload", "Certificate payload of a sealed proof")
+        .path_param::<Uuid>("id")
+        .response::<CertificatePayload>();
+    doc.get("/proofs/{id}/evm", "get_evm_proof", "Proof in ABI form with the enclave's EIP-712 signature")
+        .path_param::<Uuid>("id")
+        .query::<EvmProofQuery>()
+        .response::<SignedEvmProof>();
+    doc.get("/proofs/{id}/multiproof", "get_multiproof", "Merkle multi-proof of several records of a proof")
+        .path_param::<Uuid>("id")
+        .query::<MultiProofQuery>()
+        .response::<MultiProofResponse>();
+    doc.get("/proofs/{id}/sum-proof", "get_sum_proof", "Merkle sum tree proof that a reading was counted")
+        .path_param::<Uuid>("id")
+        .query::<SumProofQuery>()
+        .response::<SumProofResponse>();
+    doc.get("/mmr", "get_mmr_root", "Root of the proof accumulator")
+        .query::<MmrQuery>()
+        .response::<MmrRootResponse>();
+    doc.get("/mmr/consistency", "get_mmr_consistency_proof", "Proof that the accumulator was only appended to")
+        .query::<MmrConsistencyQuery>()
+        .response::<ConsistencyProof>();
+    doc.get("/mmr/{leaf_index}", "get_mmr_inclusion_proof", "Inclusion proof of a finalized proof in the accumulator")
+        .path_param::<u64>("leaf_index")
+        .query::<MmrQuery>()
+        .response::<InclusionProof>();
+    doc.get("/quotas", "get_quota_batches", "Signed `batch_set_quotas` arguments for every finalized window")
+        .query::<QuotaBatchQuery>()
+        .response::<Vec<QuotaBatch>>();
+    doc.get("/reconciliation", "get_reconciliation", "Latest chain reconciliation report")
+        .query::<ReconciliationQuery>()
+        .response::<ReconciliationReport>();
+    doc.post("/meters/{id}/rotate", "rotate_meter_key", "Hand a meter over to a new key")
+        .path_param::<String>("id")
+        .body::<KeyRotation>()
+        .response::<Vec<KeyEpoch>>();
+    doc.get("/meters/{id}/keys", "get_meter_keys", "Key history of a meter")
+        .path_param::<String>("id")
+        .response::<Vec<KeyEpoch>>();
+    doc.post("/meters/{id}/certificate", "enroll_meter_certificate", "Enroll a meter with a device certificate")
+        .path_param::<String>("id")
+        .body::<DeviceCertificate>()
+        .response::<Vec<KeyEpoch>>();
+    doc.get("/meters/{id}/state", "get_meter_state", "Cumulative state of a meter with an (non-)inclusion proof")
+        .path_param::<String>("id")
+        .response::<MeterStateResponse>();
+    doc.post("/seal", "seal_proof", "Seal a proof to Walrus")
+        .body::<SealRequest>()
+        .response::<SealResponse>();
+    doc.get("/seal/estimate", "estimate_seal_cost", "Estimated Walrus storage cost")
+        .query::<SealEstimateQuery>()
+        .response::<SealEstimateResponse>();
+
+    // Legacy routes: the same operations, flagged deprecated
+    let mut paths = doc.paths;
+    for &route in LEGACY_ROUTES {
+        let mut item = paths[&format!("{}{}", API_PREFIX, route)].clone();
+        for operation in item.as_object_mut().unwrap().values_mut() {
+            operation["deprecated"] = json!(true);
+            operation["operationId"] = json!(format!("{}_legacy", operation["operationId"].as_str().unwrap()));
+            operation["description"] = json!(format!(
+                "Deprecated: use `{}{}`. Responses carry `Deprecation`, `Sunset` and a `successor-version` `Link`.",
+                API_PREFIX, route,
+            ));
+        }
+        paths.insert(route.to_string(), item);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "GreenShare ROFL Enclave API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "TEE aggregation and verification of smart meter data",
+        },
+        "paths": paths,
+        "components": { "schemas": doc.gen.take_definitions() },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every `$ref` in `value`
+    fn refs(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::String(reference)) = object.get("$ref") {
+                    found.push(reference.clone());
+                }
+                object.values().for_each(|value| refs(value, found));
+            }
+            Value::Array(values) => values.iter().for_each(|value| refs(value, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_document_is_self_contained() {
+        let document = document();
+        assert_eq!(document["openapi"], "3.0.3");
+
+        let mut found = Vec::new();
+        refs(document, &mut found);
+        assert!(!found.is_empty());
+        for reference in found {
+            let name = reference.strip_prefix("#/components/schemas/").unwrap();
+            assert!(document["components"]["schemas"].get(name).is_some(), "dangling {}", reference);
+        }
+
+        let ingest = &document["paths"]["/api/v1/ingest"]["post"];
+        assert_eq!(ingest["requestBody"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/SignedMeterData");
+        let multiproof = &document["paths"]["/api/v1/proofs/{id}/multiproof"]["get"]["parameters"];
+        let names: Vec<&str> = multiproof.as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["id", "indices", "meter_ids"]);
+        assert_eq!(document["paths"]["/api/v1/proofs/{id}/sum-proof"]["get"]["parameters"][1]["required"], true);
+    }
+
+    #[test]
+    fn test_legacy_routes_are_deprecated() {
+        let document = document();
+        for route in LEGACY_ROUTES {
+            let item = document["paths"][*route].as_object().unwrap();
+            assert!(item.values().all(|operation| operation["deprecated"] == true), "{}", route);
+            assert!(document["paths"][format!("{}{}", API_PREFIX, route)].as_object().unwrap()
+                .values().all(|operation| operation.get("deprecated").is_none()));
+        }
+
+        let headers = deprecation_headers("/health");
+        assert_eq!(headers[0].1, "@1793491200");
+        assert_eq!(headers[2].1, "</api/v1/health>; rel=\"successor-version\"");
+    }
+}
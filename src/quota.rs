@@ -0,0 +1,191 @@
+//! Per-window minting quotas for `sKWH::batch_set_quotas`, signed by the enclave
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::models::ProofData;
+use crate::signer::{self, EnclaveSigner};
+
+const QUOTA_BATCH_DOMAIN: &[u8] = b"greenshare.quota-batch.v1";
+
+/// Entries per batch by default; keeps each pure argument well under Sui's size limit
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+pub const MAX_BATCH_SIZE: usize = 250;
+
+/// Quota a window produced: the sum of the proofs finalized for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct QuotaEntry {
+    pub window_id: String,
+    pub window_start: DateTime<Utc>,
+    /// Quota in micro-sKWH
+    pub quota_amount: u64,
+    pub proof_ids: Vec<Uuid>,
+}
+
+/// One `batch_set_quotas(admin_cap, quota_ledger, window_ids, quota_amounts, clock)` call
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuotaBatch {
+    pub window_ids: Vec<String>,
+    pub quota_amounts: Vec<u64>,
+    /// Base64 BCS of `window_ids` (`vector<String>`) and `quota_amounts` (`vector<u64>`) as pure inputs
+    pub window_ids_bcs: String,
+    pub quota_amounts_bcs: String,
+    pub entries: Vec<QuotaEntry>,
+    /// `keccak256(domain || window_ids_bcs || quota_amounts_bcs)`
+    pub batch_hash: String,
+    /// EIP-191 signature over the 32 `batch_hash` bytes
+    pub signature: String,
+    pub signer: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Quota entries per window, oldest first
+pub fn quota_entries(proofs: &[ProofData]) -> Vec<QuotaEntry> {
+    let mut windows: BTreeMap<(DateTime<Utc>, String), QuotaEntry> = BTreeMap::new();
+
+    for proof in proofs {
+        let window_id = proof.window_id();
+        let entry = windows.entry((proof.window_start, window_id.clone())).or_insert_with(|| QuotaEntry {
+            window_id,
+            window_start: proof.window_start,
+            quota_amount: 0,
+            proof_ids: Vec::new(),
+        });
+        entry.quota_amount += proof.aggregate_micro_kwh();
+        entry.proof_ids.push(proof.proof_id);
+    }
+
+    windows.into_values()
+        .filter(|entry| entry.quota_amount > 0)
+        .collect()
+}
+
+fn batch_args(window_ids: &[String], quota_amounts: &[u64]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let window_ids = bcs::to_bytes(window_ids).map_err(|e| e.to_string())?;
+    let quota_amounts = bcs::to_bytes(quota_amounts).map_err(|e| e.to_string())?;
+    Ok((window_ids, quota_amounts))
+}
+
+fn batch_hash(window_ids_bcs: &[u8], quota_amounts_bcs: &[u8]) -> [u8; 32] {
+    let mut preimage = QUOTA_BATCH_DOMAIN.to_vec();
+    preimage.extend_from_slice(window_ids_bcs);
+    preimage.extend_from_slice(quota_amounts_bcs);
+    signer::keccak256(&preimage)
+}
+
+/// Split entries into signed batches of at most `batch_size`
+pub fn sign_batches(signer: &EnclaveSigner, entries: Vec<QuotaEntry>, batch_size: usize) -> Result<Vec<QuotaBatch>, String> {
+    if batch_size == 0 || batch_size > MAX_BATCH_SIZE {
+        return Err(format!("Batch size must be between 1 and {}", MAX_BATCH_SIZE));
+    }
+
+    entries.chunks(batch_size).map(|chunk| {
+        let window_ids: Vec<String> = chunk.iter().map(|entry| entry.window_id.clone()).collect();
+        let quota_amounts: Vec<u64> = chunk.iter().map(|entry| entry.quota_amount).collect();
+        let (window_ids_bcs, quota_amounts_bcs) = batch_args(&window_ids, &quota_amounts)?;
+        let hash = batch_hash(&window_ids_bcs, &quota_amounts_bcs);
+
+        Ok(QuotaBatch {
+            window_ids,
+            quota_amounts,
+            window_ids_bcs: BASE64.encode(window_ids_bcs),
+            quota_amounts_bcs: BASE64.encode(quota_amounts_bcs),
+            entries: chunk.to_vec(),
+            batch_hash: format!("0x{}", hex::encode(hash)),
+            signature: signer.sign_message(&hash),
+            signer: signer.address(),
+            generated_at: Utc::now(),
+        })
+    }).collect()
+}
+
+/// Check that a batch's arguments are what its signer signed
+pub fn verify_batch(batch: &QuotaBatch) -> bool {
+    let Ok((window_ids_bcs, quota_amounts_bcs)) = batch_args(&batch.window_ids, &batch.quota_amounts) else {
+        return false;
+    };
+    let hash = batch_hash(&window_ids_bcs, &quota_amounts_bcs);
+    if format!("0x{}", hex::encode(hash)) != batch.batch_hash
+        || BASE64.encode(&window_ids_bcs) != batch.window_ids_bcs
+        || BASE64.encode(&quota_amounts_bcs) != batch.quota_amounts_bcs {
+        return false;
+    }
+
+    let signature: [u8; 65] = match hex::decode(batch.signature.trim_start_matches("0x")) {
+        Ok(bytes) => match bytes.try_into() {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        },
+        Err(_) => return false,
+    };
+    match signer::recover_address(&signer::eip191_hash(&hash), &signature) {
+        Ok(address) => signer::to_checksum_address(&address).eq_ignore_ascii_case(&batch.signer),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HashScheme;
+    use chrono::TimeZone;
+
+    fn proof(hour: u32, kwh: f64) -> ProofData {
+        ProofData {
+            proof_id: Uuid::new_v4(),
+            aggregate_kwh: kwh,
+            merkle_root: "ab".repeat(32),
+            window_start: Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap(),
+            window_end: Utc.with_ymd_and_hms(2024, 1, 1, hour + 1, 0, 0).unwrap(),
+            record_count: 1,
+            meter_ids: vec!["meter_001".to_string()],
+            generated_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::Legacy,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
+        }
+    }
+
+    #[test]
+    fn test_entries_sum_proofs_per_window() {
+        // Two proofs for 10:00 (capacity-forced finalize), one for 09:00
+        let proofs = vec![proof(10, 1.5), proof(9, 0.25), proof(10, 2.0)];
+        let entries = quota_entries(&proofs);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].window_id, "2024-01-01T09:00:00Z/2024-01-01T10:00:00Z");
+        assert_eq!(entries[0].quota_amount, 250_000);
+        assert_eq!(entries[1].quota_amount, 3_500_000);
+        assert_eq!(entries[1].proof_ids.len(), 2);
+    }
+
+    #[test]
+    fn test_batches_match_move_arguments_and_verify() {
+        let signer = EnclaveSigner::from_hex(&"42".repeat(32)).unwrap();
+        let entries = quota_entries(&[proof(9, 1.0), proof(10, 2.0), proof(11, 3.0)]);
+        let batches = sign_batches(&signer, entries, 2).unwrap();
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].quota_amounts, vec![1_000_000, 2_000_000]);
+
+        // vector<u64>: ULEB128 length, then little-endian values
+        let amounts = BASE64.decode(&batches[0].quota_amounts_bcs).unwrap();
+        assert_eq!(amounts[0], 2);
+        assert_eq!(&amounts[1..9], &1_000_000u64.to_le_bytes());
+
+        assert!(batches.iter().all(verify_batch));
+
+        let mut tampered = batches[1].clone();
+        tampered.quota_amounts[0] += 1;
+        assert!(!verify_batch(&tampered));
+
+        assert!(sign_batches(&signer, Vec::new(), 0).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Periodic comparison of archived proofs with their on-chain mint and seal state
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::aggregator::DataAggregator;
+use crate::chain::ChainAdapter;
+use crate::crypto;
+use crate::models::ProofData;
+use crate::seal::SealResponse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofChainStatus {
+    /// Minted against `sKWH::mint_from_proof`
+    Minted,
+    /// Registered in the `SealRegistry` but not minted yet
+    SealedOnChain,
+    /// Neither minted nor registered
+    Orphaned,
+}
+
+/// Where the enclave's own records and the chain disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Divergence {
+    /// Local seal receipt, but no `SealRegistry` entry
+    SealNotRegistered,
+    /// `SealRegistry` entry without a successful local seal
+    UnknownSealRegistration,
+    /// Minted without a `SealRegistry` entry
+    MintedWithoutSeal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReconciledProof {
+    pub proof_id: Uuid,
+    pub proof_hash: String,
+    pub window_id: String,
+    pub status: ProofChainStatus,
+    pub seal_object: Option<String>,
+    pub sealed_locally: bool,
+    pub divergences: Vec<Divergence>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReconciliationReport {
+    pub run_at: DateTime<Utc>,
+    pub minted: usize,
+    pub sealed_on_chain: usize,
+    pub orphaned: usize,
+    /// Proofs the chain could not be queried for
+    pub errors: Vec<String>,
+    pub proofs: Vec<ReconciledProof>,
+}
+
+impl ReconciliationReport {
+    /// Proofs with at least one divergence
+    pub fn divergent(&self) -> impl Iterator<Item = &ReconciledProof> {
+        self.proofs.iter().filter(|proof| !proof.divergences.is_empty())
+    }
+}
+
+/// Reconcile one proof against the chain; `receipt` is its local seal receipt
+pub async fn reconcile_proof(chain: &dyn ChainAdapter, proof: &ProofData, receipt: Option<&SealResponse>)
+    -> Result<ReconciledProof, Box<dyn std::error::Error + Send + Sync>> {
+
+    let proof_hash = crypto::proof_hash(proof);
+    let minted = chain.is_proof_used(&proof_hash).await?;
+    let seal_object = chain.seal_registration(&proof.proof_id.to_string()).await?;
+    let sealed_locally = receipt.is_some_and(|receipt| receipt.success);
+
+    let status = match (minted, &seal_object) {
+        (true, _) => ProofChainStatus::Minted,
+        (false, Some(_)) => ProofChainStatus::SealedOnChain,
+        (false, None) => ProofChainStatus::Orphaned,
+    };
+
+    let mut divergences = Vec::new();
+    match (sealed_locally, seal_object.is_some()) {
+        (true, false) => divergences.push(Divergence::SealNotRegistered),
+        (false, true) => divergences.push(Divergence::UnknownSealRegistration),
+        _ => {}
+    }
+    if minted && seal_object.is_none() {
+        divergences.push(Divergence::MintedWithoutSeal);
+    }
+
+    Ok(ReconciledProof {
+        proof_id: proof.proof_id,
+        proof_hash,
+        window_id: proof.window_id(),
+        status,
+        seal_object,
+        sealed_locally,
+        divergences,
+    })
+}
+
+/// Reconcile every archived proof and store the report
+pub async fn run_once(aggregator: &Arc<Mutex<DataAggregator>>, chain: &dyn ChainAdapter)
+    -> Result<ReconciliationReport, Box<dyn std::error::Error + Send + Sync>> {
+
+    // Read local state under the lock, query the chain without it
+    let archived = {
+        let mut aggregator = aggregator.lock().await;
+        let mut archived = Vec::new();
+        for proof in aggregator.list_proofs().await?.iter() {
+            let receipt = aggregator.get_seal_receipt(&proof.proof_id).await?;
+            archived.push((proof.clone(), receipt));
+        }
+        archived
+    };
+
+    let mut report = ReconciliationReport {
+        run_at: Utc::now(),
+        minted: 0,
+        sealed_on_chain: 0,
+        orphaned: 0,
+        errors: Vec::new(),
+        proofs: Vec::new(),
+    };
+
+    for (proof, receipt) in &archived {
+        match reconcile_proof(chain, proof, receipt.as_ref()).await {
+            Ok(reconciled) => {
+                match reconciled.status {
+                    ProofChainStatus::Minted => report.minted += 1,
+                    ProofChainStatus::SealedOnChain => report.sealed_on_chain += 1,
+                    ProofChainStatus::Orphaned => report.orphaned += 1,
+                }
+                report.proofs.push(reconciled);
+            }
+            Err(e) => report.errors.push(format!("{}: {}", proof.proof_id, e)),
+        }
+    }
+
+    aggregator.lock().await.save_reconciliation_report(&report).await?;
+    Ok(report)
+}
+
+/// Run reconciliation every `interval` in the background
+pub fn spawn(aggregator: Arc<Mutex<DataAggregator>>, chain: Arc<dyn ChainAdapter>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match run_once(&aggregator, chain.as_ref()).await {
+                Ok(report) => {
+                    let divergent = report.divergent().count();
+                    info!("🔁 Reconciled {} proofs: {} minted, {} sealed on-chain, {} orphaned",
+                          report.proofs.len(), report.minted, report.sealed_on_chain, report.orphaned);
+                    if divergent > 0 || !report.errors.is_empty() {
+                        warn!("⚠️ {} proofs diverge from chain state, {} could not be checked",
+                              divergent, report.errors.len());
+                    }
+                }
+                Err(e) => error!("Reconciliation failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HashScheme;
+    use crate::chain::LocalChain;
+    use chrono::TimeZone;
+
+    fn proof() -> ProofData {
+        ProofData {
+            proof_id: Uuid::new_v4(),
+            aggregate_kwh: 1.5,
+            merkle_root: "ab".repeat(32),
+            window_start: Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap(),
+            window_end: Utc.with_ymd_and_hms(2024, 1, 1, 11, 0, 0).unwrap(),
+            record_count: 1,
+            meter_ids: vec!["meter_001".to_string()],
+            generated_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::Legacy,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
+        }
+    }
+
+    fn receipt() -> SealResponse {
+        serde_json::from_value(serde_json::json!({
+            "success": true,
+            "blob_id": "sealed_blob",
+            "walrus_url": null,
+            "tx_digest": null,
+            "cost": null,
+            "sui_object_id": null,
+            "end_epoch": null,
+            "already_certified": false,
+            "content_blob_id": null,
+            "records_manifest_blob_id": null,
+            "seal_hash": null,
+            "seal_signature": null,
+            "error": null
+        })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_statuses_and_divergences() {
+        let chain = LocalChain::default();
+        let (orphan, sealed, minted, unsealed_mint) = (proof(), proof(), proof(), proof());
+
+        chain.register_seal(&sealed.proof_id.to_string(), "0x5ea1").await;
+        chain.register_seal(&minted.proof_id.to_string(), "0x5ea2").await;
+        chain.mark_proof_used(&crypto::proof_hash(&minted)).await;
+        chain.mark_proof_used(&crypto::proof_hash(&unsealed_mint)).await;
+
+        let result = reconcile_proof(&chain, &orphan, Some(&receipt())).await.unwrap();
+        assert_eq!(result.status, ProofChainStatus::Orphaned);
+        assert_eq!(result.divergences, vec![Divergence::SealNotRegistered]);
+
+        let result = reconcile_proof(&chain, &sealed, Some(&receipt())).await.unwrap();
+        assert_eq!(result.status, ProofChainStatus::SealedOnChain);
+        assert!(result.divergences.is_empty());
+
+        let result = reconcile_proof(&chain, &minted, None).await.unwrap();
+        assert_eq!(result.status, ProofChainStatus::Minted);
+        assert_eq!(result.divergences, vec![Divergence::UnknownSealRegistration]);
+
+        let result = reconcile_proof(&chain, &unsealed_mint, None).await.unwrap();
+        assert_eq!(result.status, ProofChainStatus::Minted);
+        assert_eq!(result.divergences, vec![Divergence::MintedWithoutSeal]);
+    }
+}
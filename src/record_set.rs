@@ -0,0 +1,251 @@
+//! Full record sets sealed next to a proof, so a third party can re-derive
+//! `merkle_root` and `aggregate_kwh` from Walrus alone
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::crypto::CryptoService;
+use crate::merkle::MerkleTree;
+use crate::models::{ProofData, VerifiedRecord};
+use crate::registry::MeterRegistry;
+use crate::sum_tree::MerkleSumTree;
+
+/// One blob's worth of records, in Merkle leaf order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordChunk {
+    pub proof_id: Uuid,
+    pub index: usize,
+    pub records: Vec<VerifiedRecord>,
+}
+
+/// Pointer from the manifest to a stored chunk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkRef {
+    pub index: usize,
+    pub blob_id: String,
+    pub record_count: usize,
+    /// SHA-256 of the chunk blob bytes
+    pub sha256: String,
+}
+
+/// Blob linking all chunks of a sealed record set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordManifest {
+    pub proof_id: Uuid,
+    pub merkle_root: String,
+    pub aggregate_kwh: f64,
+    pub record_count: usize,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub chunks: Vec<ChunkRef>,
+}
+
+impl RecordManifest {
+    pub fn new(proof: &ProofData, chunks: Vec<ChunkRef>) -> Self {
+        Self {
+            proof_id: proof.proof_id,
+            merkle_root: proof.merkle_root.clone(),
+            aggregate_kwh: proof.aggregate_kwh,
+            record_count: proof.record_count,
+            window_start: proof.window_start,
+            window_end: proof.window_end,
+            chunks,
+        }
+    }
+}
+
+/// Split records into chunks of at most `chunk_size` records, keeping leaf order
+pub fn chunk_records(proof_id: Uuid, records: &[VerifiedRecord], chunk_size: usize) -> Vec<RecordChunk> {
+    records
+        .chunks(chunk_size.max(1))
+        .enumerate()
+        .map(|(index, records)| RecordChunk {
+            proof_id,
+            index,
+            records: records.to_vec(),
+        })
+        .collect()
+}
+
+/// Hex SHA-256 of a chunk blob
+pub fn chunk_digest(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex::encode(hasher.finalize())
+}
+
+/// Re-derive a proof from its full record set: record hashes, meter signatures
+/// (when a meter registry is given), Merkle root, aggregate, count and meter IDs
+pub fn verify_records(proof: &ProofData, records: &[VerifiedRecord], signatures: Option<&MeterRegistry>)
+    -> Result<(), Box<dyn std::error::Error>> {
+
+    if records.len() != proof.record_count {
+        return Err(format!("Record count mismatch: expected {}, got {}", proof.record_count, records.len()).into());
+    }
+
+    let crypto = CryptoService::new();
+    for verified in records {
+        if crypto.hash_record(&verified.record, proof.hash_scheme)? != verified.record_hash {
+            return Err(format!("Record hash mismatch for meter {}", verified.record.meter_id).into());
+        }
+        if let Some(registry) = signatures {
+            let signer = registry.verify(&crypto, &verified.record, &verified.signature, verified.scheme)
+                .map_err(|e| format!("Invalid meter signature for meter {}: {}", verified.record.meter_id, e))?;
+            if verified.signer.as_ref().is_some_and(|expected| *expected != signer) {
+                return Err(format!("Signer mismatch for meter {}", verified.record.meter_id).into());
+            }
+        }
+    }
+
+    let record_hashes = records.iter().map(|r| &r.record_hash);
+    let merkle_tree = MerkleTree::with_scheme(record_hashes, proof.hash_scheme)?;
+    if merkle_tree.root != proof.merkle_root {
+        return Err(format!("Merkle root mismatch: expected {}, got {}", proof.merkle_root, merkle_tree.root).into());
+    }
+
+    if let Some(sum_root) = &proof.sum_root {
+        let sum_tree = MerkleSumTree::from_records(records).map_err(|e| e.to_string())?;
+        if sum_tree.root() != *sum_root {
+            return Err(format!("Sum root mismatch: expected {}, got {}", sum_root.hash, sum_tree.root().hash).into());
+        }
+    }
+
+    // Summed in leaf order, exactly as the aggregator does
+    let aggregate_kwh: f64 = records.iter().map(|r| r.record.kwh_delta).sum();
+    if (aggregate_kwh - proof.aggregate_kwh).abs() > 1e-9 {
+        return Err(format!("Aggregate mismatch: expected {}, got {}", proof.aggregate_kwh, aggregate_kwh).into());
+    }
+
+    let mut meter_ids: Vec<String> = records.iter().map(|r| r.record.meter_id.clone()).collect();
+    meter_ids.sort();
+    meter_ids.dedup();
+    if meter_ids != proof.meter_ids {
+        return Err("Meter ID set mismatch".into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{HashScheme, MeterRecord, RecordEncoding, SignatureScheme};
+
+    fn verified(meter_id: &str, kwh_delta: f64) -> VerifiedRecord {
+        verified_with(meter_id, kwh_delta, HashScheme::Legacy)
+    }
+
+    fn verified_with(meter_id: &str, kwh_delta: f64, hash_scheme: HashScheme) -> VerifiedRecord {
+        let record = MeterRecord {
+            meter_id: meter_id.to_string(),
+            timestamp: 1640995200000,
+            kwh_delta,
+            nonce: format!("{:0>32}", meter_id.len()),
+            encoding: RecordEncoding::V1,
+        };
+        let record_hash = CryptoService::new().hash_record(&record, hash_scheme).unwrap();
+
+        VerifiedRecord {
+            record,
+            signature: "0x00".to_string(),
+            scheme: SignatureScheme::Legacy,
+            signer: None,
+            verification_timestamp: Utc::now(),
+            record_hash,
+        }
+    }
+
+    fn proof_for(records: &[VerifiedRecord], hash_scheme: HashScheme) -> ProofData {
+        let hashes = records.iter().map(|r| &r.record_hash);
+        let mut meter_ids: Vec<String> = records.iter().map(|r| r.record.meter_id.clone()).collect();
+        meter_ids.sort();
+        meter_ids.dedup();
+
+        ProofData {
+            proof_id: Uuid::new_v4(),
+            aggregate_kwh: records.iter().map(|r| r.record.kwh_delta).sum(),
+            merkle_root: MerkleTree::with_scheme(hashes, hash_scheme).unwrap().root,
+            window_start: Utc::now(),
+            window_end: Utc::now(),
+            record_count: records.len(),
+            meter_ids,
+            generated_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            hash_scheme,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
+        }
+    }
+
+    #[test]
+    fn test_chunking_preserves_order() {
+        let records: Vec<_> = (0..5).map(|i| verified(&format!("meter_{}", i), 1.0)).collect();
+        let chunks = chunk_records(Uuid::new_v4(), &records, 2);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].index, 2);
+        assert_eq!(chunks[2].records.len(), 1);
+
+        let rejoined: Vec<_> = chunks.into_iter().flat_map(|c| c.records).collect();
+        assert_eq!(rejoined[3].record.meter_id, "meter_3");
+    }
+
+    #[test]
+    fn test_verify_records_rederives_proof() {
+        let records = vec![verified("meter_a", 1.25), verified("meter_b", 2.5), verified("meter_c", 0.75)];
+        let proof = proof_for(&records, HashScheme::Legacy);
+
+        assert!(verify_records(&proof, &records, None).is_ok());
+
+        let mut tampered = records.clone();
+        tampered[1].record.kwh_delta = 25.0;
+        assert!(verify_records(&proof, &tampered, None).is_err());
+
+        assert!(verify_records(&proof, &records[..2], None).is_err());
+        assert!(verify_records(&proof, &records, Some(&MeterRegistry::default())).is_err()); // placeholder signatures
+    }
+
+    #[test]
+    fn test_verify_records_per_hash_scheme() {
+        let legacy = vec![verified("meter_a", 1.25), verified("meter_b", 2.5)];
+        let tagged = vec![
+            verified_with("meter_a", 1.25, HashScheme::V1),
+            verified_with("meter_b", 2.5, HashScheme::V1),
+        ];
+        let legacy_proof = proof_for(&legacy, HashScheme::Legacy);
+        let tagged_proof = proof_for(&tagged, HashScheme::V1);
+
+        // Proofs are re-derived with the scheme they were generated under
+        assert_ne!(legacy_proof.merkle_root, tagged_proof.merkle_root);
+        assert!(verify_records(&legacy_proof, &legacy, None).is_ok());
+        assert!(verify_records(&tagged_proof, &tagged, None).is_ok());
+        assert!(verify_records(&tagged_proof, &legacy, None).is_err());
+
+        // Legacy proofs round-trip without the new field
+        let json = serde_json::to_value(&legacy_proof).unwrap();
+        assert!(json.get("hash_scheme").is_none());
+        let json = serde_json::to_value(&tagged_proof).unwrap();
+        assert_eq!(json["hash_scheme"], "greenshare.merkle.v1");
+        assert_eq!(serde_json::from_value::<ProofData>(json).unwrap().hash_scheme, HashScheme::V1);
+    }
+
+    #[test]
+    fn test_verify_records_checks_sum_root() {
+        let records = vec![
+            verified_with("meter_a", 1.25, HashScheme::V2),
+            verified_with("meter_b", 2.5, HashScheme::V2),
+            verified_with("meter_c", 0.75, HashScheme::V2),
+        ];
+        let mut proof = proof_for(&records, HashScheme::V2);
+        proof.sum_root = Some(MerkleSumTree::from_records(&records).unwrap().root());
+        assert!(verify_records(&proof, &records, None).is_ok());
+        assert_eq!(proof.aggregate_micro_kwh(), 4_500_000);
+
+        // A total the leaves do not add up to is caught
+        proof.sum_root.as_mut().unwrap().total_micro_kwh += 1;
+        assert!(verify_records(&proof, &records, None).is_err());
+    }
+}
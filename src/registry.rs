@@ -0,0 +1,764 @@
+//! Registered meters, the signature scheme each one uses and the history of its keys
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::attestation::{CertificateError, DeviceCertificate, TrustStore};
+use crate::canonical;
+use crate::crypto::CryptoService;
+use crate::models::{MeterRecord, SignatureScheme};
+use crate::schemes::{self, KeyScheme, Secp256k1RecoverableVerifier, SignatureVerifier};
+use crate::signer::SignatureError;
+
+type RegistryResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Version tag embedded in every canonical key rotation message
+pub const KEY_ROTATION_ENCODING: &str = "greenshare.key-rotation.v1";
+
+/// Entry of the `METER_REGISTRY_FILE` JSON array
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredMeter {
+    pub meter_id: String,
+    #[serde(default)]
+    pub scheme: KeyScheme,
+    /// Hex public key; SEC1 for secp256k1/P-256, 32 raw bytes for Ed25519.
+    /// For recoverable meters, optionally the expected Ethereum address.
+    #[serde(default)]
+    pub public_key: Option<String>,
+}
+
+/// Handover from a meter's current key to a new one, signed by the current key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct KeyRotation {
+    pub meter_id: String,
+    pub new_scheme: KeyScheme,
+    /// New key in the `public_key` format of [`RegisteredMeter`]; the address for recoverable keys
+    pub new_public_key: String,
+    /// Record timestamp (ms) from which only the new key is accepted. Once the enclave clock
+    /// passes it, readings signed by the old key are refused whatever their timestamp.
+    pub effective_at: i64,
+    pub nonce: String,
+    /// Old key's signature over [`KeyRotation::message`], made like a legacy record signature
+    pub signature: String,
+}
+
+impl KeyRotation {
+    /// RFC 8785 (JCS) encoding of every field but the signature
+    pub fn message(&self) -> String {
+        canonical::jcs(&serde_json::json!({
+            "encoding": KEY_ROTATION_ENCODING,
+            "meter_id": self.meter_id,
+            "new_scheme": self.new_scheme,
+            "new_public_key": self.new_public_key,
+            "effective_at": self.effective_at,
+            "nonce": self.nonce,
+        }))
+    }
+}
+
+/// One key of a meter and the readings it signs for
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct KeyEpoch {
+    pub scheme: KeyScheme,
+    /// Registered public key, or the address of a recoverable key (unset until its first reading)
+    pub public_key: Option<String>,
+    /// Record timestamp (ms) from which this key signs; unset for a meter's first key
+    pub effective_from: Option<i64>,
+    /// Handover that introduced this key
+    pub rotation: Option<KeyRotation>,
+    /// Trust anchor certificate vouching for this key, checked on every reading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<DeviceCertificate>,
+}
+
+impl KeyEpoch {
+    /// First key of a meter that is bound on first use
+    fn unbound() -> Self {
+        Self {
+            scheme: KeyScheme::Secp256k1Recoverable,
+            public_key: None,
+            effective_from: None,
+            rotation: None,
+            certificate: None,
+        }
+    }
+
+    fn signs_at(&self, timestamp: i64) -> bool {
+        self.effective_from.is_none_or(|from| from <= timestamp)
+    }
+}
+
+/// Certificate log entry: the certificate of one key in a meter's history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CertifiedKey {
+    key_index: usize,
+    certificate: DeviceCertificate,
+}
+
+/// Why a key rotation was refused
+#[derive(Debug, thiserror::Error)]
+pub enum RotationError {
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Invalid rotation: {0}")]
+    Invalid(String),
+    #[error("Stale rotation: effective_at must be after {0}")]
+    Stale(i64),
+    #[error("Replayed rotation: meter {meter_id} already rotated with nonce {nonce}")]
+    Replayed { meter_id: String, nonce: String },
+    #[error("Meter {0} has no bound key to hand over from")]
+    Unbound(String),
+    #[error("Cannot store key rotation: {0}")]
+    Storage(String),
+}
+
+struct MeterKey {
+    epoch: KeyEpoch,
+    verifier: Box<dyn SignatureVerifier>,
+    /// Bound to the address that signed the meter's first reading rather than configured
+    first_use: bool,
+}
+
+impl MeterKey {
+    fn new(epoch: KeyEpoch) -> RegistryResult<Self> {
+        let verifier = schemes::verifier_for(epoch.scheme, epoch.public_key.as_deref())?;
+        Ok(Self { epoch, verifier, first_use: false })
+    }
+
+    fn unbound() -> Self {
+        Self { epoch: KeyEpoch::unbound(), verifier: Box::new(Secp256k1RecoverableVerifier), first_use: false }
+    }
+
+    /// A recoverable key signs only once its identity matches the bound address
+    fn check(&self, identity: String) -> RegistryResult<String> {
+        match &self.epoch.public_key {
+            Some(expected) if self.epoch.scheme == KeyScheme::Secp256k1Recoverable
+                && !expected.eq_ignore_ascii_case(&identity) => {
+                Err(format!("key is {}, signed by {}", expected, identity).into())
+            }
+            _ => Ok(identity),
+        }
+    }
+
+    /// Bind an unbound recoverable key to the first identity that signed with it
+    fn bind(&mut self, identity: String) -> RegistryResult<String> {
+        if self.epoch.public_key.is_none() {
+            self.epoch.public_key = Some(identity.clone());
+            self.first_use = true;
+        }
+        self.check(identity)
+    }
+}
+
+/// Keys of one meter, oldest first
+struct MeterKeys {
+    keys: Vec<MeterKey>,
+}
+
+impl MeterKeys {
+    fn index_at(&self, timestamp: i64) -> usize {
+        self.keys.iter().rposition(|key| key.epoch.signs_at(timestamp)).unwrap_or(0)
+    }
+
+    fn history(&self) -> Vec<KeyEpoch> {
+        self.keys.iter().map(|key| key.epoch.clone()).collect()
+    }
+}
+
+/// Meter ID -> key history. Meters missing from the registry file are bound to the
+/// secp256k1 address that signs their first accepted reading, unless only registered
+/// meters are accepted.
+#[derive(Default)]
+pub struct MeterRegistry {
+    meters: RwLock<HashMap<String, MeterKeys>>,
+    /// Reject readings of meters that are not in the registry
+    registered_only: bool,
+    /// Where first-use bindings are stored, replayed on startup
+    binding_log: Option<PathBuf>,
+    /// Where enrolled certificates are stored, replayed on startup
+    certificate_log: Option<PathBuf>,
+    /// Replayed certificates of keys that only exist once the rotation log is replayed
+    unattached: Vec<CertifiedKey>,
+    /// Where accepted rotations are stored, replayed on startup
+    rotation_log: Option<PathBuf>,
+    /// Anchors whose device certificates stand in for registration
+    trust: TrustStore,
+}
+
+impl MeterRegistry {
+    /// Load a JSON array of [`RegisteredMeter`]
+    pub fn from_file(path: &str) -> RegistryResult<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read meter registry {}: {}", path, e))?;
+        let meters: Vec<RegisteredMeter> = serde_json::from_str(&content)?;
+
+        let mut registry = Self::default();
+        for meter in meters {
+            registry.register(meter)?;
+        }
+        Ok(registry)
+    }
+
+    /// Replay the first-use bindings stored at `path` (if any) and store new ones there.
+    /// Load before the rotation log, whose handovers start from these keys.
+    pub fn with_binding_log(mut self, path: impl AsRef<Path>) -> RegistryResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let bindings: BTreeMap<String, String> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let meters = self.meters.get_mut().unwrap();
+        for (meter_id, address) in bindings {
+            let meter = meters.entry(meter_id.clone()).or_insert_with(|| MeterKeys { keys: vec![MeterKey::unbound()] });
+            meter.keys[0].bind(address)
+                .map_err(|e| format!("Cannot replay {}: meter {} {}", path.display(), meter_id, e))?;
+        }
+        self.binding_log = Some(path);
+        Ok(self)
+    }
+
+    /// Replay the certificates stored at `path` (if any) and store enrolled ones there.
+    /// Load after the trust store and binding log, and before the rotation log: enrolled
+    /// keys are where handovers start, and certificates of rotated keys are attached once
+    /// the rotations are replayed. Revocation and validity are checked per reading.
+    pub fn with_certificate_log(mut self, path: impl AsRef<Path>) -> RegistryResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let certified: Vec<CertifiedKey> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let meters = self.meters.get_mut().unwrap();
+        for entry in certified {
+            let certificate = &entry.certificate;
+            let replay_error = |e: &dyn std::fmt::Display| {
+                format!("Cannot replay {}: meter {} {}", path.display(), certificate.meter_id, e)
+            };
+            self.trust.verify_signature(certificate).map_err(|e| replay_error(&e))?;
+            if entry.key_index > 0 {
+                self.unattached.push(entry);
+                continue;
+            }
+
+            let meter = meters.entry(certificate.meter_id.clone()).or_insert_with(|| MeterKeys { keys: Vec::new() });
+            match meter.keys.first_mut() {
+                Some(key) if key.epoch.public_key.as_deref()
+                    .is_some_and(|public_key| certificate.certifies(key.epoch.scheme, public_key)) => {
+                    key.epoch.certificate = Some(entry.certificate);
+                }
+                Some(key) if key.epoch.public_key.is_some() && !key.first_use => {
+                    return Err(replay_error(&CertificateError::KeyMismatch(certificate.meter_id.clone())).into());
+                }
+                first => {
+                    let key = MeterKey::new(KeyEpoch {
+                        scheme: certificate.scheme,
+                        public_key: Some(certificate.public_key.clone()),
+                        certificate: Some(certificate.clone()),
+                        ..KeyEpoch::unbound()
+                    }).map_err(|e| replay_error(&e))?;
+                    match first {
+                        Some(first) => *first = key,
+                        None => meter.keys.push(key),
+                    }
+                }
+            }
+        }
+        self.certificate_log = Some(path);
+        Ok(self)
+    }
+
+    /// Replay the rotations stored at `path` (if any) and store accepted ones there
+    pub fn with_rotation_log(mut self, path: impl AsRef<Path>) -> RegistryResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let rotations: Vec<KeyRotation> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        for rotation in rotations {
+            self.rotate(rotation).map_err(|e| format!("Cannot replay {}: {}", path.display(), e))?;
+        }
+
+        let meters = self.meters.get_mut().unwrap();
+        for entry in std::mem::take(&mut self.unattached) {
+            let certificate = entry.certificate;
+            let key = meters.get_mut(&certificate.meter_id)
+                .and_then(|meter| meter.keys.get_mut(entry.key_index))
+                .filter(|key| certificate.certifies(key.epoch.scheme, key.epoch.public_key.as_deref().unwrap_or_default()))
+                .ok_or_else(|| format!("Cannot replay certificate {}: meter {} has no key {} it certifies",
+                    certificate.serial, certificate.meter_id, entry.key_index))?;
+            key.epoch.certificate = Some(certificate);
+        }
+        self.rotation_log = Some(path);
+        Ok(self)
+    }
+
+    /// Only accept meters that are registered, replayed from the binding log, or enrolled
+    pub fn registered_only(mut self, registered_only: bool) -> Self {
+        self.registered_only = registered_only;
+        self
+    }
+
+    pub fn with_trust_store(mut self, trust: TrustStore) -> Self {
+        self.trust = trust;
+        self
+    }
+
+    pub fn register(&mut self, meter: RegisteredMeter) -> RegistryResult<()> {
+        let key = MeterKey::new(KeyEpoch {
+            scheme: meter.scheme,
+            public_key: meter.public_key,
+            ..KeyEpoch::unbound()
+        }).map_err(|e| format!("Meter {}: {}", meter.meter_id, e))?;
+
+        self.meters.get_mut().unwrap().insert(meter.meter_id, MeterKeys { keys: vec![key] });
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.meters.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Key history of a meter, oldest first
+    pub fn history(&self, meter_id: &str) -> Option<Vec<KeyEpoch>> {
+        self.meters.read().unwrap().get(meter_id).map(MeterKeys::history)
+    }
+
+    /// Verify a record's signature with the key its meter had at the record's timestamp
+    /// and return the signer identity
+    pub fn verify(&self, crypto: &CryptoService, record: &MeterRecord, signature_hex: &str, encoding: SignatureScheme)
+        -> RegistryResult<String> {
+        self.verify_at(crypto, record, signature_hex, encoding, None)
+    }
+
+    /// [`Self::verify`], and when the record is ingested at `now` (ms), refuse a key that a
+    /// rotation effective by then has superseded or whose certificate is no longer valid. The
+    /// meter picks the record timestamp, so the old key could otherwise keep signing by
+    /// backdating its readings.
+    fn verify_at(&self, crypto: &CryptoService, record: &MeterRecord, signature_hex: &str, encoding: SignatureScheme, now: Option<i64>)
+        -> RegistryResult<String> {
+        let signature = decode_signature(signature_hex)?;
+        match self.meters.read().unwrap().get(&record.meter_id) {
+            Some(meter) => {
+                let index = meter.index_at(record.timestamp);
+                let key = &meter.keys[index];
+                if let Some(now) = now {
+                    if meter.index_at(now) > index {
+                        let successor = meter.keys[index + 1].epoch.effective_from.unwrap_or_default();
+                        return Err(format!("key of meter {} was superseded at {}", record.meter_id, successor).into());
+                    }
+                }
+                if let Some(certificate) = &key.epoch.certificate {
+                    self.trust.check_valid_at(certificate, record.timestamp)?;
+                    // Nor can an expired certificate keep vouching by backdating
+                    if let Some(now) = now {
+                        self.trust.check_valid_at(certificate, now)?;
+                    }
+                }
+                key.check(key.verifier.verify(crypto, record, &signature, encoding)?)
+            }
+            None if self.registered_only => Err(format!("meter {} is not registered", record.meter_id).into()),
+            None => Secp256k1RecoverableVerifier.verify(crypto, record, &signature, encoding),
+        }
+    }
+
+    /// [`Self::verify`] for ingest, binding an unbound meter key to the address that signed
+    pub fn verify_and_bind(&self, crypto: &CryptoService, record: &MeterRecord, signature_hex: &str, encoding: SignatureScheme)
+        -> RegistryResult<String> {
+        self.verify_and_bind_at(crypto, record, signature_hex, encoding, Utc::now().timestamp_millis())
+    }
+
+    /// [`Self::verify_and_bind`] for a record ingested at `now` (ms)
+    pub fn verify_and_bind_at(&self, crypto: &CryptoService, record: &MeterRecord, signature_hex: &str, encoding: SignatureScheme, now: i64)
+        -> RegistryResult<String> {
+        let identity = self.verify_at(crypto, record, signature_hex, encoding, Some(now))?;
+
+        let bound = self.meters.read().unwrap()
+            .get(&record.meter_id)
+            .is_some_and(|meter| meter.keys[meter.index_at(record.timestamp)].epoch.public_key.is_some());
+        if bound {
+            return Ok(identity);
+        }
+
+        // Under the write lock a concurrent reading may have bound the key first
+        let mut meters = self.meters.write().unwrap();
+        let unbound = meters.get(&record.meter_id)
+            .is_none_or(|meter| meter.keys[meter.index_at(record.timestamp)].epoch.public_key.is_none());
+        if unbound {
+            // Store the binding before making it, so a restart cannot hand the meter to another key
+            let mut bindings = first_use_bindings(&meters);
+            bindings.insert(&record.meter_id, &identity);
+            self.store_bindings(&bindings)?;
+        }
+        let meter = meters.entry(record.meter_id.clone()).or_insert_with(|| MeterKeys { keys: vec![MeterKey::unbound()] });
+        let index = meter.index_at(record.timestamp);
+        meter.keys[index].bind(identity)
+            .map_err(|e| format!("meter {} {}", record.meter_id, e).into())
+    }
+
+    /// Write `bindings` to the binding log
+    fn store_bindings(&self, bindings: &BTreeMap<&str, &str>) -> RegistryResult<()> {
+        let Some(path) = &self.binding_log else {
+            return Ok(());
+        };
+        std::fs::write(path, serde_json::to_string_pretty(bindings)?)
+            .map_err(|e| format!("Cannot store meter binding: {}", e).into())
+    }
+
+    /// Hand a meter over to a new key. The rotation must be signed by the meter's latest key,
+    /// which must already be bound, take effect after it and carry a nonce the meter has not
+    /// rotated with before. The rotation is stored before the
+    /// handover is made.
+    pub fn rotate(&self, rotation: KeyRotation) -> Result<Vec<KeyEpoch>, RotationError> {
+        let signature = decode_signature(&rotation.signature)
+            .map_err(|e| RotationError::InvalidSignature(e.to_string()))?;
+        let new_key = MeterKey::new(KeyEpoch {
+            scheme: rotation.new_scheme,
+            public_key: Some(rotation.new_public_key.clone()),
+            effective_from: Some(rotation.effective_at),
+            rotation: Some(rotation.clone()),
+            certificate: None,
+        }).map_err(|e| RotationError::Invalid(e.to_string()))?;
+
+        let mut meters = self.meters.write().unwrap();
+        let meter = meters.get(&rotation.meter_id);
+        let current = meter
+            .map(|meter| meter.keys.last().unwrap())
+            .filter(|current| current.epoch.public_key.is_some())
+            .ok_or_else(|| RotationError::Unbound(rotation.meter_id.clone()))?;
+        // A meter that returns to an earlier key must not be handed over by a captured rotation
+        let replayed = meter.into_iter()
+            .flat_map(|meter| meter.keys.iter().filter_map(|key| key.epoch.rotation.as_ref()))
+            .any(|earlier| earlier.nonce == rotation.nonce);
+        if replayed {
+            return Err(RotationError::Replayed { meter_id: rotation.meter_id, nonce: rotation.nonce });
+        }
+        if let Some(from) = current.epoch.effective_from {
+            if rotation.effective_at <= from {
+                return Err(RotationError::Stale(from));
+            }
+        }
+        current.verifier
+            .verify_message(rotation.message().as_bytes(), &signature)
+            .and_then(|identity| current.check(identity))
+            .map_err(|e| RotationError::InvalidSignature(e.to_string()))?;
+
+        if let Some(path) = &self.rotation_log {
+            let rotations: Vec<&KeyRotation> = meters.values()
+                .flat_map(|meter| meter.keys.iter().filter_map(|key| key.epoch.rotation.as_ref()))
+                .chain([&rotation])
+                .collect();
+            let content = serde_json::to_string_pretty(&rotations)
+                .map_err(|e| RotationError::Storage(e.to_string()))?;
+            std::fs::write(path, content).map_err(|e| RotationError::Storage(e.to_string()))?;
+        }
+
+        let meter = meters.get_mut(&rotation.meter_id).unwrap();
+        meter.keys.push(new_key);
+        Ok(meter.history())
+    }
+
+    /// Accept a meter key on the strength of a trust anchor's certificate. It becomes the key of
+    /// an unknown or unbound meter, replaces a key the meter was only bound to on first use, and
+    /// renews the certificate of a meter already on that key.
+    pub fn enroll(&self, meter_id: &str, certificate: DeviceCertificate) -> Result<Vec<KeyEpoch>, CertificateError> {
+        self.enroll_at(meter_id, certificate, Utc::now().timestamp_millis())
+    }
+
+    /// [`Self::enroll`] at `now` (ms), at which the certificate must be valid. The enrollment
+    /// is stored before it is made.
+    pub fn enroll_at(&self, meter_id: &str, certificate: DeviceCertificate, now: i64) -> Result<Vec<KeyEpoch>, CertificateError> {
+        if certificate.meter_id != meter_id {
+            return Err(CertificateError::WrongMeter(certificate.meter_id));
+        }
+        self.trust.check_valid_at(&certificate, now)?;
+        // Readings may carry their certificate every time; skip the ones already verified
+        if let Some(meter) = self.meters.read().unwrap().get(meter_id) {
+            if meter.keys.last().unwrap().epoch.certificate.as_ref() == Some(&certificate) {
+                return Ok(meter.history());
+            }
+        }
+
+        self.trust.verify(&certificate)?;
+        let new_key = MeterKey::new(KeyEpoch {
+            scheme: certificate.scheme,
+            public_key: Some(certificate.public_key.clone()),
+            certificate: Some(certificate.clone()),
+            ..KeyEpoch::unbound()
+        }).map_err(|e| CertificateError::Invalid(e.to_string()))?;
+
+        let mut meters = self.meters.write().unwrap();
+        let current = meters.get(meter_id)
+            .and_then(|meter| meter.keys.last().map(|key| (meter.keys.len() - 1, key)));
+        let (key_index, renew, replaces_binding) = match current {
+            None => (0, false, false),
+            Some((index, current)) if current.epoch.public_key.is_none() => (index, false, false),
+            Some((index, current)) => {
+                let certified = certificate.certifies(current.epoch.scheme, current.epoch.public_key.as_deref().unwrap_or_default());
+                if !certified && !current.first_use {
+                    return Err(CertificateError::KeyMismatch(meter_id.to_string()));
+                }
+                (index, certified, !certified)
+            }
+        };
+
+        self.store_certificates(&meters, &CertifiedKey { key_index, certificate: certificate.clone() })?;
+        if replaces_binding {
+            // Drop the uncertified binding from the log before replacing it
+            let mut bindings = first_use_bindings(&meters);
+            bindings.remove(meter_id);
+            self.store_bindings(&bindings).map_err(|e| CertificateError::Storage(e.to_string()))?;
+        }
+
+        let meter = meters.entry(meter_id.to_string()).or_insert_with(|| MeterKeys { keys: Vec::new() });
+        match meter.keys.get_mut(key_index) {
+            None => meter.keys.push(new_key),
+            Some(current) if renew => current.epoch.certificate = Some(certificate),
+            Some(current) => *current = new_key,
+        }
+        Ok(meter.history())
+    }
+
+    /// Write the certificate log with `enrolled` in place of the certificate of its key
+    fn store_certificates(&self, meters: &HashMap<String, MeterKeys>, enrolled: &CertifiedKey) -> Result<(), CertificateError> {
+        let Some(path) = &self.certificate_log else {
+            return Ok(());
+        };
+        let mut certified: Vec<CertifiedKey> = meters.values()
+            .flat_map(|meter| meter.keys.iter().enumerate().filter_map(|(key_index, key)| {
+                key.epoch.certificate.clone().map(|certificate| CertifiedKey { key_index, certificate })
+            }))
+            .filter(|entry| entry.certificate.meter_id != enrolled.certificate.meter_id || entry.key_index != enrolled.key_index)
+            .collect();
+        certified.push(enrolled.clone());
+        let content = serde_json::to_string_pretty(&certified)
+            .map_err(|e| CertificateError::Storage(e.to_string()))?;
+        std::fs::write(path, content).map_err(|e| CertificateError::Storage(e.to_string()))
+    }
+}
+
+/// Meter ID -> address of every key bound on first use
+fn first_use_bindings(meters: &HashMap<String, MeterKeys>) -> BTreeMap<&str, &str> {
+    meters.iter()
+        .filter_map(|(meter_id, meter)| {
+            let key = &meter.keys[0];
+            key.first_use.then(|| (meter_id.as_str(), key.epoch.public_key.as_deref().unwrap_or_default()))
+        })
+        .collect()
+}
+
+fn decode_signature(signature_hex: &str) -> Result<Vec<u8>, SignatureError> {
+    hex::decode(signature_hex.strip_prefix("0x").unwrap_or(signature_hex))
+        .map_err(|_| SignatureError::InvalidHex)
+}
+
+impl std::fmt::Debug for MeterRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let meters: HashMap<String, Vec<KeyEpoch>> = self.meters.read().unwrap()
+            .iter()
+            .map(|(meter_id, meter)| (meter_id.clone(), meter.history()))
+            .collect();
+        f.debug_struct("MeterRegistry")
+            .field("meters", &meters)
+            .field("registered_only", &self.registered_only)
+            .field("binding_log", &self.binding_log)
+            .field("certificate_log", &self.certificate_log)
+            .field("rotation_log", &self.rotation_log)
+            .field("trust_anchors", &self.trust.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RecordEncoding;
+    use crate::signer::EnclaveSigner;
+    use sha2::{Digest, Sha256};
+
+    fn record(meter_id: &str) -> MeterRecord {
+        MeterRecord {
+            meter_id: meter_id.to_string(),
+            timestamp: 1640995200000,
+            kwh_delta: 0.5,
+            nonce: "nonce".to_string(),
+            encoding: RecordEncoding::V1,
+        }
+    }
+
+    #[test]
+    fn test_dispatches_on_registered_scheme() {
+        let crypto = CryptoService::new();
+        let ed_key = ed25519_dalek::SigningKey::from_bytes(&[3u8; 32]);
+        let eth_key = EnclaveSigner::from_hex(&"42".repeat(32)).unwrap();
+
+        let mut registry = MeterRegistry::default();
+        registry.register(RegisteredMeter {
+            meter_id: "meter_ed".to_string(),
+            scheme: KeyScheme::Ed25519,
+            public_key: Some(hex::encode(ed_key.verifying_key().as_bytes())),
+        }).unwrap();
+        registry.register(RegisteredMeter {
+            meter_id: "meter_eth".to_string(),
+            scheme: KeyScheme::Secp256k1Recoverable,
+            public_key: Some(eth_key.address()),
+        }).unwrap();
+
+        let message = crypto.create_message(&record("meter_ed"));
+        let ed_sig = hex::encode(ed25519_dalek::Signer::sign(&ed_key, message.as_bytes()).to_bytes());
+        assert!(registry.verify(&crypto, &record("meter_ed"), &ed_sig, SignatureScheme::Legacy).is_ok());
+
+        // Unregistered meters are recovered, registered ones must match their address
+        let digest = crypto.meter_signing_digest(&record("meter_eth"), SignatureScheme::Eip712).unwrap();
+        let eth_sig = hex::encode(eth_key.sign_digest(&digest));
+        assert_eq!(registry.verify(&crypto, &record("meter_eth"), &eth_sig, SignatureScheme::Eip712).unwrap(), eth_key.address());
+
+        let other = EnclaveSigner::from_hex(&"43".repeat(32)).unwrap();
+        let digest = crypto.meter_signing_digest(&record("meter_eth"), SignatureScheme::Eip712).unwrap();
+        let other_sig = hex::encode(other.sign_digest(&digest));
+        assert!(registry.verify(&crypto, &record("meter_eth"), &other_sig, SignatureScheme::Eip712).is_err());
+
+        let digest = crypto.meter_signing_digest(&record("meter_new"), SignatureScheme::Eip712).unwrap();
+        let new_sig = hex::encode(other.sign_digest(&digest));
+        assert_eq!(registry.verify(&crypto, &record("meter_new"), &new_sig, SignatureScheme::Eip712).unwrap(), other.address());
+
+        assert!(registry.verify(&crypto, &record("meter_ed"), "0xzz", SignatureScheme::Legacy).is_err());
+
+        // Unless only registered meters are accepted
+        let registry = registry.registered_only(true);
+        assert!(registry.verify(&crypto, &record("meter_new"), &new_sig, SignatureScheme::Eip712).is_err());
+        assert!(registry.verify_and_bind(&crypto, &record("meter_new"), &new_sig, SignatureScheme::Eip712).is_err());
+        assert!(registry.history("meter_new").is_none());
+        assert!(registry.verify(&crypto, &record("meter_eth"), &eth_sig, SignatureScheme::Eip712).is_ok());
+    }
+
+    #[test]
+    fn test_first_use_binding_survives_restart() {
+        let crypto = CryptoService::new();
+        let meter = EnclaveSigner::from_hex(&"42".repeat(32)).unwrap();
+        let squatter = EnclaveSigner::from_hex(&"43".repeat(32)).unwrap();
+        let reading = |key: &EnclaveSigner| {
+            let digest = crypto.meter_signing_digest(&record("meter_001"), SignatureScheme::Eip712).unwrap();
+            hex::encode(key.sign_digest(&digest))
+        };
+
+        let log = std::env::temp_dir().join(format!("bindings_{}.json", uuid::Uuid::new_v4()));
+        let registry = MeterRegistry::default().with_binding_log(&log).unwrap();
+        registry.verify_and_bind(&crypto, &record("meter_001"), &reading(&meter), SignatureScheme::Eip712).unwrap();
+
+        let restored = MeterRegistry::default().with_binding_log(&log).unwrap();
+        assert_eq!(restored.history("meter_001").unwrap()[0].public_key, Some(meter.address()));
+        assert!(restored.verify_and_bind(&crypto, &record("meter_001"), &reading(&squatter), SignatureScheme::Eip712).is_err());
+        assert!(restored.verify_and_bind(&crypto, &record("meter_001"), &reading(&meter), SignatureScheme::Eip712).is_ok());
+
+        // A binding that cannot be stored is not made
+        let missing_dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string()).join("bindings.json");
+        let unwritable = MeterRegistry::default().with_binding_log(missing_dir).unwrap();
+        assert!(unwritable.verify_and_bind(&crypto, &record("meter_001"), &reading(&meter), SignatureScheme::Eip712).is_err());
+        assert!(unwritable.history("meter_001").is_none());
+
+        std::fs::remove_file(&log).unwrap();
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let crypto = CryptoService::new();
+        let old_key = EnclaveSigner::from_hex(&"42".repeat(32)).unwrap();
+        let new_key = EnclaveSigner::from_hex(&"43".repeat(32)).unwrap();
+        let reading = |key: &EnclaveSigner, timestamp: i64| {
+            let record = MeterRecord { timestamp, ..record("meter_001") };
+            let digest = crypto.meter_signing_digest(&record, SignatureScheme::Eip712).unwrap();
+            (record, hex::encode(key.sign_digest(&digest)))
+        };
+        let signed = |key: &EnclaveSigner, rotation: &KeyRotation| KeyRotation {
+            signature: hex::encode(key.sign_digest(&Sha256::digest(rotation.message().as_bytes()).into())),
+            ..rotation.clone()
+        };
+
+        let log = std::env::temp_dir().join(format!("rotations_{}.json", uuid::Uuid::new_v4()));
+        let bindings = log.with_extension("bindings.json");
+        let registry = MeterRegistry::default()
+            .with_binding_log(&bindings).unwrap()
+            .with_rotation_log(&log).unwrap();
+
+        let rotation = KeyRotation {
+            meter_id: "meter_001".to_string(),
+            new_scheme: KeyScheme::Secp256k1Recoverable,
+            new_public_key: new_key.address(),
+            effective_at: 2_000,
+            nonce: "rotation-1".to_string(),
+            signature: String::new(),
+        };
+
+        // A meter without a bound key has nothing to hand over from
+        assert!(matches!(registry.rotate(signed(&old_key, &rotation)), Err(RotationError::Unbound(_))));
+        assert!(registry.history("meter_001").is_none());
+
+        // The first reading binds the meter to its key
+        let (record, signature) = reading(&old_key, 1_000);
+        registry.verify_and_bind(&crypto, &record, &signature, SignatureScheme::Eip712).unwrap();
+        let (record, signature) = reading(&new_key, 1_000);
+        assert!(registry.verify_and_bind(&crypto, &record, &signature, SignatureScheme::Eip712).is_err());
+
+        assert!(matches!(registry.rotate(signed(&new_key, &rotation)), Err(RotationError::InvalidSignature(_))));
+        let history = registry.rotate(signed(&old_key, &rotation)).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].public_key, Some(old_key.address()));
+        assert_eq!(history[1].effective_from, Some(2_000));
+
+        // Readings are checked against the key of their timestamp
+        for (key, timestamp, accepted) in [(&old_key, 1_999, true), (&old_key, 2_000, false), (&new_key, 1_999, false), (&new_key, 2_000, true)] {
+            let (record, signature) = reading(key, timestamp);
+            assert_eq!(registry.verify_and_bind_at(&crypto, &record, &signature, SignatureScheme::Eip712, 1_999).is_ok(), accepted);
+        }
+
+        // Once the rotation is in effect, the old key cannot backdate new readings
+        let (record, signature) = reading(&old_key, 1_999);
+        assert!(registry.verify_and_bind_at(&crypto, &record, &signature, SignatureScheme::Eip712, 2_000).is_err());
+        assert!(registry.verify_and_bind(&crypto, &record, &signature, SignatureScheme::Eip712).is_err());
+        assert!(registry.verify(&crypto, &record, &signature, SignatureScheme::Eip712).is_ok());
+        let (record, signature) = reading(&new_key, 2_000);
+        assert!(registry.verify_and_bind(&crypto, &record, &signature, SignatureScheme::Eip712).is_ok());
+
+        // Only the latest key hands over, and only forward in time
+        let stale = KeyRotation { effective_at: 1_500, nonce: "rotation-2".to_string(), ..rotation.clone() };
+        assert!(matches!(registry.rotate(signed(&new_key, &stale)), Err(RotationError::Stale(2_000))));
+        let revoked = KeyRotation { effective_at: 3_000, nonce: "rotation-2".to_string(), ..rotation.clone() };
+        assert!(matches!(registry.rotate(signed(&old_key, &revoked)), Err(RotationError::InvalidSignature(_))));
+        // A nonce is spent once per meter, even by a properly signed rotation
+        let reused = KeyRotation { new_public_key: old_key.address(), effective_at: 3_000, ..rotation.clone() };
+        assert!(matches!(registry.rotate(signed(&new_key, &reused)), Err(RotationError::Replayed { .. })));
+
+        // The history survives a restart
+        let restored = MeterRegistry::default()
+            .with_binding_log(&bindings).unwrap()
+            .with_rotation_log(&log).unwrap();
+        assert_eq!(restored.history("meter_001").unwrap().len(), 2);
+        let (record, signature) = reading(&old_key, 1_500);
+        assert!(restored.verify(&crypto, &record, &signature, SignatureScheme::Eip712).is_ok());
+        let (record, signature) = reading(&old_key, 2_500);
+        assert!(restored.verify(&crypto, &record, &signature, SignatureScheme::Eip712).is_err());
+
+        // A rotation that cannot be stored is not made
+        std::fs::remove_file(&log).unwrap();
+        std::fs::create_dir(&log).unwrap();
+        let next = KeyRotation { effective_at: 3_000, nonce: "rotation-3".to_string(), ..rotation };
+        assert!(matches!(restored.rotate(signed(&new_key, &next)), Err(RotationError::Storage(_))));
+        assert_eq!(restored.history("meter_001").unwrap().len(), 2);
+
+        std::fs::remove_dir(&log).unwrap();
+        std::fs::remove_file(&bindings).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+//! Key algorithms meters sign with, behind one verification trait
+
+use ed25519_dalek::Verifier as _;
+use p256::ecdsa::signature::hazmat::PrehashVerifier as _;
+use secp256k1::{PublicKey, Secp256k1, ecdsa::Signature};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::crypto::CryptoService;
+use crate::models::{MeterRecord, SignatureScheme};
+use crate::signer::{self, RecoverableSig, SignatureError};
+
+type SchemeResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Key algorithm of a registered meter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyScheme {
+    /// secp256k1 with a recovery byte; identity is the recovered Ethereum address
+    #[default]
+    Secp256k1Recoverable,
+    /// secp256k1 ECDSA checked against a registered public key
+    Secp256k1,
+    /// Ed25519 over the canonical record bytes
+    Ed25519,
+    /// ECDSA P-256 (TPM-backed gateways)
+    P256,
+}
+
+impl fmt::Display for KeyScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeyScheme::Secp256k1Recoverable => "secp256k1-recoverable",
+            KeyScheme::Secp256k1 => "secp256k1",
+            KeyScheme::Ed25519 => "ed25519",
+            KeyScheme::P256 => "p256",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Verifies meter signatures for one key algorithm
+pub trait SignatureVerifier: Send + Sync {
+    /// Check `signature` over `record` under the given message encoding and
+    /// return the signer identity (Ethereum address or public key hex)
+    fn verify(&self, crypto: &CryptoService, record: &MeterRecord, signature: &[u8], encoding: SignatureScheme)
+        -> SchemeResult<String>;
+
+    /// Check a signature over other meter-signed messages (key rotations). They are signed
+    /// like legacy records: SHA-256 prehash for ECDSA, the bytes themselves for Ed25519.
+    fn verify_message(&self, message: &[u8], signature: &[u8]) -> SchemeResult<String>;
+}
+
+/// Build the verifier for a key scheme; every scheme but the recoverable one needs a public key
+pub fn verifier_for(scheme: KeyScheme, public_key_hex: Option<&str>) -> SchemeResult<Box<dyn SignatureVerifier>> {
+    let public_key = public_key_hex
+        .map(|key| hex::decode(key.trim_start_matches("0x")))
+        .transpose()
+        .map_err(|_| format!("Invalid {} public key", scheme))?;
+
+    match (scheme, public_key) {
+        (KeyScheme::Secp256k1Recoverable, _) => Ok(Box::new(Secp256k1RecoverableVerifier)),
+        (KeyScheme::Secp256k1, Some(key)) => Ok(Box::new(Secp256k1Verifier {
+            public_key: PublicKey::from_slice(&key).map_err(|e| format!("Invalid secp256k1 public key: {}", e))?,
+        })),
+        (KeyScheme::Ed25519, Some(key)) => {
+            let key: [u8; 32] = key.try_into().map_err(|_| "Ed25519 public keys are 32 bytes")?;
+            Ok(Box::new(Ed25519Verifier {
+                public_key: ed25519_dalek::VerifyingKey::from_bytes(&key).map_err(|e| format!("Invalid Ed25519 public key: {}", e))?,
+            }))
+        }
+        (KeyScheme::P256, Some(key)) => Ok(Box::new(P256Verifier {
+            public_key: p256::ecdsa::VerifyingKey::from_sec1_bytes(&key).map_err(|e| format!("Invalid P-256 public key: {}", e))?,
+        })),
+        (scheme, None) => Err(format!("{} meters need a registered public key", scheme).into()),
+    }
+}
+
+/// Recover the signer from `r || s || v` or EIP-2098 signatures (the scheme unregistered meters use)
+pub struct Secp256k1RecoverableVerifier;
+
+impl SignatureVerifier for Secp256k1RecoverableVerifier {
+    fn verify(&self, crypto: &CryptoService, record: &MeterRecord, signature: &[u8], encoding: SignatureScheme)
+        -> SchemeResult<String> {
+        crypto.recover_meter_address(record, signature, encoding)
+    }
+
+    fn verify_message(&self, message: &[u8], signature: &[u8]) -> SchemeResult<String> {
+        let public_key = RecoverableSig::from_bytes(signature)?.recover(&sha256(message))?;
+        Ok(signer::to_checksum_address(&signer::public_key_to_address(&public_key)))
+    }
+}
+
+pub struct Secp256k1Verifier {
+    public_key: PublicKey,
+}
+
+impl SignatureVerifier for Secp256k1Verifier {
+    fn verify(&self, crypto: &CryptoService, record: &MeterRecord, signature: &[u8], encoding: SignatureScheme)
+        -> SchemeResult<String> {
+        self.verify_digest(&crypto.meter_signing_digest(record, encoding)?, signature)
+    }
+
+    fn verify_message(&self, message: &[u8], signature: &[u8]) -> SchemeResult<String> {
+        self.verify_digest(&sha256(message), signature)
+    }
+}
+
+impl Secp256k1Verifier {
+    fn verify_digest(&self, digest: &[u8; 32], signature: &[u8]) -> SchemeResult<String> {
+        // A trailing recovery byte is tolerated and ignored
+        let compact = match signature.len() {
+            64 | 65 => &signature[..64],
+            len => return Err(format!("secp256k1 signatures are 64 bytes, got {}", len).into()),
+        };
+
+        signer::check_scalars(&compact[..32], &compact[32..])?;
+        let signature = Signature::from_compact(compact)?;
+        Secp256k1::verification_only()
+            .verify_ecdsa(&secp256k1::Message::from_digest(*digest), &signature, &self.public_key)
+            .map_err(|_| "secp256k1 signature does not match the registered key")?;
+
+        Ok(hex::encode(self.public_key.serialize()))
+    }
+}
+
+pub struct Ed25519Verifier {
+    public_key: ed25519_dalek::VerifyingKey,
+}
+
+impl SignatureVerifier for Ed25519Verifier {
+    fn verify(&self, crypto: &CryptoService, record: &MeterRecord, signature: &[u8], encoding: SignatureScheme)
+        -> SchemeResult<String> {
+        // Ed25519 hashes internally, so it signs the canonical message itself
+        if encoding != SignatureScheme::Legacy {
+            return Err("Ed25519 meters sign the canonical record, not EIP-191/EIP-712 digests".into());
+        }
+
+        self.verify_message(crypto.create_message(record).as_bytes(), signature)
+    }
+
+    fn verify_message(&self, message: &[u8], signature: &[u8]) -> SchemeResult<String> {
+        let signature = ed25519_dalek::Signature::from_slice(signature)
+            .map_err(|_| format!("Ed25519 signatures are 64 bytes, got {}", signature.len()))?;
+        self.public_key
+            .verify(message, &signature)
+            .map_err(|_| "Ed25519 signature does not match the registered key")?;
+
+        Ok(hex::encode(self.public_key.as_bytes()))
+    }
+}
+
+pub struct P256Verifier {
+    public_key: p256::ecdsa::VerifyingKey,
+}
+
+impl SignatureVerifier for P256Verifier {
+    fn verify(&self, crypto: &CryptoService, record: &MeterRecord, signature: &[u8], encoding: SignatureScheme)
+        -> SchemeResult<String> {
+        self.verify_digest(&crypto.meter_signing_digest(record, encoding)?, signature)
+    }
+
+    fn verify_message(&self, message: &[u8], signature: &[u8]) -> SchemeResult<String> {
+        self.verify_digest(&sha256(message), signature)
+    }
+}
+
+impl P256Verifier {
+    fn verify_digest(&self, digest: &[u8; 32], signature: &[u8]) -> SchemeResult<String> {
+        // Fixed-size r || s, or DER as most TPM stacks emit it
+        let signature = p256::ecdsa::Signature::from_slice(signature)
+            .or_else(|_| p256::ecdsa::Signature::from_der(signature))
+            .map_err(|_| "P-256 signatures are 64-byte r || s or DER")?;
+        // Like secp256k1, only low-S: (r, n - s) would otherwise be a second valid signature.
+        // Gateways whose TPM emits high-S signatures must normalize them before sending.
+        if signature.normalize_s().is_some() {
+            return Err(SignatureError::HighS.into());
+        }
+
+        self.public_key
+            .verify_prehash(digest, &signature)
+            .map_err(|_| "P-256 signature does not match the registered key")?;
+
+        Ok(hex::encode(self.public_key.to_encoded_point(true).as_bytes()))
+    }
+}
+
+fn sha256(message: &[u8]) -> [u8; 32] {
+    Sha256::digest(message).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RecordEncoding;
+    use p256::ecdsa::signature::Signer as _;
+
+    fn record() -> MeterRecord {
+        MeterRecord {
+            meter_id: "meter_ed".to_string(),
+            timestamp: 1640995200000,
+            kwh_delta: 1.25,
+            nonce: "nonce".to_string(),
+            encoding: RecordEncoding::V1,
+        }
+    }
+
+    #[test]
+    fn test_ed25519_signatures() {
+        let crypto = CryptoService::new();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let verifier = verifier_for(KeyScheme::Ed25519, Some(&hex::encode(key.verifying_key().as_bytes()))).unwrap();
+
+        let signature = ed25519_dalek::Signer::sign(&key, crypto.create_message(&record()).as_bytes()).to_bytes();
+        let identity = verifier.verify(&crypto, &record(), &signature, SignatureScheme::Legacy).unwrap();
+        assert_eq!(identity, hex::encode(key.verifying_key().as_bytes()));
+
+        let mut tampered = record();
+        tampered.kwh_delta = 12.5;
+        assert!(verifier.verify(&crypto, &tampered, &signature, SignatureScheme::Legacy).is_err());
+        assert!(verifier.verify(&crypto, &record(), &signature, SignatureScheme::Eip191).is_err());
+    }
+
+    #[test]
+    fn test_p256_signatures() {
+        let crypto = CryptoService::new();
+        let key = p256::ecdsa::SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let public_key = hex::encode(key.verifying_key().to_encoded_point(false).as_bytes());
+        let verifier = verifier_for(KeyScheme::P256, Some(&public_key)).unwrap();
+
+        // `sign` hashes with SHA-256, which is the legacy digest
+        let signature: p256::ecdsa::Signature = key.sign(crypto.create_message(&record()).as_bytes());
+        let signature = signature.normalize_s().unwrap_or(signature);
+        assert!(verifier.verify(&crypto, &record(), &signature.to_bytes(), SignatureScheme::Legacy).is_ok());
+        assert!(verifier.verify(&crypto, &record(), signature.to_der().as_bytes(), SignatureScheme::Legacy).is_ok());
+
+        // The high-S twin of a valid signature is refused
+        let high_s = p256::ecdsa::Signature::from_scalars(signature.r(), -signature.s()).unwrap();
+        let refused = verifier.verify(&crypto, &record(), &high_s.to_bytes(), SignatureScheme::Legacy).unwrap_err();
+        assert_eq!(refused.downcast_ref::<SignatureError>(), Some(&SignatureError::HighS));
+
+        let other = p256::ecdsa::SigningKey::from_slice(&[10u8; 32]).unwrap();
+        let signature: p256::ecdsa::Signature = other.sign(crypto.create_message(&record()).as_bytes());
+        assert!(verifier.verify(&crypto, &record(), &signature.to_bytes(), SignatureScheme::Legacy).is_err());
+    }
+
+    #[test]
+    fn test_secp256k1_explicit_key() {
+        let crypto = CryptoService::new();
+        let secp = Secp256k1::new();
+        let secret = secp256k1::SecretKey::from_slice(&[5u8; 32]).unwrap();
+        let public_key = PublicKey::from_secret_key(&secp, &secret);
+        let verifier = verifier_for(KeyScheme::Secp256k1, Some(&hex::encode(public_key.serialize()))).unwrap();
+
+        let digest = crypto.meter_signing_digest(&record(), SignatureScheme::Eip712).unwrap();
+        let signature = secp.sign_ecdsa(&secp256k1::Message::from_digest(digest), &secret).serialize_compact();
+        assert!(verifier.verify(&crypto, &record(), &signature, SignatureScheme::Eip712).is_ok());
+        assert!(verifier.verify(&crypto, &record(), &signature, SignatureScheme::Legacy).is_err());
+    }
+
+    #[test]
+    fn test_schemes_need_public_keys() {
+        assert!(verifier_for(KeyScheme::Secp256k1Recoverable, None).is_ok());
+        assert!(verifier_for(KeyScheme::Ed25519, None).is_err());
+        assert!(verifier_for(KeyScheme::P256, Some("0x1234")).is_err());
+    }
+}
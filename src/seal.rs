@@ -1,10 +1,110 @@
 // FILE: src/seal.rs
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use schemars::JsonSchema;
 use reqwest::Client;
 use std::collections::HashMap;
+use std::str::FromStr;
+use sha2::{Digest, Sha256};
+use chrono::Utc;
 use tokio::time::{timeout, Duration};
 use log::{info, error, warn};
-use crate::models::ProofData;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use crate::canonical::{format_js_number, json_string};
+use crate::config::Config;
+use crate::envelope::{self, EncryptedEnvelope, Recipient};
+use crate::models::{ProofData, VerifiedRecord};
+use crate::record_set::{self, ChunkRef, RecordChunk, RecordManifest};
+use crate::signer::{self, EnclaveSigner};
+
+/// HTTP API flavour spoken by the Walrus publisher/aggregator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WalrusApi {
+    /// Current Walrus API: raw bytes on `PUT /v1/blobs?epochs=N`, read back from `GET /v1/blobs/{id}`
+    Blobs,
+    /// Base64 JSON body on `PUT /v1/store`, read back from `GET /v1/{id}` (TS mock server)
+    Legacy,
+}
+
+impl FromStr for WalrusApi {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "blobs" => Ok(WalrusApi::Blobs),
+            "legacy" => Ok(WalrusApi::Legacy),
+            other => Err(format!("Unknown Walrus API '{}' (expected 'blobs' or 'legacy')", other)),
+        }
+    }
+}
+
+/// Offline Walrus storage price model. Prices are in FROST (1 WAL = 10^9 FROST)
+/// and charged per started storage unit of the *encoded* blob.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalrusPricing {
+    /// Erasure-coding expansion of the blob payload (about 5x for RedStuff)
+    pub encoding_overhead: f64,
+    /// Fixed per-blob metadata added to the encoded size, independent of blob size
+    pub metadata_bytes: u64,
+    /// Size of a billable storage unit
+    pub unit_bytes: u64,
+    /// Price per storage unit per epoch
+    pub storage_price_per_unit: u64,
+    /// One-off write fee per storage unit
+    pub write_price_per_unit: u64,
+}
+
+impl Default for WalrusPricing {
+    fn default() -> Self {
+        Self {
+            encoding_overhead: 5.0,
+            metadata_bytes: 64 * 1024 * 1024,
+            unit_bytes: 1024 * 1024,
+            storage_price_per_unit: 100_000,
+            write_price_per_unit: 20_000,
+        }
+    }
+}
+
+/// Breakdown of the estimated cost of storing a blob
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CostEstimate {
+    pub unencoded_bytes: u64,
+    pub encoded_bytes: u64,
+    pub storage_units: u64,
+    pub epochs: u32,
+    pub storage_cost: u64,
+    pub write_fee: u64,
+    pub total_cost: u64,
+}
+
+impl WalrusPricing {
+    /// Estimate the cost of storing `bytes` of payload for `epochs` epochs
+    pub fn estimate(&self, bytes: u64, epochs: u32) -> CostEstimate {
+        let encoded_bytes = (bytes as f64 * self.encoding_overhead).ceil() as u64 + self.metadata_bytes;
+        let storage_units = encoded_bytes.div_ceil(self.unit_bytes.max(1));
+        let storage_cost = storage_units
+            .saturating_mul(self.storage_price_per_unit)
+            .saturating_mul(epochs as u64);
+        let write_fee = storage_units.saturating_mul(self.write_price_per_unit);
+
+        CostEstimate {
+            unencoded_bytes: bytes,
+            encoded_bytes,
+            storage_units,
+            epochs,
+            storage_cost,
+            write_fee,
+            total_cost: storage_cost.saturating_add(write_fee),
+        }
+    }
+
+    /// Total cost of storing blobs of the given sizes for `epochs` epochs
+    pub fn plan_cost(&self, blob_sizes: &[u64], epochs: u32) -> u64 {
+        blob_sizes.iter().map(|size| self.estimate(*size, epochs).total_cost).sum()
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalrusUploadRequest {
@@ -13,8 +113,10 @@ pub struct WalrusUploadRequest {
     pub deletable: bool,
 }
 
+/// Legacy `/v1/store` reply (the TS mock server answers in camelCase)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalrusUploadResponse {
+    #[serde(alias = "blobId")]
     pub blob_id: String,
     pub cost: u64,
     pub event: WalrusEvent,
@@ -22,14 +124,153 @@ pub struct WalrusUploadResponse {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalrusEvent {
+    #[serde(alias = "txDigest")]
     pub tx_digest: String,
+    #[serde(alias = "eventSeq", deserialize_with = "de_u64_from_str_or_num")]
     pub event_seq: u64,
 }
 
+/// `PUT /v1/blobs` reply: either a freshly registered blob or one that was already certified
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlobStoreResponse {
+    NewlyCreated(NewlyCreatedBlob),
+    AlreadyCertified(AlreadyCertifiedBlob),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewlyCreatedBlob {
+    pub blob_object: BlobObject,
+    #[serde(default)]
+    pub cost: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobObject {
+    /// Sui object ID of the blob
+    pub id: String,
+    pub blob_id: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub registered_epoch: u64,
+    pub certified_epoch: Option<u64>,
+    pub storage: BlobStorage,
+    #[serde(default)]
+    pub deletable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobStorage {
+    pub id: String,
+    pub start_epoch: u64,
+    pub end_epoch: u64,
+    #[serde(default)]
+    pub storage_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlreadyCertifiedBlob {
+    pub blob_id: String,
+    pub event: Option<WalrusEvent>,
+    /// Newer publishers reference the certifying blob object instead of an event
+    pub object: Option<String>,
+    pub end_epoch: u64,
+}
+
+/// Protocol-independent result of a store call
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredBlob {
+    pub blob_id: String,
+    pub sui_object_id: Option<String>,
+    pub tx_digest: Option<String>,
+    pub cost: Option<u64>,
+    pub end_epoch: Option<u64>,
+    pub already_certified: bool,
+}
+
+impl From<WalrusUploadResponse> for StoredBlob {
+    fn from(response: WalrusUploadResponse) -> Self {
+        StoredBlob {
+            blob_id: response.blob_id,
+            sui_object_id: None,
+            tx_digest: Some(response.event.tx_digest),
+            cost: Some(response.cost),
+            end_epoch: None,
+            already_certified: false,
+        }
+    }
+}
+
+impl From<BlobStoreResponse> for StoredBlob {
+    fn from(response: BlobStoreResponse) -> Self {
+        match response {
+            BlobStoreResponse::NewlyCreated(created) => StoredBlob {
+                blob_id: created.blob_object.blob_id,
+                sui_object_id: Some(created.blob_object.id),
+                tx_digest: None,
+                cost: Some(created.cost),
+                end_epoch: Some(created.blob_object.storage.end_epoch),
+                already_certified: false,
+            },
+            // Same content was stored before: nothing was paid, reuse the existing blob
+            BlobStoreResponse::AlreadyCertified(certified) => StoredBlob {
+                blob_id: certified.blob_id,
+                sui_object_id: certified.object,
+                tx_digest: certified.event.map(|e| e.tx_digest),
+                cost: None,
+                end_epoch: Some(certified.end_epoch),
+                already_certified: true,
+            },
+        }
+    }
+}
+
+/// Walrus encodes sequence numbers as strings, the mock server as numbers
+fn de_u64_from_str_or_num<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrNum {
+        Str(String),
+        Num(u64),
+    }
+
+    match StrOrNum::deserialize(deserializer)? {
+        StrOrNum::Str(s) => s.parse().map_err(serde::de::Error::custom),
+        StrOrNum::Num(n) => Ok(n),
+    }
+}
+
+/// ECDSA seal over a content hash (same shape as `SealSignature` in `scripts/upload.ts`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SealSignature {
+    pub hash: String,
+    pub signature: String,
+    pub signer: String,
+    pub timestamp: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SealRequest {
     pub proof_data: ProofData,
     pub metadata: HashMap<String, String>,
+    /// Blob holding the unsealed envelope the seal refers to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub walrus_cid: Option<String>,
+    /// Manifest blob of the full record set, when records are sealed too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub records_manifest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seal_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seal_signature: Option<SealSignature>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,14 +280,162 @@ pub struct SealResponse {
     pub walrus_url: Option<String>,
     pub tx_digest: Option<String>,
     pub cost: Option<u64>,
+    pub sui_object_id: Option<String>,
+    pub end_epoch: Option<u64>,
+    pub already_certified: bool,
+    /// Blob of the unsealed envelope (`walrus_cid` in the seal hash)
+    pub content_blob_id: Option<String>,
+    /// Manifest blob of the sealed record set
+    pub records_manifest_blob_id: Option<String>,
+    pub seal_hash: Option<String>,
+    pub seal_signature: Option<SealSignature>,
     pub error: Option<String>,
 }
 
+/// Sealing a proof was refused because its estimated cost is over the configured budget
+#[derive(Debug, thiserror::Error)]
+#[error("Seal budget exceeded: estimated {estimated} FROST, budget {budget} FROST")]
+pub struct BudgetExceeded {
+    pub estimated: u64,
+    pub budget: u64,
+}
+
+/// Payload of a Walrus blob written by the enclave
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StoredEnvelope {
+    Encrypted(EncryptedEnvelope),
+    Plain(Box<SealRequest>),
+}
+
+impl SealResponse {
+    fn failed(error: String) -> Self {
+        SealResponse {
+            success: false,
+            blob_id: None,
+            walrus_url: None,
+            tx_digest: None,
+            cost: None,
+            sui_object_id: None,
+            end_epoch: None,
+            already_certified: false,
+            content_blob_id: None,
+            records_manifest_blob_id: None,
+            seal_hash: None,
+            seal_signature: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// SHA-256 content hash of a sealed proof, byte-compatible with `generateSeal` in
+/// `scripts/upload.ts`, which hashes
+/// `JSON.stringify({ proofId, merkleRoot, aggregateKwh, recordCount, walrusCid })`
+pub fn seal_content_hash(proof_data: &ProofData, walrus_cid: &str) -> String {
+    let content = format!(
+        "{{\"proofId\":{},\"merkleRoot\":{},\"aggregateKwh\":{},\"recordCount\":{},\"walrusCid\":{}}}",
+        json_string(&proof_data.proof_id.to_string()),
+        json_string(&proof_data.merkle_root),
+        format_js_number(proof_data.aggregate_kwh),
+        proof_data.record_count,
+        json_string(walrus_cid),
+    );
+
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Message the seal signature is an EIP-191 signature over:
+/// `solidityPackedKeccak256(['string'], [sealHash])`
+fn seal_message(seal_hash: &str) -> [u8; 32] {
+    signer::keccak256(seal_hash.as_bytes())
+}
+
+/// Sign a seal hash the way `scripts/upload.ts` does with `wallet.signMessage`
+pub fn sign_seal(signer: &EnclaveSigner, seal_hash: &str) -> SealSignature {
+    SealSignature {
+        hash: seal_hash.to_string(),
+        signature: signer.sign_message(&seal_message(seal_hash)),
+        signer: signer.address(),
+        timestamp: Utc::now().timestamp_millis(),
+    }
+}
+
+/// Check that a seal signature was produced by its claimed signer over its hash
+pub fn verify_seal_signature(seal: &SealSignature) -> bool {
+    let signature = match hex::decode(seal.signature.trim_start_matches("0x")) {
+        Ok(bytes) if bytes.len() == 65 => {
+            let mut signature = [0u8; 65];
+            signature.copy_from_slice(&bytes);
+            signature
+        }
+        _ => return false,
+    };
+
+    let digest = signer::eip191_hash(&seal_message(&seal.hash));
+    match signer::recover_address(&digest, &signature) {
+        Ok(address) => signer::to_checksum_address(&address).eq_ignore_ascii_case(&seal.signer),
+        Err(_) => false,
+    }
+}
+
+/// Check an envelope is sealed: it names its content blob and carries the matching content
+/// hash and a valid signature over it. An unsealed envelope proves nothing.
+pub fn check_seal(seal_request: &SealRequest) -> Result<(), String> {
+    let (Some(walrus_cid), Some(seal_hash), Some(seal_signature)) =
+        (&seal_request.walrus_cid, &seal_request.seal_hash, &seal_request.seal_signature) else {
+        return Err("envelope is not sealed".to_string());
+    };
+    if seal_content_hash(&seal_request.proof_data, walrus_cid) != *seal_hash {
+        return Err("seal hash mismatch".to_string());
+    }
+    if seal_signature.hash != *seal_hash || !verify_seal_signature(seal_signature) {
+        return Err("seal signature invalid".to_string());
+    }
+    Ok(())
+}
+
+/// Upper bound on what `walrus_cid`, `seal_hash`, `seal_signature` or `records_manifest` add to an envelope
+const SEAL_FIELDS_ALLOWANCE: u64 = 1024;
+/// Size allowance for a record manifest: fixed fields plus one entry per chunk
+const MANIFEST_ALLOWANCE: u64 = 1024;
+const MANIFEST_ENTRY_ALLOWANCE: u64 = 256;
+
+/// Sizes of the blobs sealing stores: the record chunks of `chunk_sizes` and their manifest (if
+/// any), then the content and sealed envelopes, each at most the bare `envelope_bytes` plus the
+/// seal fields
+pub fn seal_blob_plan(envelope_bytes: u64, chunk_sizes: &[u64]) -> Vec<u64> {
+    let mut planned = chunk_sizes.to_vec();
+    if !chunk_sizes.is_empty() {
+        planned.push(MANIFEST_ALLOWANCE + MANIFEST_ENTRY_ALLOWANCE * chunk_sizes.len() as u64);
+    }
+    let envelope_len = envelope_bytes + SEAL_FIELDS_ALLOWANCE;
+    planned.extend([envelope_len, envelope_len]);
+    planned
+}
+
+/// Blobs written while sealing one proof
+struct SealUploads {
+    content: StoredBlob,
+    sealed: StoredBlob,
+    records_manifest: Option<String>,
+    seal_hash: String,
+    seal_signature: Option<SealSignature>,
+    cost: Option<u64>,
+}
+
 pub struct WalrusClient {
     client: Client,
     publisher_url: String,
     gateway_url: String,
     default_epochs: u32,
+    api: WalrusApi,
+    signer: Option<EnclaveSigner>,
+    recipients: Vec<Recipient>,
+    pricing: WalrusPricing,
+    budget: Option<u64>,
+    chunk_size: usize,
 }
 
 impl WalrusClient {
@@ -61,46 +450,191 @@ impl WalrusClient {
             publisher_url,
             gateway_url,
             default_epochs,
+            api: WalrusApi::Blobs,
+            signer: None,
+            recipients: Vec::new(),
+            pricing: WalrusPricing::default(),
+            budget: None,
+            chunk_size: 1000,
+        }
+    }
+
+    /// Build a client from the enclave configuration
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.walrus_publisher_url.clone(),
+            config.walrus_gateway_url.clone(),
+            config.walrus_epochs,
+        )
+        .with_api(config.walrus_api)
+        .with_signer(EnclaveSigner::from_config(config).ok().flatten())
+        .with_recipients(config.seal_recipients.clone())
+        .with_pricing(config.walrus_pricing.clone(), config.seal_budget)
+        .with_chunk_size(config.seal_chunk_records)
+    }
+
+    /// Select the publisher/aggregator HTTP API flavour
+    pub fn with_api(mut self, api: WalrusApi) -> Self {
+        self.api = api;
+        self
+    }
+
+    /// Set the key used to sign seals (unsigned seals carry only the content hash)
+    pub fn with_signer(mut self, signer: Option<EnclaveSigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Encrypt uploaded envelopes for these recipients (no recipients uploads in the clear)
+    pub fn with_recipients(mut self, recipients: Vec<Recipient>) -> Self {
+        self.recipients = recipients;
+        self
+    }
+
+    /// Set the price model and the maximum estimated cost of sealing one proof
+    pub fn with_pricing(mut self, pricing: WalrusPricing, budget: Option<u64>) -> Self {
+        self.pricing = pricing;
+        self.budget = budget;
+        self
+    }
+
+    /// Maximum number of records per sealed record chunk
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Public URL a blob can be read back from
+    pub fn blob_url(&self, blob_id: &str) -> String {
+        match self.api {
+            WalrusApi::Blobs => format!("{}/v1/blobs/{}", self.gateway_url, blob_id),
+            WalrusApi::Legacy => format!("{}/v1/{}", self.gateway_url, blob_id),
         }
     }
 
     /// Upload proof data to Walrus and return the seal response
     pub async fn seal_proof(&self, proof_data: &ProofData) -> Result<SealResponse, Box<dyn std::error::Error>> {
+        self.seal_proof_with_records(proof_data, None).await
+    }
+
+    /// Upload proof data to Walrus, optionally together with its full record set
+    /// (chunk blobs plus a manifest blob referenced from the proof envelope). A proof over the
+    /// seal budget is refused with a [`BudgetExceeded`] error before anything is stored.
+    pub async fn seal_proof_with_records(&self, proof_data: &ProofData, records: Option<&[VerifiedRecord]>)
+        -> Result<SealResponse, Box<dyn std::error::Error>> {
         info!("🔒 Sealing proof {} to Walrus", proof_data.proof_id);
 
         // Prepare the data to be sealed
         let seal_data = self.prepare_seal_data(proof_data)?;
         
-        // Upload to Walrus
-        match self.upload_to_walrus(&seal_data).await {
-            Ok(upload_response) => {
-                info!("✅ Successfully sealed proof to Walrus: {}", upload_response.blob_id);
-                
-                let walrus_url = format!("{}/v1/{}", self.gateway_url, upload_response.blob_id);
-                
+        match self.upload_and_seal(proof_data, seal_data, records).await {
+            Ok(uploads) => {
+                let sealed = uploads.sealed;
+                if sealed.already_certified {
+                    info!("♻️ Proof already certified on Walrus: {}", sealed.blob_id);
+                } else {
+                    info!("✅ Successfully sealed proof to Walrus: {}", sealed.blob_id);
+                }
+
                 Ok(SealResponse {
                     success: true,
-                    blob_id: Some(upload_response.blob_id),
-                    walrus_url: Some(walrus_url),
-                    tx_digest: Some(upload_response.event.tx_digest),
-                    cost: Some(upload_response.cost),
+                    walrus_url: Some(self.blob_url(&sealed.blob_id)),
+                    blob_id: Some(sealed.blob_id),
+                    tx_digest: sealed.tx_digest,
+                    cost: uploads.cost,
+                    sui_object_id: sealed.sui_object_id,
+                    end_epoch: sealed.end_epoch,
+                    already_certified: sealed.already_certified,
+                    content_blob_id: Some(uploads.content.blob_id),
+                    records_manifest_blob_id: uploads.records_manifest,
+                    seal_hash: Some(uploads.seal_hash),
+                    seal_signature: uploads.seal_signature,
                     error: None,
                 })
             },
+            Err(e) if e.is::<BudgetExceeded>() => Err(e),
             Err(e) => {
                 error!("❌ Failed to seal proof to Walrus: {}", e);
-                Ok(SealResponse {
-                    success: false,
-                    blob_id: None,
-                    walrus_url: None,
-                    tx_digest: None,
-                    cost: None,
-                    error: Some(e.to_string()),
-                })
+                Ok(SealResponse::failed(e.to_string()))
             }
         }
     }
 
+    /// Upload the record set (if any) and the proof envelope, seal the envelope's blob ID,
+    /// then upload the sealed envelope. Mirrors the two-step upload in `scripts/upload.ts`.
+    async fn upload_and_seal(&self, proof_data: &ProofData, mut seal_data: SealRequest, records: Option<&[VerifiedRecord]>)
+        -> Result<SealUploads, Box<dyn std::error::Error>> {
+
+        let chunks = match records {
+            Some(records) => record_set::chunk_records(proof_data.proof_id, records, self.chunk_size)
+                .iter()
+                .map(|chunk| Ok((chunk.records.len(), serde_json::to_vec(chunk)?)))
+                .collect::<Result<Vec<_>, serde_json::Error>>()?,
+            None => Vec::new(),
+        };
+
+        // Refuse before anything is stored
+        let chunk_sizes: Vec<u64> = chunks.iter().map(|(_, chunk)| chunk.len() as u64).collect();
+        self.check_budget(&seal_blob_plan(self.encode_envelope(&seal_data)?.len() as u64, &chunk_sizes))?;
+
+        let mut cost = None;
+        let mut add_cost = |blob: &StoredBlob| {
+            if let Some(blob_cost) = blob.cost {
+                cost = Some(cost.unwrap_or(0) + blob_cost);
+            }
+        };
+
+        let records_manifest = if chunks.is_empty() {
+            None
+        } else {
+            let mut chunk_refs = Vec::with_capacity(chunks.len());
+            for (index, (record_count, chunk)) in chunks.into_iter().enumerate() {
+                let sha256 = record_set::chunk_digest(&chunk);
+                let stored = self.upload_to_walrus(chunk).await?;
+                add_cost(&stored);
+                chunk_refs.push(ChunkRef { index, blob_id: stored.blob_id, record_count, sha256 });
+            }
+
+            let manifest = RecordManifest::new(proof_data, chunk_refs);
+            let stored = self.upload_to_walrus(serde_json::to_vec_pretty(&manifest)?).await?;
+            add_cost(&stored);
+            info!("📚 Sealed {} records in {} chunks, manifest {}", proof_data.record_count, manifest.chunks.len(), stored.blob_id);
+            Some(stored.blob_id)
+        };
+        seal_data.records_manifest = records_manifest.clone();
+
+        let content = self.upload_to_walrus(self.encode_envelope(&seal_data)?).await?;
+        add_cost(&content);
+
+        let seal_hash = seal_content_hash(proof_data, &content.blob_id);
+        let seal_signature = match &self.signer {
+            Some(signer) => Some(sign_seal(signer, &seal_hash)),
+            None => {
+                warn!("No enclave signing key configured, sealing proof {} unsigned", proof_data.proof_id);
+                None
+            }
+        };
+        info!("🔒 Generated seal hash: {}", seal_hash);
+
+        let sealed_data = SealRequest {
+            walrus_cid: Some(content.blob_id.clone()),
+            seal_hash: Some(seal_hash.clone()),
+            seal_signature: seal_signature.clone(),
+            ..seal_data
+        };
+        let sealed = self.upload_to_walrus(self.encode_envelope(&sealed_data)?).await?;
+        add_cost(&sealed);
+
+        Ok(SealUploads {
+            content,
+            sealed,
+            records_manifest,
+            seal_hash,
+            seal_signature,
+            cost,
+        })
+    }
+
     /// Prepare proof data for sealing (JSON format with metadata)
     fn prepare_seal_data(&self, proof_data: &ProofData) -> Result<SealRequest, Box<dyn std::error::Error>> {
         let mut metadata = HashMap::new();
@@ -116,29 +650,84 @@ impl WalrusClient {
         Ok(SealRequest {
             proof_data: proof_data.clone(),
             metadata,
+            walrus_cid: None,
+            records_manifest: None,
+            seal_hash: None,
+            seal_signature: None,
         })
     }
 
+    /// Serialize an envelope to JSON, encrypted when recipients are configured
+    fn encode_envelope(&self, seal_request: &SealRequest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let json_data = if self.recipients.is_empty() {
+            serde_json::to_string_pretty(seal_request)?
+        } else {
+            let encrypted = envelope::encrypt_envelope(seal_request, &self.recipients)
+                .map_err(|e| e.to_string())?;
+            serde_json::to_string_pretty(&encrypted)?
+        };
+
+        Ok(json_data.into_bytes())
+    }
+
+    /// Fail if storing blobs of the given sizes would exceed the seal budget
+    fn check_budget(&self, blob_sizes: &[u64]) -> Result<(), BudgetExceeded> {
+        let Some(budget) = self.budget else {
+            return Ok(());
+        };
+
+        let estimated = self.pricing.plan_cost(blob_sizes, self.default_epochs);
+        if estimated > budget {
+            return Err(BudgetExceeded { estimated, budget });
+        }
+
+        Ok(())
+    }
+
     /// Upload data to Walrus storage
-    async fn upload_to_walrus(&self, seal_request: &SealRequest) -> Result<WalrusUploadResponse, Box<dyn std::error::Error>> {
-        // Serialize the seal request to JSON
-        let json_data = serde_json::to_string_pretty(seal_request)?;
-        
-        // Base64 encode the JSON data
-        let encoded_data = base64::encode(json_data.as_bytes());
-        
-        // Prepare upload request
+    async fn upload_to_walrus(&self, data: Vec<u8>) -> Result<StoredBlob, Box<dyn std::error::Error>> {
+        match self.api {
+            WalrusApi::Blobs => self.store_blob(data).await,
+            WalrusApi::Legacy => self.store_legacy(&data).await,
+        }
+    }
+
+    /// Store raw bytes via `PUT /v1/blobs?epochs=N`
+    async fn store_blob(&self, data: Vec<u8>) -> Result<StoredBlob, Box<dyn std::error::Error>> {
+        let upload_url = format!("{}/v1/blobs", self.publisher_url);
+
+        info!("📤 Uploading {} bytes to Walrus: {}", data.len(), upload_url);
+
+        let response = timeout(
+            Duration::from_secs(60), // 60 second timeout for uploads
+            self.client
+                .put(&upload_url)
+                .query(&[("epochs", self.default_epochs)])
+                .body(data)
+                .send()
+        ).await??;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Walrus upload failed: {}", error_text).into());
+        }
+
+        let store_response: BlobStoreResponse = response.json().await?;
+        Ok(store_response.into())
+    }
+
+    /// Store base64-wrapped bytes via the legacy `PUT /v1/store` JSON endpoint
+    async fn store_legacy(&self, data: &[u8]) -> Result<StoredBlob, Box<dyn std::error::Error>> {
         let upload_request = WalrusUploadRequest {
-            data: encoded_data,
+            data: BASE64.encode(data),
             epochs: self.default_epochs,
             deletable: false, // Proofs should be permanent
         };
 
-        // Make the upload request
         let upload_url = format!("{}/v1/store", self.publisher_url);
-        
+
         info!("📤 Uploading to Walrus: {}", upload_url);
-        
+
         let response = timeout(
             Duration::from_secs(60), // 60 second timeout for uploads
             self.client
@@ -153,12 +742,12 @@ impl WalrusClient {
         }
 
         let upload_response: WalrusUploadResponse = response.json().await?;
-        Ok(upload_response)
+        Ok(upload_response.into())
     }
 
-    /// Retrieve sealed data from Walrus
-    pub async fn retrieve_sealed_data(&self, blob_id: &str) -> Result<SealRequest, Box<dyn std::error::Error>> {
-        let retrieve_url = format!("{}/v1/{}", self.gateway_url, blob_id);
+    /// Download the raw bytes of a blob
+    async fn fetch_blob(&self, blob_id: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let retrieve_url = self.blob_url(blob_id);
         
         info!("📥 Retrieving from Walrus: {}", retrieve_url);
         
@@ -174,14 +763,66 @@ impl WalrusClient {
 
         let data_bytes = response.bytes().await?;
         
-        // Decode base64 data
-        let json_data = base64::decode(&data_bytes)?;
-        let json_str = String::from_utf8(json_data)?;
+        // The legacy gateway hands back the base64 payload it was given
+        match self.api {
+            WalrusApi::Blobs => Ok(data_bytes.to_vec()),
+            WalrusApi::Legacy => Ok(BASE64.decode(&data_bytes)?),
+        }
+    }
+
+    /// Retrieve a stored envelope from Walrus without decrypting it
+    pub async fn retrieve_envelope(&self, blob_id: &str) -> Result<StoredEnvelope, Box<dyn std::error::Error>> {
+        let json_str = String::from_utf8(self.fetch_blob(blob_id).await?)?;
         
         // Parse JSON
-        let seal_request: SealRequest = serde_json::from_str(&json_str)?;
+        let stored: StoredEnvelope = serde_json::from_str(&json_str)?;
         
-        Ok(seal_request)
+        Ok(stored)
+    }
+
+    /// Rebuild a sealed record set from its manifest blob, checking every chunk's digest.
+    /// Feed the result to `record_set::verify_records` together with the sealed proof.
+    pub async fn retrieve_record_set(&self, manifest_blob_id: &str)
+        -> Result<(RecordManifest, Vec<VerifiedRecord>), Box<dyn std::error::Error>> {
+
+        let manifest: RecordManifest = serde_json::from_slice(&self.fetch_blob(manifest_blob_id).await?)?;
+
+        let mut records = Vec::with_capacity(manifest.record_count);
+        for (position, chunk_ref) in manifest.chunks.iter().enumerate() {
+            let bytes = self.fetch_blob(&chunk_ref.blob_id).await?;
+            if record_set::chunk_digest(&bytes) != chunk_ref.sha256 {
+                return Err(format!("Chunk {} digest mismatch", chunk_ref.blob_id).into());
+            }
+
+            let chunk: RecordChunk = serde_json::from_slice(&bytes)?;
+            if chunk.proof_id != manifest.proof_id || chunk.index != position || chunk_ref.index != position
+                || chunk.records.len() != chunk_ref.record_count {
+                return Err(format!("Chunk {} does not match the manifest", chunk_ref.blob_id).into());
+            }
+            records.extend(chunk.records);
+        }
+
+        Ok((manifest, records))
+    }
+
+    /// Retrieve sealed data from Walrus (fails for encrypted envelopes)
+    pub async fn retrieve_sealed_data(&self, blob_id: &str) -> Result<SealRequest, Box<dyn std::error::Error>> {
+        match self.retrieve_envelope(blob_id).await? {
+            StoredEnvelope::Plain(seal_request) => Ok(*seal_request),
+            StoredEnvelope::Encrypted(_) => {
+                Err(format!("Blob {} is encrypted, a recipient key is required", blob_id).into())
+            }
+        }
+    }
+
+    /// Retrieve sealed data, decrypting it with a recipient's secret key when encrypted
+    pub async fn retrieve_decrypted(&self, blob_id: &str, secret_key_hex: &str) -> Result<SealRequest, Box<dyn std::error::Error>> {
+        match self.retrieve_envelope(blob_id).await? {
+            StoredEnvelope::Plain(seal_request) => Ok(*seal_request),
+            StoredEnvelope::Encrypted(encrypted) => {
+                Ok(envelope::decrypt_envelope(&encrypted, secret_key_hex).map_err(|e| e.to_string())?)
+            }
+        }
     }
 
     /// Verify that a proof exists and is valid on Walrus
@@ -189,7 +830,15 @@ impl WalrusClient {
         match self.retrieve_sealed_data(blob_id).await {
             Ok(seal_request) => {
                 let proof_id_str = seal_request.proof_data.proof_id.to_string();
-                Ok(proof_id_str == expected_proof_id)
+                if proof_id_str != expected_proof_id {
+                    return Ok(false);
+                }
+
+                if let Err(reason) = check_seal(&seal_request) {
+                    warn!("Seal of {} rejected: {}", blob_id, reason);
+                    return Ok(false);
+                }
+                Ok(true)
             },
             Err(e) => {
                 warn!("Seal verification failed for {}: {}", blob_id, e);
@@ -198,38 +847,16 @@ impl WalrusClient {
         }
     }
 
-    /// Get storage cost estimate for data
-    pub async fn estimate_cost(&self, data_size_bytes: u64) -> Result<u64, Box<dyn std::error::Error>> {
-        // Implement actual cost estimation API call
-        let cost_response = self.client
-            .get(&format!("{}/api/v1/cost-estimate", self.endpoint))
-            .query(&[("size", data.len())])
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get cost estimate: {}", e))?;
-        
-        if !cost_response.status().is_success() {
-            return Err(format!("Cost estimation failed: {}", cost_response.status()));
-        }
-        
-        let cost_data: serde_json::Value = cost_response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse cost estimate: {}", e))?;
-        
-        cost_data["estimated_cost"].as_f64().unwrap_or(0.001)
-        // For now, return a simple estimate based on data size and epochs
-        let base_cost_per_mb = 1000; // Mock cost in gas units
-        let size_mb = (data_size_bytes as f64 / 1_000_000.0).ceil() as u64;
-        let total_cost = size_mb * base_cost_per_mb * self.default_epochs as u64;
-        
-        Ok(total_cost)
+    /// Estimate the cost of storing a blob for the configured number of epochs
+    pub fn estimate_cost(&self, data_size_bytes: u64) -> CostEstimate {
+        self.pricing.estimate(data_size_bytes, self.default_epochs)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::HashScheme;
     use uuid::Uuid;
     use chrono::Utc;
 
@@ -251,6 +878,10 @@ mod tests {
             meter_ids: vec!["meter1".to_string(), "meter2".to_string()],
             generated_at: Utc::now(),
             version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::Legacy,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
         };
 
         let seal_request = client.prepare_seal_data(&proof_data).unwrap();
@@ -261,18 +892,241 @@ mod tests {
         assert_eq!(seal_request.metadata.get("proof_type").unwrap(), "aggregated_meter_data");
     }
 
+    fn fixed_proof() -> ProofData {
+        ProofData {
+            proof_id: Uuid::parse_str("6f1c2a9e-4b7d-4c1e-9a3f-2d5b8e0c7a14").unwrap(),
+            aggregate_kwh: 123.45,
+            merkle_root: "0x1234567890abcdef".to_string(),
+            window_start: Utc::now(),
+            window_end: Utc::now(),
+            record_count: 10,
+            meter_ids: vec!["meter1".to_string()],
+            generated_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::Legacy,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
+        }
+    }
+
+    #[test]
+    fn test_seal_content_hash_matches_upload_ts() {
+        // sha256(JSON.stringify({proofId, merkleRoot, aggregateKwh, recordCount, walrusCid})) in Node.js
+        let hash = seal_content_hash(&fixed_proof(), "mock_0123456789abcdef");
+        assert_eq!(hash, "02a41e0efac62a74c46df4601c22cf7e9fa36642773a50d420553891f7a39ca3");
+
+        let mut whole_kwh = fixed_proof();
+        whole_kwh.aggregate_kwh = 120.0; // JSON.stringify renders 120, not 120.0
+        assert_eq!(seal_content_hash(&whole_kwh, "mock_0123456789abcdef"), "08168d44a9f91ffc6a81610a8f979419a2f1ec47ec2222249592ab6c18f0b328");
+    }
+
+    #[test]
+    fn test_seal_signature_roundtrip() {
+        let signer = EnclaveSigner::from_hex(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+        ).unwrap();
+        let seal_hash = seal_content_hash(&fixed_proof(), "mock_0123456789abcdef");
+
+        let seal = sign_seal(&signer, &seal_hash);
+        assert_eq!(seal.signer, "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23");
+        assert_eq!(seal.signature.len(), 132); // 0x + 65 bytes
+        assert!(verify_seal_signature(&seal));
+
+        let mut tampered = seal.clone();
+        tampered.hash = seal_content_hash(&fixed_proof(), "another_blob");
+        assert!(!verify_seal_signature(&tampered));
+    }
+
+    #[test]
+    fn test_check_seal() {
+        let signer = EnclaveSigner::from_hex(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+        ).unwrap();
+        let seal_hash = seal_content_hash(&fixed_proof(), "mock_0123456789abcdef");
+        let sealed = SealRequest {
+            proof_data: fixed_proof(),
+            metadata: HashMap::new(),
+            walrus_cid: Some("mock_0123456789abcdef".to_string()),
+            records_manifest: None,
+            seal_signature: Some(sign_seal(&signer, &seal_hash)),
+            seal_hash: Some(seal_hash),
+        };
+        assert!(check_seal(&sealed).is_ok());
+
+        // Missing any seal field is not a valid seal
+        let unsealed = [
+            SealRequest { walrus_cid: None, ..sealed.clone() },
+            SealRequest { seal_hash: None, ..sealed.clone() },
+            SealRequest { seal_signature: None, ..sealed.clone() },
+        ];
+        for request in &unsealed {
+            assert!(check_seal(request).is_err());
+        }
+        let moved = SealRequest { walrus_cid: Some("another_blob".to_string()), ..sealed.clone() };
+        assert!(check_seal(&moved).is_err());
+    }
+
+    #[test]
+    fn test_walrus_api_from_str() {
+        assert_eq!("blobs".parse::<WalrusApi>().unwrap(), WalrusApi::Blobs);
+        assert_eq!("Legacy".parse::<WalrusApi>().unwrap(), WalrusApi::Legacy);
+        assert!("v2".parse::<WalrusApi>().is_err());
+    }
+
+    #[test]
+    fn test_blob_url_per_api() {
+        let client = WalrusClient::new(
+            "https://publisher-devnet.walrus.space".to_string(),
+            "https://aggregator-devnet.walrus.space".to_string(),
+            5
+        );
+        assert_eq!(client.blob_url("abc"), "https://aggregator-devnet.walrus.space/v1/blobs/abc");
+
+        let client = client.with_api(WalrusApi::Legacy);
+        assert_eq!(client.blob_url("abc"), "https://aggregator-devnet.walrus.space/v1/abc");
+    }
+
+    #[test]
+    fn test_parse_newly_created_response() {
+        let body = r#"{
+            "newlyCreated": {
+                "blobObject": {
+                    "id": "0xe91eee8c5b6f35b9a250cfc29e30f0d9e5463a21fd8d1ddb0fc22d44db4eac50",
+                    "registeredEpoch": 34,
+                    "blobId": "M4hsZGQ1oCktdzegB6HnI6Mi28S2nqOPHxK-W7_4BUk",
+                    "size": 17,
+                    "encodingType": "RS2",
+                    "certifiedEpoch": 34,
+                    "storage": {
+                        "id": "0x4748cd83217b5ce7aa77e7f1ad6fc5f7f694e26a157381b9391ac65c47815faf",
+                        "startEpoch": 34,
+                        "endEpoch": 35,
+                        "storageSize": 66034000
+                    },
+                    "deletable": false
+                },
+                "resourceOperation": {
+                    "registerFromScratch": { "encodedLength": 66034000, "epochsAhead": 1 }
+                },
+                "cost": 132300
+            }
+        }"#;
+
+        let stored: StoredBlob = serde_json::from_str::<BlobStoreResponse>(body).unwrap().into();
+        assert_eq!(stored.blob_id, "M4hsZGQ1oCktdzegB6HnI6Mi28S2nqOPHxK-W7_4BUk");
+        assert_eq!(stored.cost, Some(132300));
+        assert_eq!(stored.end_epoch, Some(35));
+        assert!(stored.sui_object_id.is_some());
+        assert!(!stored.already_certified);
+    }
+
+    #[test]
+    fn test_parse_already_certified_response() {
+        let body = r#"{
+            "alreadyCertified": {
+                "blobId": "M4hsZGQ1oCktdzegB6HnI6Mi28S2nqOPHxK-W7_4BUk",
+                "event": {
+                    "txDigest": "4XQHFa9S324wTzYHF3vsBSwpUZuLpmwTHYMFv9nsDCSW",
+                    "eventSeq": "0"
+                },
+                "endEpoch": 35
+            }
+        }"#;
+
+        let stored: StoredBlob = serde_json::from_str::<BlobStoreResponse>(body).unwrap().into();
+        assert!(stored.already_certified);
+        assert_eq!(stored.tx_digest.as_deref(), Some("4XQHFa9S324wTzYHF3vsBSwpUZuLpmwTHYMFv9nsDCSW"));
+        assert_eq!(stored.cost, None);
+        assert_eq!(stored.end_epoch, Some(35));
+    }
+
+    #[test]
+    fn test_parse_legacy_mock_response() {
+        let body = r#"{"blobId":"mock_0123","cost":10,"event":{"txDigest":"0xabc","eventSeq":1700000000000}}"#;
+
+        let stored: StoredBlob = serde_json::from_str::<WalrusUploadResponse>(body).unwrap().into();
+        assert_eq!(stored.blob_id, "mock_0123");
+        assert_eq!(stored.cost, Some(10));
+        assert_eq!(stored.tx_digest.as_deref(), Some("0xabc"));
+    }
+
+    #[test]
+    fn test_stored_envelope_detects_encryption() {
+        let plain = SealRequest {
+            proof_data: fixed_proof(),
+            metadata: HashMap::new(),
+            walrus_cid: None,
+            records_manifest: None,
+            seal_hash: None,
+            seal_signature: None,
+        };
+        let recipient: Recipient = "auditor:031b84c5567b126440995d3ed5aaba0565d71e1834604819ff9c17f5e9d5dd078f"
+            .parse()
+            .unwrap();
+        let encrypted = envelope::encrypt_envelope(&plain, &[recipient]).unwrap();
+
+        let stored: StoredEnvelope = serde_json::from_str(&serde_json::to_string(&plain).unwrap()).unwrap();
+        assert!(matches!(stored, StoredEnvelope::Plain(_)));
+        let stored: StoredEnvelope = serde_json::from_str(&serde_json::to_string(&encrypted).unwrap()).unwrap();
+        assert!(matches!(stored, StoredEnvelope::Encrypted(_)));
+    }
+
     #[test]
     fn test_cost_estimation() {
-        let rt = tokio::runtime::Runtime::new().unwrap();
         let client = WalrusClient::new(
             "https://publisher-devnet.walrus.space".to_string(),
             "https://aggregator-devnet.walrus.space".to_string(),
             5
         );
 
-        rt.block_on(async {
-            let cost = client.estimate_cost(1_000_000).await.unwrap(); // 1 MB
-            assert!(cost > 0);
-        });
+        let estimate = client.estimate_cost(1_000_000); // 1 MB
+        assert!(estimate.total_cost > 0);
+        assert_eq!(estimate.epochs, 5);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_pricing_model() {
+        let pricing = WalrusPricing {
+            encoding_overhead: 5.0,
+            metadata_bytes: 1000,
+            unit_bytes: 1000,
+            storage_price_per_unit: 10,
+            write_price_per_unit: 3,
+        };
+
+        // 2000 bytes encode to 10_000 + 1000 metadata = 11 units
+        let estimate = pricing.estimate(2000, 4);
+        assert_eq!(estimate.encoded_bytes, 11_000);
+        assert_eq!(estimate.storage_units, 11);
+        assert_eq!(estimate.storage_cost, 11 * 10 * 4);
+        assert_eq!(estimate.write_fee, 11 * 3);
+        assert_eq!(estimate.total_cost, 440 + 33);
+
+        // A started unit is billed in full
+        assert_eq!(pricing.estimate(2001, 1).storage_units, 12);
+    }
+
+    #[test]
+    fn test_budget_check() {
+        let client = WalrusClient::new("http://localhost".to_string(), "http://localhost".to_string(), 5);
+        let one_blob = client.estimate_cost(1000).total_cost;
+
+        assert!(client.check_budget(&[1000, 1000]).is_ok()); // no budget configured
+
+        let client = client.with_pricing(WalrusPricing::default(), Some(one_blob * 2));
+        assert!(client.check_budget(&[1000, 1000]).is_ok());
+        let err = client.check_budget(&[1000, 1000, 1000]).unwrap_err();
+        assert_eq!((err.estimated, err.budget), (one_blob * 3, one_blob * 2));
+    }
+
+    #[test]
+    fn test_seal_blob_plan() {
+        let envelope = 1000 + SEAL_FIELDS_ALLOWANCE;
+        assert_eq!(seal_blob_plan(1000, &[]), vec![envelope, envelope]);
+
+        // Record chunks and their manifest come first
+        let manifest = MANIFEST_ALLOWANCE + 2 * MANIFEST_ENTRY_ALLOWANCE;
+        assert_eq!(seal_blob_plan(1000, &[500, 600]), vec![500, 600, manifest, envelope, envelope]);
+    }
+}
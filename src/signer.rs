@@ -0,0 +1,322 @@
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey, ecdsa::{RecoverableSignature, RecoveryId}};
+use sha3::{Digest, Keccak256};
+use crate::config::Config;
+
+/// Keccak-256 digest as used by Ethereum
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// EIP-191 `personal_sign` digest: keccak256("\x19Ethereum Signed Message:\n" + len + message)
+pub fn eip191_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Ethereum address of a secp256k1 public key
+pub fn public_key_to_address(public_key: &PublicKey) -> [u8; 20] {
+    let hash = keccak256(&public_key.serialize_uncompressed()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// EIP-55 mixed-case checksum encoding of an address
+pub fn to_checksum_address(address: &[u8; 20]) -> String {
+    let lower = hex::encode(address);
+    let hash = keccak256(lower.as_bytes());
+
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if c.is_ascii_alphabetic() && nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    format!("0x{}", checksummed)
+}
+
+/// secp256k1 group order `n`, big-endian
+const CURVE_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// `n / 2`, the largest `s` a canonical (low-S) signature may have
+const HALF_CURVE_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Why a secp256k1 signature was rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignatureError {
+    #[error("signature is not valid hex")]
+    InvalidHex,
+    #[error("signature must be 65 bytes (r || s || v) or 64 bytes (EIP-2098), got {0}")]
+    InvalidLength(usize),
+    #[error("invalid recovery byte {0}, expected 0, 1, 27 or 28")]
+    InvalidRecoveryId(u8),
+    #[error("r and s must be between 1 and the curve order")]
+    ScalarOutOfRange,
+    #[error("s is in the upper half of the curve order (malleable signature)")]
+    HighS,
+    #[error("no public key recovers from this signature")]
+    RecoveryFailed,
+}
+
+/// Reject zero or out-of-range scalars and high-S signatures, so every signature has one encoding
+pub fn check_scalars(r: &[u8], s: &[u8]) -> Result<(), SignatureError> {
+    let in_range = |scalar: &[u8]| scalar.iter().any(|&byte| byte != 0) && scalar < &CURVE_ORDER[..];
+    if !in_range(r) || !in_range(s) {
+        return Err(SignatureError::ScalarOutOfRange);
+    }
+    if s > &HALF_CURVE_ORDER[..] {
+        return Err(SignatureError::HighS);
+    }
+    Ok(())
+}
+
+/// Strictly parsed recoverable secp256k1 signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoverableSig {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    /// Normalized recovery ID, 0 or 1
+    pub y_parity: u8,
+}
+
+impl RecoverableSig {
+    /// Parse `r || s || v` (v in {0, 1, 27, 28}) or EIP-2098 `r || yParity·s`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignatureError> {
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        r.copy_from_slice(bytes.get(..32).ok_or(SignatureError::InvalidLength(bytes.len()))?);
+
+        let y_parity = match bytes.len() {
+            65 => {
+                s.copy_from_slice(&bytes[32..64]);
+                match bytes[64] {
+                    v @ (0 | 1) => v,
+                    v @ (27 | 28) => v - 27,
+                    v => return Err(SignatureError::InvalidRecoveryId(v)),
+                }
+            }
+            64 => {
+                // The top bit of `s` is free in low-S signatures and carries the parity
+                s.copy_from_slice(&bytes[32..64]);
+                let y_parity = s[0] >> 7;
+                s[0] &= 0x7f;
+                y_parity
+            }
+            len => return Err(SignatureError::InvalidLength(len)),
+        };
+
+        check_scalars(&r, &s)?;
+        Ok(Self { r, s, y_parity })
+    }
+
+    /// Parse a hex signature, with or without `0x`
+    pub fn from_hex(signature_hex: &str) -> Result<Self, SignatureError> {
+        let bytes = hex::decode(signature_hex.strip_prefix("0x").unwrap_or(signature_hex))
+            .map_err(|_| SignatureError::InvalidHex)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Canonical `r || s || v` with v in {27, 28}
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut bytes = [0u8; 65];
+        bytes[..32].copy_from_slice(&self.r);
+        bytes[32..64].copy_from_slice(&self.s);
+        bytes[64] = 27 + self.y_parity;
+        bytes
+    }
+
+    pub fn to_recoverable(&self) -> Result<RecoverableSignature, SignatureError> {
+        let recovery_id = RecoveryId::from_i32(self.y_parity as i32)
+            .map_err(|_| SignatureError::InvalidRecoveryId(self.y_parity))?;
+        RecoverableSignature::from_compact(&self.to_bytes()[..64], recovery_id)
+            .map_err(|_| SignatureError::ScalarOutOfRange)
+    }
+
+    /// Public key that produced this signature over `digest`
+    pub fn recover(&self, digest: &[u8; 32]) -> Result<PublicKey, SignatureError> {
+        Secp256k1::verification_only()
+            .recover_ecdsa(&Message::from_digest(*digest), &self.to_recoverable()?)
+            .map_err(|_| SignatureError::RecoveryFailed)
+    }
+}
+
+/// Recover the signer address from a strictly parsed signature (see [`RecoverableSig::from_bytes`])
+pub fn recover_address(digest: &[u8; 32], signature: &[u8]) -> Result<[u8; 20], SignatureError> {
+    let public_key = RecoverableSig::from_bytes(signature)?.recover(digest)?;
+    Ok(public_key_to_address(&public_key))
+}
+
+/// secp256k1 key the enclave signs its outputs with
+#[derive(Clone)]
+pub struct EnclaveSigner {
+    secp: Secp256k1<secp256k1::All>,
+    secret_key: SecretKey,
+    address: [u8; 20],
+}
+
+impl EnclaveSigner {
+    /// Load a signer from a hex private key (with or without `0x`)
+    pub fn from_hex(private_key_hex: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let key_bytes = hex::decode(private_key_hex.trim().trim_start_matches("0x"))?;
+        let secret_key = SecretKey::from_slice(&key_bytes)?;
+        let secp = Secp256k1::new();
+        let address = public_key_to_address(&secret_key.public_key(&secp));
+
+        Ok(Self {
+            secp,
+            secret_key,
+            address,
+        })
+    }
+
+    /// Load the configured enclave signing key, if any
+    pub fn from_config(config: &Config) -> Result<Option<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        config.enclave_signing_key
+            .as_deref()
+            .map(Self::from_hex)
+            .transpose()
+    }
+
+    /// EIP-55 checksummed address of the signing key
+    pub fn address(&self) -> String {
+        to_checksum_address(&self.address)
+    }
+
+    /// Sign a 32-byte digest, returning `r || s || v` with v in {27, 28}
+    pub fn sign_digest(&self, digest: &[u8; 32]) -> [u8; 65] {
+        let message = Message::from_digest(*digest);
+        let (recovery_id, compact) = self.secp
+            .sign_ecdsa_recoverable(&message, &self.secret_key)
+            .serialize_compact();
+
+        let mut signature = [0u8; 65];
+        signature[..64].copy_from_slice(&compact);
+        signature[64] = 27 + recovery_id.to_i32() as u8;
+        signature
+    }
+
+    /// EIP-191 `personal_sign` over raw message bytes (ethers `signMessage`), as 0x-prefixed hex
+    pub fn sign_message(&self, message: &[u8]) -> String {
+        format!("0x{}", hex::encode(self.sign_digest(&eip191_hash(message))))
+    }
+}
+
+impl std::fmt::Debug for EnclaveSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnclaveSigner")
+            .field("address", &self.address())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Key and expected signature from the web3.js `accounts.sign` documentation
+    const TEST_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[test]
+    fn test_address_derivation() {
+        let signer = EnclaveSigner::from_hex(TEST_KEY).unwrap();
+        assert_eq!(signer.address(), "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23");
+    }
+
+    #[test]
+    fn test_eip191_signature_matches_web3() {
+        let signer = EnclaveSigner::from_hex(TEST_KEY).unwrap();
+        let signature = signer.sign_message(b"Some data");
+
+        assert_eq!(
+            signature,
+            "0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c"
+        );
+    }
+
+    #[test]
+    fn test_recover_address_roundtrip() {
+        let signer = EnclaveSigner::from_hex(TEST_KEY).unwrap();
+        let digest = eip191_hash(b"GreenShare");
+        let signature = signer.sign_digest(&digest);
+
+        let recovered = recover_address(&digest, &signature).unwrap();
+        assert_eq!(to_checksum_address(&recovered), signer.address());
+    }
+
+    /// `n - s`, the high-S twin of a signature's `s`
+    fn negate_scalar(s: &[u8]) -> [u8; 32] {
+        let mut result = [0u8; 32];
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let mut diff = CURVE_ORDER[i] as i16 - s[i] as i16 - borrow;
+            borrow = if diff < 0 { diff += 256; 1 } else { 0 };
+            result[i] = diff as u8;
+        }
+        result
+    }
+
+    #[test]
+    fn test_strict_signature_parsing() {
+        let signer = EnclaveSigner::from_hex(TEST_KEY).unwrap();
+        let digest = eip191_hash(b"GreenShare");
+        let signature = signer.sign_digest(&digest);
+        let expected = signer.address();
+        let recover = |bytes: &[u8]| recover_address(&digest, bytes).map(|address| to_checksum_address(&address));
+
+        // v as 27/28 or 0/1, and the EIP-2098 compact form, all recover the same signer
+        let mut raw_v = signature;
+        raw_v[64] -= 27;
+        let mut compact = [0u8; 64];
+        compact.copy_from_slice(&signature[..64]);
+        compact[32] |= raw_v[64] << 7;
+        assert_eq!(recover(&signature).unwrap(), expected);
+        assert_eq!(recover(&raw_v).unwrap(), expected);
+        assert_eq!(recover(&compact).unwrap(), expected);
+        assert_eq!(RecoverableSig::from_bytes(&compact).unwrap().to_bytes(), signature);
+
+        // The malleable twin (n - s, flipped parity) is a valid ECDSA signature, but not canonical
+        let mut high_s = signature;
+        high_s[32..64].copy_from_slice(&negate_scalar(&signature[32..64]));
+        high_s[64] = if signature[64] == 27 { 28 } else { 27 };
+        assert_eq!(recover(&high_s), Err(SignatureError::HighS));
+
+        let mut bad_v = signature;
+        bad_v[64] = 29;
+        assert_eq!(recover(&bad_v), Err(SignatureError::InvalidRecoveryId(29)));
+
+        let mut zero_r = signature;
+        zero_r[..32].fill(0);
+        assert_eq!(recover(&zero_r), Err(SignatureError::ScalarOutOfRange));
+
+        let mut r_overflow = signature;
+        r_overflow[..32].copy_from_slice(&CURVE_ORDER);
+        assert_eq!(recover(&r_overflow), Err(SignatureError::ScalarOutOfRange));
+
+        assert_eq!(recover(&signature[..63]), Err(SignatureError::InvalidLength(63)));
+        assert_eq!(recover(&[]), Err(SignatureError::InvalidLength(0)));
+        assert_eq!(RecoverableSig::from_hex("0xzz"), Err(SignatureError::InvalidHex));
+    }
+
+    #[test]
+    fn test_invalid_key_rejected() {
+        assert!(EnclaveSigner::from_hex("0x1234").is_err());
+        assert!(EnclaveSigner::from_hex("not hex").is_err());
+    }
+}
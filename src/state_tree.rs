@@ -0,0 +1,371 @@
+//! Sparse Merkle tree of every meter's cumulative state across finalized windows, keyed by
+//! `keccak(meter_id)`. Proofs show a meter's lifetime totals or that it never reported.
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sha3::{Digest, Keccak256};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::merkle::{Node, EMPTY_NODE, NODE_TAG};
+use crate::models::{kwh_to_micro, VerifiedRecord};
+
+type StateTreeResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Leaves are `keccak(0x05 || key || value hash)`; internal nodes reuse the Merkle node tag.
+/// A subtree holding a single leaf is that leaf, an empty subtree is the zero hash.
+pub const STATE_LEAF_TAG: u8 = 0x05;
+
+/// What a meter has contributed over every finalized window
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MeterState {
+    pub lifetime_micro_kwh: u64,
+    pub readings: u64,
+    /// Timestamp and nonce of the latest reading counted
+    pub last_timestamp: i64,
+    pub last_nonce: String,
+}
+
+impl MeterState {
+    /// `keccak(lifetime || readings || last timestamp || last nonce)`, integers 8 bytes big-endian
+    pub fn value_hash(&self) -> Node {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.lifetime_micro_kwh.to_be_bytes());
+        hasher.update(self.readings.to_be_bytes());
+        hasher.update(self.last_timestamp.to_be_bytes());
+        hasher.update(self.last_nonce.as_bytes());
+        hasher.finalize().into()
+    }
+}
+
+/// A leaf as it appears in a proof: enough to rehash it without the meter's state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct StateLeaf {
+    pub key: String,
+    pub value_hash: String,
+}
+
+/// Path from the position of `key` to the root
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StateProof {
+    /// `keccak(meter_id)`
+    pub key: String,
+    /// Leaf found at the key's position: the meter itself, another meter whose key shares the
+    /// path, or none when the position is empty
+    pub leaf: Option<StateLeaf>,
+    /// Siblings bottom-up, from the leaf's depth to the root
+    pub siblings: Vec<String>,
+}
+
+pub fn state_key(meter_id: &str) -> Node {
+    Keccak256::digest(meter_id.as_bytes()).into()
+}
+
+fn bit(key: &Node, depth: usize) -> bool {
+    key[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+fn hash_leaf(key: &Node, value_hash: &Node) -> Node {
+    let mut hasher = Keccak256::new();
+    hasher.update([STATE_LEAF_TAG]);
+    hasher.update(key);
+    hasher.update(value_hash);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &Node, right: &Node) -> Node {
+    let mut hasher = Keccak256::new();
+    hasher.update([NODE_TAG]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn decode_node(node: &str) -> StateTreeResult<Node> {
+    hex::decode(node)?.try_into().map_err(|_| "State tree nodes are 32 bytes".into())
+}
+
+/// Node of the tree with its hash cached, so updates only rehash the path to the root
+#[derive(Debug, Clone, Default)]
+enum SubTree {
+    #[default]
+    Empty,
+    Leaf { key: Node, value_hash: Node, hash: Node },
+    Branch { left: Box<SubTree>, right: Box<SubTree>, hash: Node },
+}
+
+impl SubTree {
+    fn leaf(key: Node, value_hash: Node) -> Self {
+        SubTree::Leaf { key, value_hash, hash: hash_leaf(&key, &value_hash) }
+    }
+
+    fn branch(left: SubTree, right: SubTree) -> Self {
+        let hash = hash_node(&left.hash(), &right.hash());
+        SubTree::Branch { left: Box::new(left), right: Box::new(right), hash }
+    }
+
+    fn hash(&self) -> Node {
+        match self {
+            SubTree::Empty => EMPTY_NODE,
+            SubTree::Leaf { hash, .. } | SubTree::Branch { hash, .. } => *hash,
+        }
+    }
+
+    /// Set the value hash of `key` in this subtree at `depth`
+    fn insert(&mut self, key: Node, value_hash: Node, depth: usize) {
+        *self = match std::mem::take(self) {
+            SubTree::Empty => SubTree::leaf(key, value_hash),
+            SubTree::Leaf { key: other, .. } if other == key => SubTree::leaf(key, value_hash),
+            // Two leaves share this position: push both down until their keys diverge
+            SubTree::Leaf { key: other, value_hash: other_value, .. } => {
+                let mut split = SubTree::branch(SubTree::Empty, SubTree::Empty);
+                split.insert(other, other_value, depth);
+                split.insert(key, value_hash, depth);
+                split
+            }
+            SubTree::Branch { mut left, mut right, .. } => {
+                if bit(&key, depth) {
+                    right.insert(key, value_hash, depth + 1);
+                } else {
+                    left.insert(key, value_hash, depth + 1);
+                }
+                SubTree::branch(*left, *right)
+            }
+        };
+    }
+}
+
+/// Every meter that ever had a reading in a finalized window
+#[derive(Debug, Clone, Default)]
+pub struct StateTree {
+    states: BTreeMap<String, MeterState>,
+    tree: SubTree,
+}
+
+impl StateTree {
+    pub fn from_states(states: BTreeMap<String, MeterState>) -> Self {
+        let mut tree = SubTree::Empty;
+        for (meter_id, state) in &states {
+            tree.insert(state_key(meter_id), state.value_hash(), 0);
+        }
+        Self { states, tree }
+    }
+
+    pub fn states(&self) -> &BTreeMap<String, MeterState> {
+        &self.states
+    }
+
+    pub fn get(&self, meter_id: &str) -> Option<&MeterState> {
+        self.states.get(meter_id)
+    }
+
+    /// Count the readings of a finalized window
+    pub fn apply(&mut self, records: &[VerifiedRecord]) {
+        let mut updated = BTreeSet::new();
+        for verified in records {
+            let record = &verified.record;
+            let state = self.states.entry(record.meter_id.clone()).or_default();
+            state.lifetime_micro_kwh = state.lifetime_micro_kwh.saturating_add(kwh_to_micro(record.kwh_delta));
+            state.readings += 1;
+            if record.timestamp >= state.last_timestamp {
+                state.last_timestamp = record.timestamp;
+                state.last_nonce = record.nonce.clone();
+            }
+            updated.insert(record.meter_id.as_str());
+        }
+        for meter_id in updated {
+            self.tree.insert(state_key(meter_id), self.states[meter_id].value_hash(), 0);
+        }
+    }
+
+    pub fn root(&self) -> String {
+        hex::encode(self.tree.hash())
+    }
+
+    /// Inclusion proof when the meter has state, non-inclusion proof otherwise
+    pub fn prove(&self, meter_id: &str) -> StateProof {
+        let key = state_key(meter_id);
+        let mut position = &self.tree;
+        let mut siblings = Vec::new();
+
+        // Descend along the key until its position holds at most one leaf
+        let mut depth = 0;
+        while let SubTree::Branch { left, right, .. } = position {
+            let (next, sibling) = if bit(&key, depth) { (right, left) } else { (left, right) };
+            siblings.push(hex::encode(sibling.hash()));
+            position = next;
+            depth += 1;
+        }
+        siblings.reverse();
+
+        StateProof {
+            key: hex::encode(key),
+            leaf: match position {
+                SubTree::Leaf { key, value_hash, .. } => Some(StateLeaf {
+                    key: hex::encode(key),
+                    value_hash: hex::encode(value_hash),
+                }),
+                _ => None,
+            },
+            siblings,
+        }
+    }
+}
+
+impl StateProof {
+    /// Whether `meter_id` has exactly `state` under `root`
+    pub fn verify_inclusion(&self, meter_id: &str, state: &MeterState, root: &str) -> StateTreeResult<bool> {
+        let key = state_key(meter_id);
+        match &self.leaf {
+            Some(leaf) if decode_node(&leaf.key)? == key && decode_node(&leaf.value_hash)? == state.value_hash() => {
+                self.climb(&key, hash_leaf(&key, &state.value_hash()), root)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Whether `meter_id` has no state under `root`, i.e. never had a reading in a finalized window
+    pub fn verify_non_inclusion(&self, meter_id: &str, root: &str) -> StateTreeResult<bool> {
+        let key = state_key(meter_id);
+        let start = match &self.leaf {
+            None => EMPTY_NODE,
+            Some(leaf) => {
+                // The position must be taken by another meter whose key follows the same path
+                let other = decode_node(&leaf.key)?;
+                if other == key || (0..self.siblings.len()).any(|depth| bit(&other, depth) != bit(&key, depth)) {
+                    return Ok(false);
+                }
+                hash_leaf(&other, &decode_node(&leaf.value_hash)?)
+            }
+        };
+        self.climb(&key, start, root)
+    }
+
+    fn climb(&self, key: &Node, mut node: Node, root: &str) -> StateTreeResult<bool> {
+        if self.key != hex::encode(key) || self.siblings.len() > 256 {
+            return Ok(false);
+        }
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            let sibling = decode_node(sibling)?;
+            let depth = self.siblings.len() - 1 - height;
+            node = if bit(key, depth) { hash_node(&sibling, &node) } else { hash_node(&node, &sibling) };
+        }
+        Ok(hex::encode(node) == root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MeterRecord, RecordEncoding, SignatureScheme};
+    use chrono::Utc;
+
+    fn reading(meter_id: &str, timestamp: i64, kwh_delta: f64) -> VerifiedRecord {
+        VerifiedRecord {
+            record: MeterRecord {
+                meter_id: meter_id.to_string(),
+                timestamp,
+                kwh_delta,
+                nonce: format!("{}-{}", meter_id, timestamp),
+                encoding: RecordEncoding::V1,
+            },
+            signature: "0x00".to_string(),
+            scheme: SignatureScheme::Legacy,
+            signer: None,
+            verification_timestamp: Utc::now(),
+            record_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_lifetime_state_across_windows() {
+        let mut tree = StateTree::default();
+        assert_eq!(tree.root(), hex::encode(EMPTY_NODE));
+        assert!(tree.prove("meter_001").verify_non_inclusion("meter_001", &tree.root()).unwrap());
+
+        tree.apply(&[reading("meter_001", 2_000, 1.5), reading("meter_001", 1_000, 0.25)]);
+        let first_root = tree.root();
+        tree.apply(&[reading("meter_001", 3_000, 0.5), reading("meter_002", 3_000, 2.0)]);
+        let root = tree.root();
+        assert_ne!(root, first_root);
+
+        let state = tree.get("meter_001").unwrap().clone();
+        assert_eq!(state.lifetime_micro_kwh, 2_250_000);
+        assert_eq!(state.readings, 3);
+        assert_eq!((state.last_timestamp, state.last_nonce.as_str()), (3_000, "meter_001-3000"));
+
+        let proof = tree.prove("meter_001");
+        assert!(proof.verify_inclusion("meter_001", &state, &root).unwrap());
+        assert!(!proof.verify_inclusion("meter_001", &state, &first_root).unwrap());
+        assert!(!proof.verify_inclusion("meter_001", &MeterState { lifetime_micro_kwh: 2_000_000, ..state.clone() }, &root).unwrap());
+        assert!(!proof.verify_non_inclusion("meter_001", &root).unwrap());
+        assert!(!proof.verify_inclusion("meter_002", &state, &root).unwrap());
+
+        // Reloading from the stored states gives the same root
+        assert_eq!(StateTree::from_states(tree.states().clone()).root(), root);
+    }
+
+    /// Root rebuilt from scratch over the leaves sorted by key
+    fn reference_root(states: &BTreeMap<String, MeterState>) -> String {
+        fn subtree(leaves: &[(Node, Node)], depth: usize) -> Node {
+            match leaves {
+                [] => EMPTY_NODE,
+                [(key, value_hash)] => hash_leaf(key, value_hash),
+                _ => {
+                    let (left, right) = leaves.split_at(leaves.partition_point(|(key, _)| !bit(key, depth)));
+                    hash_node(&subtree(left, depth + 1), &subtree(right, depth + 1))
+                }
+            }
+        }
+        let mut leaves: Vec<(Node, Node)> = states.iter()
+            .map(|(meter_id, state)| (state_key(meter_id), state.value_hash()))
+            .collect();
+        leaves.sort_unstable_by_key(|(key, _)| *key);
+        hex::encode(subtree(&leaves, 0))
+    }
+
+    #[test]
+    fn test_cached_hashes_match_rebuild() {
+        let mut tree = StateTree::default();
+        for round in 0..20 {
+            let records: Vec<VerifiedRecord> = (0..round * 3)
+                .map(|i| reading(&format!("meter_{:03}", (i * 7 + round) % 50), 1_000 + round as i64, 0.5))
+                .collect();
+            tree.apply(&records);
+            assert_eq!(tree.root(), reference_root(tree.states()), "round {}", round);
+        }
+        assert_eq!(StateTree::from_states(tree.states().clone()).root(), tree.root());
+    }
+
+    #[test]
+    fn test_non_inclusion() {
+        let mut tree = StateTree::default();
+        let meters: Vec<String> = (0..40).map(|i| format!("meter_{:03}", i)).collect();
+        let records: Vec<VerifiedRecord> = meters.iter().map(|meter_id| reading(meter_id, 1_000, 1.0)).collect();
+        tree.apply(&records);
+        let root = tree.root();
+
+        for meter_id in &meters {
+            let proof = tree.prove(meter_id);
+            assert!(proof.verify_inclusion(meter_id, tree.get(meter_id).unwrap(), &root).unwrap());
+        }
+
+        // Inactive meters end either at an empty position or at another meter's leaf
+        let mut ends = (false, false);
+        for i in 40..200 {
+            let meter_id = format!("meter_{:03}", i);
+            let proof = tree.prove(&meter_id);
+            assert!(proof.verify_non_inclusion(&meter_id, &root).unwrap(), "{}", meter_id);
+            assert!(!proof.verify_inclusion(&meter_id, &MeterState::default(), &root).unwrap());
+            if proof.leaf.is_some() { ends.1 = true } else { ends.0 = true }
+        }
+        assert_eq!(ends, (true, true));
+
+        // An active meter cannot pass off another meter's leaf or an empty position as its own
+        let mut forged = tree.prove("meter_007");
+        assert!(!forged.verify_non_inclusion("meter_007", &root).unwrap());
+        forged.leaf = None;
+        assert!(!forged.verify_non_inclusion("meter_007", &root).unwrap());
+        let borrowed = StateProof { key: hex::encode(state_key("meter_007")), ..tree.prove("meter_008") };
+        assert!(!borrowed.verify_non_inclusion("meter_007", &root).unwrap());
+    }
+}
@@ -0,0 +1,311 @@
+//! Unsigned Sui transactions for the GreenShare Move package
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use uuid::Uuid;
+
+use crate::crypto;
+use crate::models::ProofData;
+
+/// `0x6`, the shared system clock
+const CLOCK_OBJECT_ID: &str = "0x6";
+const CLOCK_INITIAL_SHARED_VERSION: u64 = 1;
+
+/// On-chain objects the mint call needs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SuiMintConfig {
+    pub package_id: Option<String>,
+    pub treasury_cap_id: Option<String>,
+    /// Current version of the (owned) treasury cap; changes with every mint
+    pub treasury_cap_version: Option<u64>,
+    /// Current base58 object digest of the treasury cap
+    pub treasury_cap_digest: Option<String>,
+    pub quota_ledger_id: Option<String>,
+    pub quota_ledger_initial_version: Option<u64>,
+}
+
+/// 32-byte Sui object ID or address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuiAddress([u8; 32]);
+
+impl SuiAddress {
+    /// Parse `0x`-prefixed hex, left-padding short forms such as `0x6`
+    pub fn from_hex(value: &str) -> Result<Self, String> {
+        let digits = value.strip_prefix("0x").unwrap_or(value);
+        if digits.is_empty() || digits.len() > 64 {
+            return Err(format!("Invalid Sui address '{}'", value));
+        }
+
+        let bytes = hex::decode(format!("{:0>64}", digits))
+            .map_err(|_| format!("Invalid Sui address '{}'", value))?;
+        let mut address = [0u8; 32];
+        address.copy_from_slice(&bytes);
+        Ok(SuiAddress(address))
+    }
+
+    pub fn to_hex(self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+}
+
+// BCS mirrors of the Sui transaction types (variant order matters)
+
+#[derive(Debug, Serialize)]
+enum TransactionKind {
+    ProgrammableTransaction(ProgrammableTransaction),
+}
+
+#[derive(Debug, Serialize)]
+struct ProgrammableTransaction {
+    inputs: Vec<CallArg>,
+    commands: Vec<Command>,
+}
+
+#[derive(Debug, Serialize)]
+enum CallArg {
+    Pure(Vec<u8>),
+    Object(ObjectArg),
+}
+
+#[derive(Debug, Serialize)]
+enum ObjectArg {
+    ImmOrOwnedObject((SuiAddress, u64, Vec<u8>)),
+    SharedObject {
+        id: SuiAddress,
+        initial_shared_version: u64,
+        mutable: bool,
+    },
+}
+
+#[derive(Debug, Serialize)]
+enum Command {
+    MoveCall(ProgrammableMoveCall),
+}
+
+#[derive(Debug, Serialize)]
+struct ProgrammableMoveCall {
+    package: SuiAddress,
+    module: String,
+    function: String,
+    type_arguments: Vec<TypeTag>,
+    arguments: Vec<Argument>,
+}
+
+/// Never constructed: `mint_from_proof` takes no type arguments
+#[derive(Debug, Serialize)]
+enum TypeTag {}
+
+#[derive(Debug, Serialize)]
+#[allow(dead_code)]
+enum Argument {
+    GasCoin,
+    Input(u16),
+}
+
+/// Unsigned `sKWH::mint_from_proof` call for a proof
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MintTransaction {
+    pub proof_id: Uuid,
+    pub target: String,
+    pub proof_hash: String,
+    pub window_id: String,
+    /// Amount in micro-sKWH (6 decimals, 1 sKWH = 1 kWh)
+    pub amount: u64,
+    pub recipient: String,
+    /// Base64 BCS `TransactionKind`; set sender and gas (e.g. `Transaction.fromKind`) and sign
+    pub tx_kind_bytes: String,
+}
+
+/// Resolved object references for building mint transactions
+#[derive(Debug, Clone)]
+pub struct MintTxBuilder {
+    package_id: SuiAddress,
+    treasury_cap: (SuiAddress, u64, Vec<u8>),
+    quota_ledger: (SuiAddress, u64),
+}
+
+impl MintTxBuilder {
+    /// Validate the configured package and object IDs
+    pub fn from_config(config: &SuiMintConfig) -> Result<Self, String> {
+        fn required<'a, T>(value: &'a Option<T>, name: &str) -> Result<&'a T, String> {
+            value.as_ref().ok_or_else(|| format!("{} is not configured", name))
+        }
+
+        let treasury_cap_id = SuiAddress::from_hex(required(&config.treasury_cap_id, "SUI_TREASURY_CAP_ID")?)?;
+        let treasury_cap_digest = parse_digest(required(&config.treasury_cap_digest, "SUI_TREASURY_CAP_DIGEST")?)?;
+
+        Ok(Self {
+            package_id: SuiAddress::from_hex(required(&config.package_id, "SUI_PACKAGE_ID")?)?,
+            treasury_cap: (
+                treasury_cap_id,
+                *required(&config.treasury_cap_version, "SUI_TREASURY_CAP_VERSION")?,
+                treasury_cap_digest,
+            ),
+            quota_ledger: (
+                SuiAddress::from_hex(required(&config.quota_ledger_id, "SUI_QUOTA_LEDGER_ID")?)?,
+                *required(&config.quota_ledger_initial_version, "SUI_QUOTA_LEDGER_INITIAL_VERSION")?,
+            ),
+        })
+    }
+
+    /// Use a fresher treasury cap reference than the configured one
+    pub fn with_treasury_cap_ref(mut self, version: u64, digest: &str) -> Result<Self, String> {
+        self.treasury_cap = (self.treasury_cap.0, version, parse_digest(digest)?);
+        Ok(self)
+    }
+
+    /// Build the unsigned mint transaction for a proof
+    pub fn build(&self, proof: &ProofData, recipient: &str) -> Result<MintTransaction, String> {
+        let recipient = SuiAddress::from_hex(recipient)?;
+        let proof_hash = crypto::proof_hash(proof);
+        let window_id = proof.window_id();
+        let amount = proof.aggregate_micro_kwh();
+        if amount == 0 {
+            return Err("Proof aggregate rounds to zero micro-sKWH".to_string());
+        }
+
+        let pure = |bytes: Result<Vec<u8>, bcs::Error>| bytes.map(CallArg::Pure).map_err(|e| e.to_string());
+        let inputs = vec![
+            CallArg::Object(ObjectArg::ImmOrOwnedObject(self.treasury_cap.clone())),
+            CallArg::Object(ObjectArg::SharedObject {
+                id: self.quota_ledger.0,
+                initial_shared_version: self.quota_ledger.1,
+                mutable: true,
+            }),
+            pure(bcs::to_bytes(&proof_hash))?,
+            pure(bcs::to_bytes(&window_id))?,
+            pure(bcs::to_bytes(&amount))?,
+            pure(bcs::to_bytes(&recipient))?,
+            CallArg::Object(ObjectArg::SharedObject {
+                id: SuiAddress::from_hex(CLOCK_OBJECT_ID)?,
+                initial_shared_version: CLOCK_INITIAL_SHARED_VERSION,
+                mutable: false,
+            }),
+        ];
+
+        let call = ProgrammableMoveCall {
+            package: self.package_id,
+            module: "sKWH".to_string(),
+            function: "mint_from_proof".to_string(),
+            type_arguments: Vec::new(),
+            arguments: (0..inputs.len() as u16).map(Argument::Input).collect(),
+        };
+
+        let kind = TransactionKind::ProgrammableTransaction(ProgrammableTransaction {
+            inputs,
+            commands: vec![Command::MoveCall(call)],
+        });
+        let tx_kind_bytes = bcs::to_bytes(&kind).map_err(|e| e.to_string())?;
+
+        Ok(MintTransaction {
+            proof_id: proof.proof_id,
+            target: format!("{}::sKWH::mint_from_proof", self.package_id.to_hex()),
+            proof_hash,
+            window_id,
+            amount,
+            recipient: recipient.to_hex(),
+            tx_kind_bytes: BASE64.encode(tx_kind_bytes),
+        })
+    }
+}
+
+fn parse_digest(digest: &str) -> Result<Vec<u8>, String> {
+    let bytes = bs58::decode(digest)
+        .into_vec()
+        .map_err(|_| format!("Invalid object digest '{}'", digest))?;
+    if bytes.len() != 32 {
+        return Err(format!("Invalid object digest '{}'", digest));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HashScheme;
+    use chrono::{TimeZone, Utc};
+
+    fn config() -> SuiMintConfig {
+        SuiMintConfig {
+            package_id: Some("0x2a".to_string()),
+            treasury_cap_id: Some("0x1234".to_string()),
+            treasury_cap_version: Some(7),
+            treasury_cap_digest: Some(bs58::encode([9u8; 32]).into_string()),
+            quota_ledger_id: Some("0x5678".to_string()),
+            quota_ledger_initial_version: Some(3),
+        }
+    }
+
+    fn proof() -> ProofData {
+        ProofData {
+            proof_id: Uuid::parse_str("6f1c2a9e-4b7d-4c1e-9a3f-2d5b8e0c7a14").unwrap(),
+            aggregate_kwh: 123.45,
+            merkle_root: "0x1234567890abcdef".to_string(),
+            window_start: Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap(),
+            window_end: Utc.with_ymd_and_hms(2024, 1, 1, 11, 0, 0).unwrap(),
+            record_count: 10,
+            meter_ids: vec!["meter_001".to_string()],
+            generated_at: Utc::now(),
+            version: "1.0.0".to_string(),
+            hash_scheme: HashScheme::Legacy,
+            sum_root: None,
+            state_root: None,
+            mmr: None,
+        }
+    }
+
+    #[test]
+    fn test_address_parsing() {
+        assert_eq!(SuiAddress::from_hex("0x6").unwrap().to_hex(), format!("0x{:0>64}", "6"));
+        assert!(SuiAddress::from_hex("0x").is_err());
+        assert!(SuiAddress::from_hex(&format!("0x{}", "1".repeat(65))).is_err());
+        assert!(SuiAddress::from_hex("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_mint_call_arguments() {
+        let tx = MintTxBuilder::from_config(&config()).unwrap().build(&proof(), "0xabc").unwrap();
+
+        assert_eq!(tx.window_id, "2024-01-01T10:00:00Z/2024-01-01T11:00:00Z");
+        assert_eq!(tx.amount, 123_450_000);
+        assert_eq!(tx.proof_hash, crypto::proof_hash(&proof()));
+        assert!(tx.target.ends_with("::sKWH::mint_from_proof"));
+    }
+
+    #[test]
+    fn test_transaction_kind_layout() {
+        let tx = MintTxBuilder::from_config(&config()).unwrap().build(&proof(), "0xabc").unwrap();
+        let bytes = BASE64.decode(&tx.tx_kind_bytes).unwrap();
+
+        // ProgrammableTransaction, 7 inputs, first an owned object (Object, ImmOrOwned)
+        assert_eq!(&bytes[..4], &[0, 7, 1, 0]);
+        let cap_id = SuiAddress::from_hex("0x1234").unwrap();
+        assert_eq!(&bytes[4..36], &cap_id.0);
+        assert_eq!(&bytes[36..44], &7u64.to_le_bytes());
+        assert_eq!(bytes[44], 32); // digest length prefix
+        assert_eq!(&bytes[45..77], &[9u8; 32]);
+
+        // Then the mutable shared quota ledger
+        assert_eq!(&bytes[77..79], &[1, 1]);
+        assert_eq!(&bytes[79..111], &SuiAddress::from_hex("0x5678").unwrap().0);
+        assert_eq!(&bytes[111..119], &3u64.to_le_bytes());
+        assert_eq!(bytes[119], 1);
+
+        // Ends with one MoveCall using inputs 0..7 in order
+        let tail: Vec<u8> = [7u8].into_iter()
+            .chain((0u16..7).flat_map(|i| [1u8, i as u8, 0]))
+            .collect();
+        assert!(bytes.ends_with(&tail));
+    }
+
+    #[test]
+    fn test_missing_config_is_reported() {
+        let mut incomplete = config();
+        incomplete.quota_ledger_id = None;
+
+        let err = MintTxBuilder::from_config(&incomplete).unwrap_err();
+        assert!(err.contains("SUI_QUOTA_LEDGER_ID"));
+    }
+}
@@ -0,0 +1,200 @@
+//! Merkle sum tree: every node commits to its hash and the micro-kWh beneath it, so the
+//! root commits to the aggregate and an inclusion proof shows a reading was counted in it
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sha3::{Digest, Keccak256};
+
+use crate::models::{kwh_to_micro, VerifiedRecord};
+
+type SumTreeResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// `keccak(0x02 || record hash || sum)` leaves and
+/// `keccak(0x03 || left hash || left sum || right hash || right sum)` nodes, sums as u64 big-endian
+pub const SUM_LEAF_TAG: u8 = 0x02;
+pub const SUM_NODE_TAG: u8 = 0x03;
+
+/// Node hash and the micro-kWh of every reading beneath it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SumNode {
+    pub hash: String,
+    pub sum: u64,
+}
+
+impl SumNode {
+    /// Sibling of the last node of an odd level; contributes nothing to the total
+    fn empty() -> Self {
+        Self { hash: hex::encode([0u8; 32]), sum: 0 }
+    }
+
+    fn leaf(record_hash: &str, micro_kwh: u64) -> SumTreeResult<Self> {
+        let mut hasher = Keccak256::new();
+        hasher.update([SUM_LEAF_TAG]);
+        hasher.update(hex::decode(record_hash)?);
+        hasher.update(micro_kwh.to_be_bytes());
+        Ok(Self { hash: hex::encode(hasher.finalize()), sum: micro_kwh })
+    }
+
+    fn parent(left: &SumNode, right: &SumNode) -> SumTreeResult<Self> {
+        let sum = left.sum.checked_add(right.sum).ok_or("Merkle sum overflows u64")?;
+
+        let mut hasher = Keccak256::new();
+        hasher.update([SUM_NODE_TAG]);
+        hasher.update(hex::decode(&left.hash)?);
+        hasher.update(left.sum.to_be_bytes());
+        hasher.update(hex::decode(&right.hash)?);
+        hasher.update(right.sum.to_be_bytes());
+        Ok(Self { hash: hex::encode(hasher.finalize()), sum })
+    }
+}
+
+/// Root of a sum tree: commits to the leaves and to their total
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SumRoot {
+    pub hash: String,
+    pub total_micro_kwh: u64,
+}
+
+/// Path from one reading to the sum root
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SumProof {
+    pub leaf_index: usize,
+    pub record_hash: String,
+    pub micro_kwh: u64,
+    /// Siblings bottom-up, padding included
+    pub siblings: Vec<SumNode>,
+}
+
+impl SumProof {
+    /// Whether the reading is a leaf of `root` and its micro-kWh are part of the root total
+    pub fn verify(&self, root: &SumRoot) -> SumTreeResult<bool> {
+        let mut node = SumNode::leaf(&self.record_hash, self.micro_kwh)?;
+        let mut index = self.leaf_index;
+
+        for sibling in &self.siblings {
+            node = if index & 1 == 0 {
+                SumNode::parent(&node, sibling)?
+            } else {
+                SumNode::parent(sibling, &node)?
+            };
+            index /= 2;
+        }
+
+        Ok(index == 0 && node.hash == root.hash && node.sum == root.total_micro_kwh)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MerkleSumTree {
+    record_hashes: Vec<String>,
+    levels: Vec<Vec<SumNode>>,
+}
+
+impl MerkleSumTree {
+    /// Build from `(record hash, micro-kWh)` leaves, positionally like Merkle v2
+    pub fn new(leaves: &[(String, u64)]) -> SumTreeResult<Self> {
+        if leaves.is_empty() {
+            return Err("Cannot build Merkle sum tree from empty leaf list".into());
+        }
+
+        let mut level = leaves.iter()
+            .map(|(record_hash, micro_kwh)| SumNode::leaf(record_hash, *micro_kwh))
+            .collect::<SumTreeResult<Vec<_>>>()?;
+        let mut levels = Vec::new();
+
+        while level.len() > 1 {
+            let next_level = level.chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => SumNode::parent(left, right),
+                    [left] => SumNode::parent(left, &SumNode::empty()),
+                    _ => unreachable!(),
+                })
+                .collect::<SumTreeResult<Vec<_>>>()?;
+            levels.push(level);
+            level = next_level;
+        }
+        levels.push(level);
+
+        let record_hashes = leaves.iter().map(|(record_hash, _)| record_hash.clone()).collect();
+        Ok(Self { record_hashes, levels })
+    }
+
+    /// Sum tree of verified records, each counted with its rounded micro-kWh
+    pub fn from_records(records: &[VerifiedRecord]) -> SumTreeResult<Self> {
+        let leaves: Vec<(String, u64)> = records.iter()
+            .map(|r| (r.record_hash.clone(), kwh_to_micro(r.record.kwh_delta)))
+            .collect();
+        Self::new(&leaves)
+    }
+
+    pub fn root(&self) -> SumRoot {
+        let root = &self.levels[self.levels.len() - 1][0];
+        SumRoot { hash: root.hash.clone(), total_micro_kwh: root.sum }
+    }
+
+    pub fn generate_proof(&self, leaf_index: usize) -> SumTreeResult<SumProof> {
+        let leaf = self.levels[0].get(leaf_index).ok_or("Leaf index out of bounds")?;
+        let mut siblings = Vec::new();
+        let mut index = leaf_index;
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            siblings.push(level.get(index ^ 1).cloned().unwrap_or_else(SumNode::empty));
+            index /= 2;
+        }
+
+        Ok(SumProof {
+            leaf_index,
+            record_hash: self.record_hashes[leaf_index].clone(),
+            micro_kwh: leaf.sum,
+            siblings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(sums: &[u64]) -> Vec<(String, u64)> {
+        sums.iter().enumerate()
+            .map(|(i, sum)| (hex::encode(Keccak256::digest([i as u8])), *sum))
+            .collect()
+    }
+
+    #[test]
+    fn test_root_commits_to_total() {
+        let tree = MerkleSumTree::new(&leaves(&[1_250_000, 500_000, 2_000_000])).unwrap();
+        let root = tree.root();
+        assert_eq!(root.total_micro_kwh, 3_750_000);
+
+        for index in 0..3 {
+            let proof = tree.generate_proof(index).unwrap();
+            assert_eq!(proof.siblings.len(), 2);
+            assert!(proof.verify(&root).unwrap());
+        }
+
+        // Neither the reading nor the published total can be changed alone
+        let proof = tree.generate_proof(1).unwrap();
+        assert!(!SumProof { micro_kwh: 400_000, ..proof.clone() }.verify(&root).unwrap());
+        assert!(!proof.verify(&SumRoot { total_micro_kwh: 3_650_000, ..root.clone() }).unwrap());
+        assert!(!SumProof { leaf_index: 0, ..proof }.verify(&root).unwrap());
+
+        // Moving value between leaves changes the root even when the total is unchanged
+        let shifted = MerkleSumTree::new(&leaves(&[1_000_000, 750_000, 2_000_000])).unwrap();
+        assert_eq!(shifted.root().total_micro_kwh, root.total_micro_kwh);
+        assert_ne!(shifted.root().hash, root.hash);
+    }
+
+    #[test]
+    fn test_single_leaf_and_overflow() {
+        let tree = MerkleSumTree::new(&leaves(&[42])).unwrap();
+        let proof = tree.generate_proof(0).unwrap();
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&tree.root()).unwrap());
+        assert!(!proof.verify(&SumRoot { total_micro_kwh: 43, ..tree.root() }).unwrap());
+
+        assert!(MerkleSumTree::new(&leaves(&[u64::MAX, 1])).is_err());
+        assert!(MerkleSumTree::new(&[]).is_err());
+        assert!(tree.generate_proof(1).is_err());
+    }
+}
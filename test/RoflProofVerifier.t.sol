@@ -0,0 +1,71 @@
+// FILE: test/RoflProofVerifier.t.sol
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "forge-std/Test.sol";
+import "../contracts/RoflProofVerifier.sol";
+
+contract RoflProofVerifierTest is Test {
+    RoflProofVerifier public verifier;
+
+    address admin = makeAddr("admin");
+
+    // Shared with `evm::tests` in the enclave
+    uint256 constant ENCLAVE_KEY = 0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318;
+    uint256 constant CHAIN_ID = 48899;
+    address constant VERIFIER_ADDRESS = 0x5615dEB798BB3E4dFa0139dFa1b3D433Cc23b72f;
+    bytes32 constant DIGEST = 0x5703795eeab3e04654a4a3487adb41554c3d12057a7f51a0070f1484e2d72808;
+    bytes constant SIGNATURE =
+        hex"92d15b75a415d85055d71e6419c28773a99993eb54db5605b42e0a8c7e2d51ae00e74d45153ec7d0514fc346ccdc7476ee930e32a6548ed47be212d9c21ac5a21b";
+
+    function setUp() public {
+        vm.chainId(CHAIN_ID);
+        verifier = new RoflProofVerifier(admin, vm.addr(ENCLAVE_KEY));
+    }
+
+    function _proof() internal pure returns (RoflProofVerifier.RoflProof memory) {
+        return RoflProofVerifier.RoflProof({
+            proofId: bytes16(0x6f1c2a9e4b7d4c1e9a3f2d5b8e0c7a14),
+            merkleRoot: bytes32(0x1111111111111111111111111111111111111111111111111111111111111111),
+            aggregateMicroKwh: 123_450_000,
+            windowStart: 1704103200, // 2024-01-01T10:00:00Z
+            windowEnd: 1704106800,
+            recordCount: 10,
+            sumRoot: bytes32(0x2222222222222222222222222222222222222222222222222222222222222222),
+            hashScheme: 2
+        });
+    }
+
+    function testDigestMatchesEnclave() public {
+        assertEq(address(verifier), VERIFIER_ADDRESS);
+        assertEq(verifier.hashProof(_proof()), DIGEST);
+    }
+
+    function testEnclaveSignatureVerifies() public {
+        assertEq(verifier.recoverSigner(_proof(), SIGNATURE), vm.addr(ENCLAVE_KEY));
+        assertTrue(verifier.verifyProof(_proof(), SIGNATURE));
+        assertTrue(verifier.verifyEncodedProof(abi.encode(_proof()), SIGNATURE));
+    }
+
+    function testTamperedProofIsRejected() public {
+        RoflProofVerifier.RoflProof memory proof = _proof();
+        proof.aggregateMicroKwh += 1;
+        assertFalse(verifier.verifyProof(proof, SIGNATURE));
+    }
+
+    function testSumRootAndSchemeAreSigned() public {
+        RoflProofVerifier.RoflProof memory proof = _proof();
+        proof.sumRoot = bytes32(0);
+        assertFalse(verifier.verifyProof(proof, SIGNATURE));
+
+        proof = _proof();
+        proof.hashScheme = 1;
+        assertFalse(verifier.verifyProof(proof, SIGNATURE));
+    }
+
+    function testUntrustedEnclaveIsRejected() public {
+        vm.prank(admin);
+        verifier.setEnclaveTrust(vm.addr(ENCLAVE_KEY), false);
+        assertFalse(verifier.verifyProof(_proof(), SIGNATURE));
+    }
+}
@@ -1,13 +1,26 @@
 // FILE: tests/crypto.test.ts
 import { describe, it, expect, beforeEach } from 'vitest';
+import { ethers } from 'ethers';
+import meterRecordVectors from './vectors/meter-record-v1.json';
+import merkleV2Vectors from './vectors/merkle-v2.json';
+import meterSignatureVectors from './vectors/meter-signature-v1.json';
 import {
   generateKeyPair,
   importPrivateKeyFromPEM,
   generateNonce,
   createMessageHash,
+  canonicalize,
+  canonicalMeterRecord,
   signMeterRecord,
   verifySignature,
   createSignedMeterData,
+  signMeterRecordEip191,
+  signMeterRecordEip712,
+  meterRecordTypedData,
+  METER_EIP712_DOMAIN,
+  METER_EIP712_TYPES,
+  merkleRootV2,
+  verifyMerkleProofV2,
   type MeterRecord
 } from '../src/crypto';
 
@@ -128,6 +141,41 @@ ${Buffer.from(testPrivateKey, 'hex').toString('base64')}
     });
   });
 
+  describe('Canonical Encoding', () => {
+    it('should match the shared test vectors', () => {
+      for (const vector of meterRecordVectors) {
+        expect(canonicalMeterRecord(vector.record), vector.name).toBe(vector.canonical);
+        expect(createMessageHash(vector.record), vector.name).toBe(vector.sha256);
+      }
+    });
+
+    it('should sort keys by UTF-16 code units', () => {
+      expect(canonicalize({ b: [1.0, 'x', null], '\ufb01': true, '\u{1f600}': false, a: { d: 2, c: 1e-7 } }))
+        .toBe('{"a":{"c":1e-7,"d":2},"b":[1,"x",null],"\u{1f600}":false,"\ufb01":true}');
+    });
+
+    it('should not depend on key insertion order', () => {
+      const reordered = { nonce: testRecord.nonce, kwh_delta: testRecord.kwh_delta, timestamp: testRecord.timestamp, meter_id: testRecord.meter_id };
+      expect(createMessageHash(reordered)).toBe(createMessageHash(testRecord));
+    });
+  });
+
+  describe('Merkle v2', () => {
+    it('should match the shared test vectors', () => {
+      for (const vector of merkleV2Vectors) {
+        expect(merkleRootV2(vector.leaves), vector.name).toBe(vector.root);
+        vector.proofs.forEach((proof, index) => {
+          expect(verifyMerkleProofV2(vector.leaves[index], proof, vector.root, index), `${vector.name} leaf ${index}`).toBe(true);
+        });
+      }
+    });
+
+    it('should not let a duplicated last leaf share the root', () => {
+      const [a, b, c] = merkleV2Vectors.find(vector => vector.leaves.length === 3)!.leaves;
+      expect(merkleRootV2([a, b, c])).not.toBe(merkleRootV2([a, b, c, c]));
+    });
+  });
+
   describe('Digital Signatures', () => {
     it('should create valid signatures', () => {
       const signature = signMeterRecord(testRecord, testPrivateKey);
@@ -145,6 +193,20 @@ ${Buffer.from(testPrivateKey, 'hex').toString('base64')}
       expect(sig1).not.toBe(sig2);
     });
 
+    it('should match the shared signature vectors', () => {
+      for (const vector of meterSignatureVectors) {
+        expect(signMeterRecord(vector.record as MeterRecord, vector.private_key), vector.name).toBe(vector.signature);
+      }
+    });
+
+    it('should produce low-S signatures', () => {
+      const halfOrder = BigInt('0x7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0');
+      for (let i = 0; i < 16; i++) {
+        const signature = signMeterRecord({ ...testRecord, nonce: `low_s_${i}` }, testPrivateKey);
+        expect(BigInt(`0x${signature.slice(66, 130)}`) <= halfOrder).toBe(true);
+      }
+    });
+
     it('should verify valid signatures', () => {
       const signature = signMeterRecord(testRecord, testPrivateKey);
       const isValid = verifySignature(testRecord, signature, testPublicKey);
@@ -182,7 +244,8 @@ ${Buffer.from(testPrivateKey, 'hex').toString('base64')}
       
       expect(signedData.record).toMatchObject({
         meter_id: 'test_meter',
-        kwh_delta: 2.5
+        kwh_delta: 2.5,
+        encoding: 'greenshare.meter-record.v1'
       });
       expect(signedData.record.timestamp).toBeTypeOf('number');
       expect(signedData.record.nonce).toMatch(/^[0-9a-f]{32}$/i);
@@ -210,6 +273,30 @@ ${Buffer.from(testPrivateKey, 'hex').toString('base64')}
     });
   });
 
+  describe('Ethereum Signatures', () => {
+    it('should sign records with personal_sign', async () => {
+      const wallet = ethers.Wallet.createRandom();
+      const sig = await signMeterRecordEip191(testRecord, wallet);
+      const message = canonicalMeterRecord(testRecord);
+
+      expect(sig).toMatch(/^0x[0-9a-f]{130}$/i);
+      expect(ethers.verifyMessage(message, sig)).toBe(wallet.address);
+    });
+
+    it('should sign records as EIP-712 typed data', async () => {
+      const wallet = ethers.Wallet.createRandom();
+      const sig = await signMeterRecordEip712(testRecord, wallet);
+      const value = meterRecordTypedData(testRecord);
+
+      expect(value.kwhDeltaMicro).toBe(1_234_000);
+      expect(ethers.verifyTypedData(METER_EIP712_DOMAIN, METER_EIP712_TYPES, value, sig)).toBe(wallet.address);
+    });
+
+    it('should reject negative energy for EIP-712', () => {
+      expect(() => meterRecordTypedData({ ...testRecord, kwh_delta: -1 })).toThrow();
+    });
+  });
+
   describe('Error Handling', () => {
     it('should throw error for invalid private key in signing', () => {
       const invalidKey = 'invalid_key';
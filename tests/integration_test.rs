@@ -1,7 +1,5 @@
 // FILE: tests/integration_test.rs
 use actix_web::{test, web, App};
-use actix_rt;
-use serde_json::json;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -10,6 +8,11 @@ use rofl_enclave::config::Config;
 use rofl_enclave::models::*;
 use rofl_enclave::handlers::*;
 use rofl_enclave::aggregator::DataAggregator;
+use rofl_enclave::ingest::VerifierPool;
+use rofl_enclave::registry::MeterRegistry;
+use rofl_enclave::crypto::CryptoService;
+use rofl_enclave::signer::EnclaveSigner;
+use sha3::{Digest, Keccak256};
 
 #[actix_rt::test]
 async fn test_health_check() {
@@ -37,11 +40,13 @@ async fn test_health_check() {
 async fn test_ingest_meter_data() {
     let config = create_test_config();
     let aggregator = Arc::new(Mutex::new(DataAggregator::new(config.clone())));
+    let verifier = VerifierPool::from_config(&config, MeterRegistry::default());
     
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(config))
             .app_data(web::Data::new(aggregator))
+            .app_data(web::Data::new(verifier))
             .route("/ingest", web::post().to(ingest_data))
     ).await;
 
@@ -65,11 +70,13 @@ async fn test_ingest_meter_data() {
 async fn test_invalid_signature_rejection() {
     let config = create_test_config();
     let aggregator = Arc::new(Mutex::new(DataAggregator::new(config.clone())));
+    let verifier = VerifierPool::from_config(&config, MeterRegistry::default());
     
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(config))
             .app_data(web::Data::new(aggregator))
+            .app_data(web::Data::new(verifier))
             .route("/ingest", web::post().to(ingest_data))
     ).await;
 
@@ -83,7 +90,7 @@ async fn test_invalid_signature_rejection() {
         .to_request();
     
     let resp = test::call_service(&app, req).await;
-    assert_eq!(resp.status(), 400); // Bad request due to invalid signature
+    assert_eq!(resp.status(), 401); // Unauthorized due to invalid signature
 }
 
 #[actix_rt::test]
@@ -115,20 +122,23 @@ async fn test_proof_generation_after_aggregation() {
     config.agg_window_sec = 1; // 1 second window for testing
     
     let aggregator = Arc::new(Mutex::new(DataAggregator::new(config.clone())));
+    let verifier = VerifierPool::from_config(&config, MeterRegistry::default());
     
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(config))
             .app_data(web::Data::new(aggregator.clone()))
+            .app_data(web::Data::new(verifier))
             .route("/ingest", web::post().to(ingest_data))
             .route("/proofs/latest", web::get().to(get_latest_proof))
     ).await;
 
     // Ingest multiple meter readings
     for i in 0..5 {
-        let mut meter_data = create_test_signed_meter_data();
-        meter_data.record.meter_id = format!("meter_{}", i);
-        meter_data.record.kwh_delta = 1.0 + (i as f64 * 0.1);
+        let mut record = create_test_signed_meter_data().record;
+        record.meter_id = format!("meter_{}", i);
+        record.kwh_delta = 1.0 + (i as f64 * 0.1);
+        let meter_data = sign_meter_data(record);
         
         let req = test::TestRequest::post()
             .uri("/ingest")
@@ -159,18 +169,20 @@ async fn test_proof_generation_after_aggregation() {
 async fn test_replay_attack_protection() {
     let config = create_test_config();
     let aggregator = Arc::new(Mutex::new(DataAggregator::new(config.clone())));
+    let verifier = VerifierPool::from_config(&config, MeterRegistry::default());
     
     let app = test::init_service(
         App::new()
             .app_data(web::Data::new(config))
             .app_data(web::Data::new(aggregator))
+            .app_data(web::Data::new(verifier))
             .route("/ingest", web::post().to(ingest_data))
     ).await;
 
     let meter_data = create_test_signed_meter_data();
     
     // Send the same data twice
-    for _ in 0..2 {
+    for attempt in 0..2 {
         let req = test::TestRequest::post()
             .uri("/ingest")
             .set_json(&meter_data)
@@ -179,12 +191,13 @@ async fn test_replay_attack_protection() {
         let resp = test::call_service(&app, req).await;
         
         // First request should succeed, second should fail (replay protection)
-        if resp.status().is_success() {
+        if attempt == 0 {
+            assert!(resp.status().is_success());
             let body: IngestResponse = test::read_body_json(resp).await;
             assert!(body.success);
         } else {
-            // Second request should be rejected
-            assert_eq!(resp.status(), 400);
+            // Second request should be rejected as a duplicate
+            assert_eq!(resp.status(), 409);
         }
     }
 }
@@ -213,83 +226,89 @@ async fn test_seal_endpoint() {
     
     let resp = test::call_service(&app, req).await;
     
-    // Should return success even if no proofs available
-    assert!(resp.status().is_success());
+    // Nothing has been aggregated yet, so there is nothing to seal
+    assert_eq!(resp.status(), 404);
     
-    let body: SealResponse = test::read_body_json(resp).await;
-    // Will fail in test environment due to no actual Walrus connection
-    assert!(!body.success);
-    assert!(body.message.contains("No proofs available") || body.message.contains("Failed to seal"));
+    let body: ErrorResponse = test::read_body_json(resp).await;
+    assert_eq!(body.code, "NO_PROOFS");
+    assert!(body.error.contains("No proofs available"));
 }
 
 #[actix_rt::test]
 async fn test_concurrent_ingestion() {
-    let config = create_test_config();
-    let aggregator = Arc::new(Mutex::new(DataAggregator::new(config.clone())));
-    
-    let app = test::init_service(
-        App::new()
-            .app_data(web::Data::new(config))
-            .app_data(web::Data::new(aggregator))
-            .route("/ingest", web::post().to(ingest_data))
-    ).await;
-
-    // Create multiple concurrent requests
+    // Create multiple concurrent requests, each through its own service over the shared state
+    let config = web::Data::new(create_test_config());
+    let aggregator = web::Data::new(Arc::new(Mutex::new(DataAggregator::new(config.get_ref().clone()))));
+    let verifier = web::Data::new(VerifierPool::from_config(&config, MeterRegistry::default()));
     let mut handles = vec![];
     
     for i in 0..10 {
-        let app_clone = app.clone();
-        let handle = tokio::spawn(async move {
-            let mut meter_data = create_test_signed_meter_data();
-            meter_data.record.meter_id = format!("concurrent_meter_{}", i);
-            meter_data.record.nonce = format!("nonce_{}", i);
+        let (config, aggregator, verifier) = (config.clone(), aggregator.clone(), verifier.clone());
+        let handle = actix_rt::spawn(async move {
+            let app = test::init_service(
+                App::new()
+                    .app_data(config)
+                    .app_data(aggregator)
+                    .app_data(verifier)
+                    .route("/ingest", web::post().to(ingest_data))
+            ).await;
+
+            let mut record = create_test_signed_meter_data().record;
+            record.meter_id = format!("concurrent_meter_{}", i);
+            record.nonce = format!("{:032x}", i);
+            let meter_data = sign_meter_data(record);
             
             let req = test::TestRequest::post()
                 .uri("/ingest")
                 .set_json(&meter_data)
                 .to_request();
             
-            test::call_service(&app_clone, req).await
+            test::call_service(&app, req).await.status()
         });
         handles.push(handle);
     }
 
     // Wait for all requests to complete
     for handle in handles {
-        let resp = handle.await.unwrap();
-        assert!(resp.status().is_success());
+        let status = handle.await.unwrap();
+        assert!(status.is_success());
     }
 }
 
 // Helper functions
 
+/// Defaults with a short-lived output directory of its own, so tests never share stored proofs
 fn create_test_config() -> Config {
     Config {
         host: "127.0.0.1".to_string(),
-        port: 8080,
         agg_window_sec: 300,
-        max_records_per_window: 1000,
-        output_dir: "/tmp/test_proofs".to_string(),
-        enable_signature_verification: true,
-        walrus_publisher_url: "https://publisher-devnet.walrus.space".to_string(),
-        walrus_gateway_url: "https://aggregator-devnet.walrus.space".to_string(),
-        walrus_epochs: 5,
+        output_dir: std::env::temp_dir()
+            .join(format!("rofl_test_proofs_{}", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned(),
+        ..Config::default()
     }
 }
 
 fn create_test_signed_meter_data() -> SignedMeterData {
-    let record = MeterRecord {
+    sign_meter_data(MeterRecord {
         meter_id: "test_meter_001".to_string(),
-        timestamp: chrono::Utc::now().timestamp(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
         kwh_delta: 1.234,
-        nonce: "test_nonce_123".to_string(),
-    };
+        nonce: format!("{:032x}", 0x7e57_0123),
+        encoding: RecordEncoding::V1,
+    })
+}
 
-    // Create a mock signature (in real implementation, this would be a valid ECDSA signature)
-    let mock_signature = "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef12";
+/// Sign with a key of the meter's own, derived from its id
+fn sign_meter_data(record: MeterRecord) -> SignedMeterData {
+    let meter = EnclaveSigner::from_hex(&hex::encode(Keccak256::digest(record.meter_id.as_bytes()))).unwrap();
+    let digest = CryptoService::new().meter_signing_digest(&record, SignatureScheme::Legacy).unwrap();
 
     SignedMeterData {
+        sig: format!("0x{}", hex::encode(meter.sign_digest(&digest))),
         record,
-        sig: mock_signature.to_string(),
+        scheme: SignatureScheme::Legacy,
+        certificate: None,
     }
-}
\ No newline at end of file
+}